@@ -0,0 +1,78 @@
+use crate::misc::shellcraft::Arch;
+use anyhow::{anyhow, Result};
+use capstone::prelude::*;
+
+/// disassemble code that is located at addr into lines of the form "0x401000: pop rdi"
+pub fn disassemble(
+    code: &[u8],
+    addr: u64,
+    target: Arch,
+    little_endian: bool,
+) -> Result<Vec<String>> {
+    let endian = if little_endian {
+        capstone::Endian::Little
+    } else {
+        capstone::Endian::Big
+    };
+
+    let cs = match target {
+        Arch::X86 => Capstone::new()
+            .x86()
+            .mode(arch::x86::ArchMode::Mode32)
+            .build(),
+        Arch::Amd64 => Capstone::new()
+            .x86()
+            .mode(arch::x86::ArchMode::Mode64)
+            .build(),
+        Arch::Arm => Capstone::new()
+            .arm()
+            .mode(arch::arm::ArchMode::Arm)
+            .endian(endian)
+            .build(),
+        Arch::Aarch64 => Capstone::new()
+            .arm64()
+            .mode(arch::arm64::ArchMode::Arm)
+            .endian(endian)
+            .build(),
+    }
+    .map_err(|e| anyhow!("Failed to create disassembler: {}", e))?;
+
+    let instructions = cs
+        .disasm_all(code, addr)
+        .map_err(|e| anyhow!("Failed to disassemble: {}", e))?;
+    Ok(instructions
+        .iter()
+        .map(|insn| {
+            format!(
+                "{:#x}: {} {}",
+                insn.address(),
+                insn.mnemonic().unwrap_or_default(),
+                insn.op_str().unwrap_or_default()
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::misc::shellcraft::sh;
+
+    #[test]
+    fn test_disassemble() {
+        assert_eq!(
+            disassemble(b"\x5f\xc3", 0x401000, Arch::Amd64, true).unwrap(),
+            vec!["0x401000: pop rdi", "0x401001: ret"]
+        );
+        assert_eq!(
+            disassemble(b"\x31\xc0\xcd\x80", 0x8048000, Arch::X86, true).unwrap(),
+            vec!["0x8048000: xor eax, eax", "0x8048002: int 0x80"]
+        );
+
+        let arm = disassemble(&sh(Arch::Aarch64)[..20], 0, Arch::Aarch64, true).unwrap();
+        assert_eq!(arm.len(), 5);
+        assert_eq!(arm[4], "0x10: svc #0");
+    }
+}
@@ -2,25 +2,63 @@ use anyhow::{anyhow, bail, Context, Result};
 use goblin::{
     container::Ctx,
     elf::{
-        dynamic::DT_PLTGOT,
-        header::{EM_386, EM_AARCH64, EM_ARM, EM_X86_64},
-        program_header::PT_INTERP,
+        dynamic::{DF_1_NOW, DF_BIND_NOW, DT_BIND_NOW, DT_FLAGS, DT_FLAGS_1, DT_PLTGOT},
+        header::{EM_386, EM_AARCH64, EM_ARM, EM_X86_64, ET_DYN},
+        program_header::{PF_X, PT_GNU_RELRO, PT_GNU_STACK, PT_INTERP, PT_LOAD},
         reloc::RelocSection,
-        section_header::{SectionHeader, SHN_UNDEF, SHT_DYNSYM, SHT_REL, SHT_RELA, SHT_SYMTAB},
-        sym::Symtab,
+        section_header::{
+            SectionHeader, SHF_ALLOC, SHF_EXECINSTR, SHN_UNDEF, SHT_DYNSYM, SHT_GNU_VERDEF,
+            SHT_GNU_VERSYM, SHT_NOBITS, SHT_REL, SHT_RELA, SHT_SYMTAB,
+        },
+        sym::{Symtab, STT_FUNC, STT_NOTYPE},
+        Elf,
     },
     strtab::Strtab,
     Object,
 };
+use std::collections::HashMap;
 use std::fs;
-use std::rc::Rc;
-use std::{cell::Cell, collections::HashMap};
+#[cfg(feature = "unicorn")]
+use std::{cell::Cell, rc::Rc};
+#[cfg(feature = "unicorn")]
 use unicorn::{
     unicorn_const::{Arch, Mode, Permission, SECOND_SCALE},
     RegisterX86,
 };
 
-use super::Binary;
+use crate::misc::packing::Endian;
+
+use super::{
+    find_all, lookup_sorted_symbols, one_gadget, plt, printable_strings, sort_symbols, Binary,
+    Mitigations, Relro,
+};
+#[cfg(feature = "capstone")]
+use {super::disasm, crate::misc::shellcraft};
+
+/// byte order of an elf from EI_DATA of its identification bytes
+pub fn ident_endian(ident: &[u8]) -> Option<Endian> {
+    if !ident.starts_with(b"\x7fELF") {
+        return None;
+    }
+    match ident.get(5) {
+        Some(1) => Some(Endian::Little),
+        Some(2) => Some(Endian::Big),
+        _ => None,
+    }
+}
+
+/// flag of the version definition that names the file itself
+const VER_FLG_BASE: u16 = 0x1;
+/// parts of a version symbol table entry
+const VERSYM_HIDDEN: u16 = 0x8000;
+const VERSYM_VERSION: u16 = 0x7fff;
+
+/// maximum number of instructions emulated per plt stub, a stub resolves its target in a few
+/// instructions so anything longer is not a stub
+pub const PLT_STUB_MAX_INSTRUCTIONS: usize = 16;
+/// maximum time in microseconds spent emulating a single plt stub
+#[cfg(feature = "unicorn")]
+const PLT_STUB_TIMEOUT: u64 = SECOND_SCALE / 10;
 
 #[derive(Debug)]
 pub struct ELFBinary {
@@ -30,8 +68,10 @@ pub struct ELFBinary {
     pub got: HashMap<String, u64>,
     /// procedure linkage table
     pub plt: HashMap<String, u64>,
-    /// symbol name to address map
+    /// symbol name to address map, .symtab entries take precedence over .dynsym ones
     pub symbols: HashMap<String, u64>,
+    /// (address, symbol name) sorted by address for reverse lookups
+    sorted_symbols: Vec<(u64, String)>,
 }
 
 impl ELFBinary {
@@ -45,12 +85,14 @@ impl ELFBinary {
         let plt = Self::parse_plt(&raw_bytes, &got).context("Failed to populate plt")?;
         let symbols =
             Self::parse_symbols(&raw_bytes, &plt, &got).context("Failed to populate symbols")?;
+        let sorted_symbols = sort_symbols(&symbols);
 
         Ok(ELFBinary {
             raw_bytes,
             got,
             plt,
             symbols,
+            sorted_symbols,
         })
     }
 
@@ -176,12 +218,68 @@ impl ELFBinary {
         Ok(got_symbols)
     }
 
-    /// emulate instructions in a plt section and trace memory accesses
+    /// start addresses of arm code (false) and thumb code (true) in a 32-bit arm binary, sorted by
+    /// address. taken from the $a/$t/$d mapping symbols and thumb function symbols (lowest bit set)
+    #[cfg(feature = "unicorn")]
+    fn arm_code_modes(elf: &Elf) -> Vec<(u64, bool)> {
+        let mut modes = vec![];
+        for sym in elf.syms.iter() {
+            let name = elf.strtab.get_at(sym.st_name).unwrap_or("");
+            // mapping symbols may carry a suffix such as $t.0
+            let kind = name.split('.').next().unwrap_or("");
+            if sym.st_type() == STT_NOTYPE && matches!(kind, "$a" | "$t" | "$d") {
+                // data is not executed anyway, treat it like arm code
+                modes.push((sym.st_value, kind == "$t"));
+            } else if sym.st_type() == STT_FUNC && sym.st_value & 1 == 1 {
+                modes.push((sym.st_value & !1, true));
+            }
+        }
+        modes.sort_unstable();
+        modes
+    }
+
+    /// whether address lies in thumb code according to the modes of arm_code_modes
+    #[cfg(feature = "unicorn")]
+    fn is_thumb(modes: &[(u64, bool)], address: u64) -> bool {
+        modes
+            .iter()
+            .take_while(|(start, _)| *start <= address)
+            .last()
+            .map_or(false, |(_, thumb)| *thumb)
+    }
+
+    /// offsets of the stubs in a plt section derived from the jump slot relocations, None if the
+    /// layout of the section is unknown
+    fn plt_stub_offsets(elf: &Elf, section_name: &str, section_size: u64) -> Option<Vec<u64>> {
+        // (header size, stub size) of lazy binding plt sections
+        let (header, stub) = match (section_name, elf.header.e_machine) {
+            (".plt", EM_386) | (".plt", EM_X86_64) => (16, 16),
+            (".plt", EM_ARM) => (20, 12),
+            (".plt", EM_AARCH64) => (32, 16),
+            // .plt.sec holds the stubs of the .plt without its header
+            (".plt.sec", EM_386) | (".plt.sec", EM_X86_64) => (0, 16),
+            _ => return None,
+        };
+
+        // only trust the layout if it covers the whole section
+        let count = elf.pltrelocs.len() as u64;
+        if count == 0 || header + count * stub != section_size {
+            return None;
+        }
+        Some((0..count).map(|i| header + i * stub).collect())
+    }
+
+    /// emulate instructions in a plt section and trace memory accesses. the stubs are started at
+    /// stub_offsets if given, otherwise at every possible offset. each stub is emulated for at most
+    /// max_instructions instructions
+    #[cfg(feature = "unicorn")]
     fn emulate_plt_instructions(
         raw_data: &[u8],
         got: u64,
         plt_section_address: u64,
         plt_section_data: &[u8],
+        stub_offsets: Option<Vec<u64>>,
+        max_instructions: usize,
     ) -> Result<Vec<(u64, u64)>> {
         // parse in raw bytes as ELF binary
         let elf = match Object::parse(raw_data).context("Failed to parse raw data")? {
@@ -275,8 +373,32 @@ impl ELFBinary {
         // (plt, got) vector, where plt is the address of the plt stub and got the address which the stub resolves/calls
         let mut plt_got_addresses: Vec<(u64, u64)> = vec![];
 
-        // assumption is that each plt stub is 4-byte aligned
-        for begin in 0..(plt_section_data.len() as u64 / 4) {
+        // thumb code of 32-bit arm binaries has to be started in thumb mode
+        let modes = if elf.header.e_machine == EM_ARM && !elf.is_64 {
+            Self::arm_code_modes(&elf)
+        } else {
+            vec![]
+        };
+
+        // without known stub offsets the assumption is that each plt stub is 4-byte aligned, thumb
+        // stubs are 2-byte aligned
+        let stub_offsets = stub_offsets.unwrap_or_else(|| {
+            let step = if modes.iter().any(|(_, thumb)| *thumb) {
+                2
+            } else {
+                4
+            };
+            (0..plt_section_data.len() as u64 / step * step)
+                .step_by(step as usize)
+                .collect()
+        });
+        for offset in stub_offsets {
+            let starting_address = plt_section_address + offset;
+            let thumb = Self::is_thumb(&modes, starting_address);
+            if !thumb && starting_address % 4 != 0 {
+                continue;
+            }
+
             // restore to the clean context and restore the faulting address
             emu.context_restore(&saved_ctx)
                 .map_err(|_err| anyhow!("Failed to restore context"))?;
@@ -288,13 +410,17 @@ impl ELFBinary {
                     .map_err(|_err| anyhow!("Failed to write to EBX"))?;
             }
 
-            // start the emulation
-            let starting_address = plt_section_address + begin * 4;
+            // start the emulation, unicorn switches to Mode::THUMB if the lowest bit of the start
+            // address is set
             let _ = emu.emu_start(
-                starting_address,
+                if thumb {
+                    starting_address | 1
+                } else {
+                    starting_address
+                },
                 mem_end,
-                1 * SECOND_SCALE,
-                (mem_end - mem_start) as usize,
+                PLT_STUB_TIMEOUT,
+                max_instructions,
             );
 
             // save the plt address where we started execution and the faulting memory access (maybe got entry)
@@ -320,21 +446,19 @@ impl ELFBinary {
         // symbol to plt map
         let mut plt_symbols = HashMap::new();
 
-        // if binary is statically linked, do not attempt to parse got
+        // if binary is statically linked, there are no plt stubs for imported functions
+        // library functions are linked in directly and resolved via .symtab in parse_symbols
         if elf.program_headers.iter().all(|x| x.p_type != PT_INTERP) {
             return Ok(plt_symbols);
         }
 
         // search all plt sections
-        let sections = [
-            Self::get_section_by_name(raw_data, ".plt").context("Failed to get section")?,
-            Self::get_section_by_name(raw_data, ".plt.got").context("Failed to get section")?,
-            Self::get_section_by_name(raw_data, ".plt.sec").context("Failed to get section")?,
-        ];
+        let section_names = [".plt", ".plt.got", ".plt.sec"];
 
         // got section address which might be required by plt code for 32-bit binaries
         let dt_pltgot = elf
             .dynamic
+            .as_ref()
             .context("Failed to get dynamic linking information")?
             .dyns
             .iter()
@@ -349,17 +473,45 @@ impl ELFBinary {
             .map(|x| (*x.1, x.0.to_owned()))
             .collect::<HashMap<_, _>>();
 
-        // try emulation for all possible plt sections
-        for section in sections.iter() {
-            if let Some(section) = section {
-                // get vector of all referenced addresses by possible plt entries
-                let plt_got_addresses = Self::emulate_plt_instructions(
-                    raw_data,
-                    dt_pltgot,
-                    section.sh_addr,
-                    &raw_data[section.sh_offset as usize
-                        ..section.sh_offset as usize + section.sh_size as usize],
-                )?;
+        // resolve the stubs of all possible plt sections
+        for name in section_names.iter() {
+            if let Some(section) =
+                Self::get_section_by_name(raw_data, name).context("Failed to get section")?
+            {
+                let section_data = &raw_data[section.sh_offset as usize
+                    ..section.sh_offset as usize + section.sh_size as usize];
+                let stub_offsets = Self::plt_stub_offsets(&elf, name, section.sh_size);
+
+                // the jumps of standard x86 stubs can be decoded without emulation
+                let plt_got_addresses = match elf.header.e_machine {
+                    EM_386 | EM_X86_64 => plt::decode_x86_plt(
+                        section.sh_addr,
+                        section_data,
+                        stub_offsets.clone(),
+                        dt_pltgot,
+                        elf.is_64,
+                    ),
+                    _ => vec![],
+                };
+
+                // get vector of all referenced addresses by possible plt entries by emulating
+                // sections without any recognized stub, without unicorn these stay unresolved
+                #[cfg(feature = "unicorn")]
+                let plt_got_addresses = if plt_got_addresses
+                    .iter()
+                    .any(|(_, got_addr)| got_targets.contains_key(got_addr))
+                {
+                    plt_got_addresses
+                } else {
+                    Self::emulate_plt_instructions(
+                        raw_data,
+                        dt_pltgot,
+                        section.sh_addr,
+                        section_data,
+                        stub_offsets,
+                        PLT_STUB_MAX_INSTRUCTIONS,
+                    )?
+                };
 
                 // now whenever a target got entry was referenced, assume that we found a valid plt entry
                 for (plt_addr, got_addr) in plt_got_addresses {
@@ -373,6 +525,97 @@ impl ELFBinary {
         Ok(plt_symbols)
     }
 
+    /// versions of the entries of the dynamic symbol table at section index dynsym as (name, hidden)
+    /// from the GNU version sections, None for local and unversioned entries. hidden entries are not
+    /// the default version of their name (name@version instead of name@@version)
+    fn parse_symbol_versions(
+        elf: &Elf,
+        raw_data: &[u8],
+        dynsym: usize,
+    ) -> Result<Vec<Option<(String, bool)>>> {
+        let bytes_at = |offset: usize, len: usize| {
+            raw_data
+                .get(offset..offset + len)
+                .context("Version data exceeds file size")
+        };
+        let u16_at = |offset: usize| -> Result<u16> {
+            let mut bytes = [0; 2];
+            bytes.copy_from_slice(bytes_at(offset, 2)?);
+            Ok(if elf.little_endian {
+                u16::from_le_bytes(bytes)
+            } else {
+                u16::from_be_bytes(bytes)
+            })
+        };
+        let u32_at = |offset: usize| -> Result<u32> {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(bytes_at(offset, 4)?);
+            Ok(if elf.little_endian {
+                u32::from_le_bytes(bytes)
+            } else {
+                u32::from_be_bytes(bytes)
+            })
+        };
+
+        let versym = match elf
+            .section_headers
+            .iter()
+            .find(|x| x.sh_type == SHT_GNU_VERSYM && x.sh_link as usize == dynsym)
+        {
+            Some(versym) => versym,
+            None => return Ok(vec![]),
+        };
+
+        // version index to name from the chained version definitions (Elf_Verdef and Elf_Verdaux)
+        let mut names = HashMap::new();
+        for verdef in elf
+            .section_headers
+            .iter()
+            .filter(|x| x.sh_type == SHT_GNU_VERDEF)
+        {
+            let string_section = elf
+                .section_headers
+                .get(verdef.sh_link as usize)
+                .context("Failed to get version string section")?;
+            let strings = Strtab::parse(
+                raw_data,
+                string_section.sh_offset as usize,
+                string_section.sh_size as usize,
+                b'\0',
+            )?;
+
+            let mut offset = verdef.sh_offset as usize;
+            for _ in 0..verdef.sh_info {
+                let flags = u16_at(offset + 2)?;
+                let index = u16_at(offset + 4)?;
+                let aux = u32_at(offset + 12)? as usize;
+                let name = u32_at(offset + aux)? as usize;
+
+                // the base definition names the file itself
+                if flags & VER_FLG_BASE == 0 {
+                    if let Some(Ok(name)) = strings.get(name) {
+                        names.insert(index, name.to_string());
+                    }
+                }
+
+                let next = u32_at(offset + 16)? as usize;
+                if next == 0 {
+                    break;
+                }
+                offset += next;
+            }
+        }
+
+        (0..versym.sh_size as usize / 2)
+            .map(|i| {
+                let version = u16_at(versym.sh_offset as usize + 2 * i)?;
+                Ok(names
+                    .get(&(version & VERSYM_VERSION))
+                    .map(|name| (name.clone(), version & VERSYM_HIDDEN != 0)))
+            })
+            .collect()
+    }
+
     /// parse all symbols of elf binary
     fn parse_symbols(
         raw_data: &[u8],
@@ -387,17 +630,22 @@ impl ELFBinary {
 
         let mut symbols = HashMap::new();
 
-        // first, populate all normal symbols (ignore symbols that have zero value)
-        for section in elf.section_headers.iter() {
-            // ignore non-symbol sections
-            // note that goblin is missing a type here: SHT_SUNW_LDYNSYM which is 0x6ffffff3
-            if section.sh_type != SHT_SYMTAB
-                && section.sh_type != SHT_DYNSYM
-                && section.sh_type != 0x6fff_fff3
-            {
-                continue;
-            }
+        // ignore non-symbol sections
+        // note that goblin is missing a type here: SHT_SUNW_LDYNSYM which is 0x6ffffff3
+        let mut symbol_sections: Vec<_> = elf
+            .section_headers
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| {
+                x.sh_type == SHT_SYMTAB || x.sh_type == SHT_DYNSYM || x.sh_type == 0x6fff_fff3
+            })
+            .collect();
+        // .symtab is the complete table (e.g. debug info of a stripped binary), so its entries take
+        // precedence over .dynsym ones regardless of the order of the sections in the file
+        symbol_sections.sort_by_key(|(_, x)| x.sh_type == SHT_SYMTAB);
 
+        // first, populate all normal symbols (ignore symbols that have zero value)
+        for (index, section) in symbol_sections {
             let symtab = Symtab::parse(
                 raw_data,
                 section.sh_offset as usize,
@@ -418,19 +666,28 @@ impl ELFBinary {
                 b'\0',
             )?;
 
-            symbols.extend(
-                symtab
-                    .iter()
-                    .filter(|x| x.st_value != 0)
-                    .map(|x| {
-                        let symbol = symbol_strings
-                            .get(x.st_name)
-                            .context("Strtab entry not found")?
-                            .context("Failed to get Strtab entry")?;
-                        Ok((symbol.to_string(), x.st_value))
-                    })
-                    .collect::<Result<HashMap<_, _>>>()?,
-            );
+            // versioned dynamic symbols are also added as name@version
+            let versions = if section.sh_type == SHT_DYNSYM {
+                Self::parse_symbol_versions(&elf, raw_data, index)?
+            } else {
+                vec![]
+            };
+
+            for (i, x) in symtab.iter().enumerate().filter(|(_, x)| x.st_value != 0) {
+                let symbol = symbol_strings
+                    .get(x.st_name)
+                    .context("Strtab entry not found")?
+                    .context("Failed to get Strtab entry")?;
+
+                if let Some(Some((version, hidden))) = versions.get(i) {
+                    symbols.insert(format!("{}@{}", symbol, version), x.st_value);
+                    // the bare name refers to the default version
+                    if *hidden && symbols.contains_key(symbol) {
+                        continue;
+                    }
+                }
+                symbols.insert(symbol.to_string(), x.st_value);
+            }
         }
 
         // process plt symbols
@@ -475,6 +732,251 @@ impl Binary for ELFBinary {
         let sym = self.symbols.get(sym).context("Symbol not found")?;
         Ok(*sym)
     }
+
+    /// given an address, retrieve the closest symbol at or below it and the offset
+    fn get_sym_at_addr(&self, addr: u64) -> Result<(String, u64)> {
+        lookup_sorted_symbols(&self.sorted_symbols, addr)
+    }
+
+    /// all symbols, see the symbols field
+    fn all_symbols(&self) -> &HashMap<String, u64> {
+        &self.symbols
+    }
+
+    /// inspect program headers, dynamic section and symbols for exploit mitigations
+    fn mitigations(&self) -> Result<Mitigations> {
+        // parse in raw bytes as ELF binary
+        let elf = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::Elf(elf) => elf,
+            _ => bail!("No valid ELF"),
+        };
+
+        // without a GNU_STACK header the loader falls back to an executable stack
+        let nx = elf
+            .program_headers
+            .iter()
+            .any(|x| x.p_type == PT_GNU_STACK && x.p_flags & PF_X == 0);
+
+        // position independent executables are of type ET_DYN
+        let pie = elf.header.e_type == ET_DYN;
+
+        // relro is only full if all relocations are resolved at startup
+        let relro = if elf.program_headers.iter().all(|x| x.p_type != PT_GNU_RELRO) {
+            Relro::None
+        } else if elf.dynamic.as_ref().map_or(false, |dynamic| {
+            dynamic.dyns.iter().any(|x| {
+                x.d_tag == DT_BIND_NOW
+                    || (x.d_tag == DT_FLAGS && x.d_val & DF_BIND_NOW != 0)
+                    || (x.d_tag == DT_FLAGS_1 && x.d_val & DF_1_NOW != 0)
+            })
+        }) {
+            Relro::Full
+        } else {
+            Relro::Partial
+        };
+
+        let canary = self.symbols.contains_key("__stack_chk_fail");
+
+        Ok(Mitigations {
+            nx,
+            pie,
+            relro,
+            canary,
+        })
+    }
+
+    /// scan executable sections for the gadget bytes and return their virtual addresses
+    fn find_gadget(&self, gadget: &[u8]) -> Result<Vec<u64>> {
+        // parse in raw bytes as ELF binary
+        let elf = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::Elf(elf) => elf,
+            _ => bail!("No valid ELF"),
+        };
+
+        // only x86 return instructions are known for now
+        match elf.header.e_machine {
+            EM_386 | EM_X86_64 => (),
+            _ => bail!("Gadget search is only supported for x86 binaries"),
+        };
+
+        // a gadget has to end in either ret (c3) or ret imm16 (c2 xx xx)
+        let ends_in_ret =
+            gadget.last() == Some(&0xc3) || (gadget.len() >= 3 && gadget[gadget.len() - 3] == 0xc2);
+        if !ends_in_ret {
+            bail!("Gadget has to end in a return instruction");
+        }
+
+        let mut addresses = vec![];
+        for section in elf.section_headers.iter() {
+            // ignore sections that are not executable or do not occupy space in the file
+            if section.sh_flags & SHF_EXECINSTR as u64 == 0 || section.sh_type == SHT_NOBITS {
+                continue;
+            }
+
+            let start = section.sh_offset as usize;
+            let data = self
+                .raw_bytes
+                .get(start..start + section.sh_size as usize)
+                .context("Section exceeds file size")?;
+
+            // translate every match back to the section's virtual address
+            addresses.extend(find_all(data, gadget, section.sh_addr));
+        }
+
+        Ok(addresses)
+    }
+
+    /// scan the allocated sections that are neither executable nor .bss-like
+    fn strings(&self, min_len: usize) -> Result<Vec<(u64, String)>> {
+        // parse in raw bytes as ELF binary
+        let elf = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::Elf(elf) => elf,
+            _ => bail!("No valid ELF"),
+        };
+
+        let mut strings = vec![];
+        for section in elf.section_headers.iter() {
+            if section.sh_flags & SHF_ALLOC as u64 == 0
+                || section.sh_flags & SHF_EXECINSTR as u64 != 0
+                || section.sh_type == SHT_NOBITS
+            {
+                continue;
+            }
+
+            let start = section.sh_offset as usize;
+            let data = self
+                .raw_bytes
+                .get(start..start + section.sh_size as usize)
+                .context("Section exceeds file size")?;
+            strings.extend(printable_strings(data, section.sh_addr, min_len));
+        }
+
+        Ok(strings)
+    }
+
+    fn endian(&self) -> Endian {
+        ident_endian(&self.raw_bytes).unwrap_or(Endian::Little)
+    }
+
+    /// search executable sections of x86_64 binaries for calls of execve with "/bin/sh"
+    fn one_gadgets(&self) -> Result<Vec<u64>> {
+        // parse in raw bytes as ELF binary
+        let elf = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::Elf(elf) => elf,
+            _ => bail!("No valid ELF"),
+        };
+
+        if elf.header.e_machine != EM_X86_64 {
+            bail!("One gadget search is only supported for x86_64 binaries");
+        }
+        let execve = self.get_sym_addr("execve").context("No execve symbol")?;
+
+        // virtual addresses of every "/bin/sh" string in the loaded segments
+        let binsh = self.find_bytes(b"/bin/sh\0")?;
+
+        let mut candidates = vec![];
+        for section in elf.section_headers.iter() {
+            if section.sh_flags & SHF_EXECINSTR as u64 == 0 || section.sh_type == SHT_NOBITS {
+                continue;
+            }
+
+            let start = section.sh_offset as usize;
+            let data = self
+                .raw_bytes
+                .get(start..start + section.sh_size as usize)
+                .context("Section exceeds file size")?;
+            candidates.extend(one_gadget::find_execve_binsh(
+                data,
+                section.sh_addr,
+                &binsh,
+                execve,
+            ));
+        }
+
+        Ok(candidates)
+    }
+
+    /// search the file backed part of the loadable segments, the zero initialized rest is skipped
+    fn find_bytes(&self, needle: &[u8]) -> Result<Vec<u64>> {
+        if needle.is_empty() {
+            bail!("Empty byte sequence");
+        }
+
+        // parse in raw bytes as ELF binary
+        let elf = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::Elf(elf) => elf,
+            _ => bail!("No valid ELF"),
+        };
+
+        let mut addresses = vec![];
+        for segment in elf.program_headers.iter().filter(|x| x.p_type == PT_LOAD) {
+            let start = segment.p_offset as usize;
+            let data = self
+                .raw_bytes
+                .get(start..start + segment.p_filesz as usize)
+                .context("Segment exceeds file size")?;
+            addresses.extend(find_all(data, needle, segment.p_vaddr));
+        }
+
+        Ok(addresses)
+    }
+
+    /// translate the virtual address to a file offset via the loadable segments and read from there
+    fn read_vaddr(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        // parse in raw bytes as ELF binary
+        let elf = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::Elf(elf) => elf,
+            _ => bail!("No valid ELF"),
+        };
+
+        // the whole range has to lie within a single loadable segment, ranges past the end of the
+        // address space are never mapped
+        let end = addr.checked_add(len as u64);
+        let segment = elf
+            .program_headers
+            .iter()
+            .find(|x| {
+                x.p_type == PT_LOAD
+                    && addr >= x.p_vaddr
+                    && end.map_or(false, |end| end <= x.p_vaddr.saturating_add(x.p_memsz))
+            })
+            .context(format!("Address {:#x} is not mapped by the binary", addr))?;
+
+        // memory past the file size of a segment (e.g. .bss) is zero initialized
+        let mut data = vec![0; len];
+        let start = addr - segment.p_vaddr;
+        if start < segment.p_filesz {
+            let file_len = (segment.p_filesz - start).min(len as u64) as usize;
+            let offset = (segment.p_offset + start) as usize;
+            data[..file_len].copy_from_slice(
+                self.raw_bytes
+                    .get(offset..offset + file_len)
+                    .context("Segment exceeds file size")?,
+            );
+        }
+
+        Ok(data)
+    }
+
+    /// disassemble with the architecture and endianness from the elf header
+    #[cfg(feature = "capstone")]
+    fn disassemble(&self, addr: u64, len: usize) -> Result<Vec<String>> {
+        // parse in raw bytes as ELF binary
+        let elf = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::Elf(elf) => elf,
+            _ => bail!("No valid ELF"),
+        };
+
+        let target = match elf.header.e_machine {
+            EM_386 => shellcraft::Arch::X86,
+            EM_X86_64 => shellcraft::Arch::Amd64,
+            EM_ARM => shellcraft::Arch::Arm,
+            EM_AARCH64 => shellcraft::Arch::Aarch64,
+            _ => bail!("Disassembly is not supported for this architecture"),
+        };
+        let code = self.read_vaddr(addr, len)?;
+        disasm::disassemble(&code, addr, target, elf.little_endian)
+    }
 }
 
 #[cfg(test)]
@@ -547,12 +1049,23 @@ mod tests {
         assert_eq!(*bin.plt.get("memalign").unwrap(), 0x18580);
         assert!(bin.plt.get("nonexistingentry").is_none());
 
-        // arm
-        let bin = ELFBinary::new("test_data/bin_arm32").unwrap();
-        assert_eq!(*bin.plt.get("puts").unwrap(), 0x102dc);
-        assert_eq!(*bin.plt.get("__libc_start_main").unwrap(), 0x102e8);
-        assert_eq!(*bin.plt.get("abort").unwrap(), 0x10300);
-        assert!(bin.plt.get("nonexistingentry").is_none());
+        // arm (plt stubs are emulated)
+        #[cfg(feature = "unicorn")]
+        {
+            let bin = ELFBinary::new("test_data/bin_arm32").unwrap();
+            assert_eq!(*bin.plt.get("puts").unwrap(), 0x102dc);
+            assert_eq!(*bin.plt.get("__libc_start_main").unwrap(), 0x102e8);
+            assert_eq!(*bin.plt.get("abort").unwrap(), 0x10300);
+            assert!(bin.plt.get("nonexistingentry").is_none());
+
+            // thumb (built for a thumb-only core, so the plt stubs are thumb-2 code marked by $t
+            // mapping symbols, see test_data/src/build.sh)
+            let bin = ELFBinary::new("test_data/bin_thumb32").unwrap();
+            assert_eq!(*bin.plt.get("puts").unwrap(), 0x202a0);
+            assert_eq!(*bin.plt.get("__libc_start_main").unwrap(), 0x20280);
+            assert_eq!(*bin.plt.get("abort").unwrap(), 0x20290);
+            assert!(bin.plt.get("nonexistingentry").is_none());
+        }
 
         // start 64-bit tests
 
@@ -568,11 +1081,84 @@ mod tests {
         assert_eq!(*bin.plt.get("__tunable_get_val").unwrap(), 0x210f0);
         assert!(bin.plt.get("nonexistingentry").is_none());
 
-        // arm
-        let bin = ELFBinary::new("test_data/bin_arm64").unwrap();
-        assert_eq!(*bin.plt.get("puts").unwrap(), 0x610);
-        assert_eq!(*bin.plt.get("__libc_start_main").unwrap(), 0x5e0);
-        assert_eq!(*bin.plt.get("abort").unwrap(), 0x600);
+        // arm (plt stubs are emulated)
+        #[cfg(feature = "unicorn")]
+        {
+            let bin = ELFBinary::new("test_data/bin_arm64").unwrap();
+            assert_eq!(*bin.plt.get("puts").unwrap(), 0x610);
+            assert_eq!(*bin.plt.get("__libc_start_main").unwrap(), 0x5e0);
+            assert_eq!(*bin.plt.get("abort").unwrap(), 0x600);
+        }
+
+        // without unicorn the symbols and got of arm binaries are still available
+        #[cfg(not(feature = "unicorn"))]
+        {
+            let bin = ELFBinary::new("test_data/bin_arm32").unwrap();
+            assert!(bin.plt.is_empty());
+            assert_eq!(*bin.got.get("puts").unwrap(), 0x2100c);
+            assert_eq!(*bin.symbols.get("got.puts").unwrap(), 0x2100c);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "unicorn")]
+    fn test_arm_code_modes() {
+        let raw_data = fs::read("test_data/bin_thumb32").unwrap();
+        let elf = Elf::parse(&raw_data).unwrap();
+        let modes = ELFBinary::arm_code_modes(&elf);
+        // main and a plt stub are thumb code, the literal pools behind them are data
+        assert!(ELFBinary::is_thumb(&modes, 0x20248));
+        assert!(ELFBinary::is_thumb(&modes, 0x20280));
+        assert!(!ELFBinary::is_thumb(&modes, 0x20254));
+        assert!(!ELFBinary::is_thumb(&modes, 0x2026c));
+        assert!(!ELFBinary::is_thumb(&modes, 0x10228));
+        assert!(!ELFBinary::is_thumb(&[], 0x20280));
+    }
+
+    #[test]
+    fn test_plt_stub_offsets() {
+        let raw_data = fs::read("test_data/bin_arm32").unwrap();
+        let elf = Elf::parse(&raw_data).unwrap();
+        assert_eq!(
+            ELFBinary::plt_stub_offsets(&elf, ".plt", 0x44),
+            Some(vec![0x14, 0x20, 0x2c, 0x38])
+        );
+        // sizes that do not match the relocations fall back to brute force
+        assert_eq!(ELFBinary::plt_stub_offsets(&elf, ".plt", 0x48), None);
+        assert_eq!(ELFBinary::plt_stub_offsets(&elf, ".plt.got", 0x10), None);
+
+        let raw_data = fs::read("test_data/libc-2.27-64.so").unwrap();
+        let elf = Elf::parse(&raw_data).unwrap();
+        let offsets = ELFBinary::plt_stub_offsets(&elf, ".plt", 0x2f0).unwrap();
+        assert_eq!(offsets.len(), 46);
+        assert_eq!(offsets.last(), Some(&0x2e0));
+    }
+
+    #[test]
+    #[cfg(feature = "unicorn")]
+    fn test_plt_emulation_limits() {
+        // a large section of endless loops (jmp $) must not stall the emulation
+        let raw_data = fs::read("test_data/bin64").unwrap();
+        let section = [0xeb, 0xfe].repeat(0x8000);
+        let start = std::time::Instant::now();
+        let plt_got_addresses = ELFBinary::emulate_plt_instructions(
+            &raw_data,
+            0,
+            0x500,
+            &section,
+            None,
+            PLT_STUB_MAX_INSTRUCTIONS,
+        )
+        .unwrap();
+        assert!(plt_got_addresses.is_empty());
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+
+        // parsing the plt of libc finds no more entries than there are stubs (46 in .plt and two
+        // in .plt.got)
+        let start = std::time::Instant::now();
+        let bin = ELFBinary::new("test_data/libc-2.27-64.so").unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+        assert!(bin.plt.len() <= 46 + 2);
     }
 
     #[test]
@@ -594,6 +1180,9 @@ mod tests {
         assert_eq!(*bin.symbols.get("random").unwrap(), 0x31080);
         assert_eq!(*bin.symbols.get("plt.___tls_get_addr").unwrap(), 0x185f0);
         assert!(bin.symbols.get("nonexistingentry").is_none());
+        assert_eq!(*bin.symbols.get("fopen").unwrap(), 0x663d0);
+        assert_eq!(*bin.symbols.get("fopen@GLIBC_2.1").unwrap(), 0x663d0);
+        assert_eq!(*bin.symbols.get("fopen@GLIBC_2.0").unwrap(), 0x1374b0);
 
         // arm
         let bin = ELFBinary::new("test_data/bin_arm32").unwrap();
@@ -617,6 +1206,14 @@ mod tests {
         assert_eq!(*bin.symbols.get("got.stdout").unwrap(), 0x3eaf40);
         assert!(bin.symbols.get("nonexistingentry").is_none());
 
+        // every version of a symbol is kept, the bare name is the default version
+        assert_eq!(*bin.symbols.get("memcpy").unwrap(), 0x9efe0);
+        assert_eq!(*bin.symbols.get("memcpy@GLIBC_2.14").unwrap(), 0x9efe0);
+        assert_eq!(*bin.symbols.get("memcpy@GLIBC_2.2.5").unwrap(), 0xbb240);
+        assert_eq!(*bin.symbols.get("realpath").unwrap(), 0x4f580);
+        assert_eq!(*bin.symbols.get("realpath@GLIBC_2.2.5").unwrap(), 0x1673f0);
+        assert!(bin.symbols.get("memcpy@GLIBC_2.0").is_none());
+
         // arm
         let bin = ELFBinary::new("test_data/bin_arm64").unwrap();
         assert_eq!(*bin.symbols.get("__init_array_end").unwrap(), 0x10d88);
@@ -624,4 +1221,208 @@ mod tests {
         assert_eq!(*bin.symbols.get("got.abort").unwrap(), 0x10fb0);
         assert!(bin.symbols.get("nonexistingentry").is_none());
     }
+
+    #[test]
+    fn test_symbol_precedence() {
+        // _IO_stdin_used is in .dynsym (section 5) and .symtab (section 26) of bin32
+        let mut raw_data = fs::read("test_data/bin32").unwrap();
+        let no_plt = HashMap::new();
+        let symbols = ELFBinary::parse_symbols(&raw_data, &no_plt, &no_plt).unwrap();
+        assert_eq!(symbols["_IO_stdin_used"], 0x5dc);
+
+        // give the .dynsym entry (16 bytes each at 0x1cc, st_value at +4) another address
+        let entry = (0..8)
+            .map(|i| 0x1cc + 16 * i)
+            .find(|entry| raw_data[entry + 4..entry + 8] == 0x5dcu32.to_le_bytes())
+            .unwrap();
+        raw_data[entry + 4..entry + 8].copy_from_slice(&0x1234u32.to_le_bytes());
+        let symbols = ELFBinary::parse_symbols(&raw_data, &no_plt, &no_plt).unwrap();
+        assert_eq!(symbols["_IO_stdin_used"], 0x5dc);
+
+        // swapping the section headers (40 bytes each at e_shoff) does not change the result
+        let shoff = u32::from_le_bytes([
+            raw_data[0x20],
+            raw_data[0x21],
+            raw_data[0x22],
+            raw_data[0x23],
+        ]) as usize;
+        let (dynsym, symtab) = (shoff + 5 * 40, shoff + 26 * 40);
+        let dynsym_header = raw_data[dynsym..dynsym + 40].to_vec();
+        raw_data.copy_within(symtab..symtab + 40, dynsym);
+        raw_data[symtab..symtab + 40].copy_from_slice(&dynsym_header);
+        let symbols = ELFBinary::parse_symbols(&raw_data, &no_plt, &no_plt).unwrap();
+        assert_eq!(symbols["_IO_stdin_used"], 0x5dc);
+    }
+
+    #[test]
+    fn test_static_binary() {
+        // statically linked x86-64 binary calling puts and system, built by test_data/src/build.sh
+        let bin = ELFBinary::new("test_data/bin64_static").unwrap();
+
+        // there is no dynamic linking, so got and plt stay empty
+        assert!(bin.got.is_empty());
+        assert!(bin.plt.is_empty());
+
+        // libc functions are linked in and resolved through .symtab
+        assert_eq!(bin.get_sym_addr("main").unwrap(), 0x401655);
+        assert_eq!(bin.get_sym_addr("system").unwrap(), 0x40a110);
+        assert_eq!(bin.get_sym_addr("__libc_system").unwrap(), 0x40a110);
+        assert_eq!(bin.get_sym_addr("puts").unwrap(), 0x40ad10);
+        assert_eq!(bin.get_sym_addr("exit").unwrap(), 0x408b80);
+        assert!(bin.get_sym_addr("nonexistingentry").is_err());
+    }
+
+    #[test]
+    fn test_sym_at_addr() {
+        let bin = ELFBinary::new("test_data/bin64_static").unwrap();
+
+        // exact symbol addresses have an offset of zero, aliases resolve to the shortest name
+        assert_eq!(
+            bin.get_sym_at_addr(0x401655).unwrap(),
+            ("main".to_string(), 0)
+        );
+        assert_eq!(
+            bin.get_sym_at_addr(0x40a110).unwrap(),
+            ("system".to_string(), 0)
+        );
+
+        // addresses inside a function resolve to the function and the delta
+        assert_eq!(
+            bin.get_sym_at_addr(0x40a110 + 0x21).unwrap(),
+            ("system".to_string(), 0x21)
+        );
+    }
+
+    #[test]
+    fn test_find_gadget() {
+        // pop rdi; ret
+        let bin = ELFBinary::new("test_data/bin64").unwrap();
+        assert_eq!(bin.find_gadget(&[0x5f, 0xc3]).unwrap(), vec![0x6b3]);
+
+        let bin = ELFBinary::new("test_data/bin64_static").unwrap();
+        let gadgets = bin.find_gadget(&[0x5f, 0xc3]).unwrap();
+        assert_eq!(gadgets.len(), 166);
+        assert_eq!(gadgets[..3], [0x401cb0, 0x4022aa, 0x402532]);
+
+        // patterns without a trailing return are rejected
+        assert!(bin.find_gadget(&[0x5f]).is_err());
+
+        // arm is not supported yet
+        let bin = ELFBinary::new("test_data/bin_arm64").unwrap();
+        assert!(bin.find_gadget(&[0xc3]).is_err());
+    }
+
+    #[test]
+    fn test_strings() {
+        let bin = ELFBinary::new("test_data/libc-2.27-64.so").unwrap();
+        let strings = bin.strings(4).unwrap();
+        assert!(strings.contains(&(0x1b3e1a, "/bin/sh".to_string())));
+        assert!(strings.iter().all(|(_, x)| x.len() >= 4));
+
+        // neither code ("AWAVI" in .text) nor unallocated sections (.comment) are scanned
+        let bin = ELFBinary::new("test_data/bin64").unwrap();
+        let strings = bin.strings(5).unwrap();
+        assert_eq!(
+            strings[0],
+            (0x238, "/lib64/ld-linux-x86-64.so.2".to_string())
+        );
+        assert!(strings
+            .iter()
+            .all(|(_, x)| x != "AWAVI" && !x.starts_with("GCC")));
+    }
+
+    #[test]
+    fn test_find_bytes() {
+        let bin = ELFBinary::new("test_data/libc-2.27-64.so").unwrap();
+        assert_eq!(bin.find_bytes(b"/bin/sh\0").unwrap(), vec![0x1b3e1a]);
+
+        // the data segment is mapped 0x200000 above its file offset, __dso_handle in .data points
+        // to itself and .rela.dyn relocates it. the copy in the unloaded .symtab is skipped
+        let bin = ELFBinary::new("test_data/bin64").unwrap();
+        assert_eq!(
+            bin.find_bytes(&0x201008u64.to_le_bytes()).unwrap(),
+            vec![0x440, 0x450, 0x201008]
+        );
+        assert_eq!(bin.find_bytes(b"\x7fELF").unwrap(), vec![0]);
+        assert!(bin.find_bytes(b"").is_err());
+    }
+
+    #[test]
+    fn test_endian() {
+        let bin = ELFBinary::new("test_data/bin_arm64").unwrap();
+        assert_eq!(bin.endian(), Endian::Little);
+
+        assert_eq!(ident_endian(b"\x7fELF\x01\x02"), Some(Endian::Big));
+        assert_eq!(ident_endian(b"\x7fELF\x02\x01\x01"), Some(Endian::Little));
+        assert_eq!(ident_endian(b"\x7fELF\x01\x00"), None);
+        assert_eq!(ident_endian(b"MZ\x90\x00\x03\x00"), None);
+    }
+
+    #[test]
+    fn test_one_gadgets() {
+        // one_gadget reports 0x4f432 and 0x10a41c, the others are inside execvpe
+        let bin = ELFBinary::new("test_data/libc-2.27-64.so").unwrap();
+        assert_eq!(
+            bin.one_gadgets().unwrap(),
+            vec![0x4f432, 0xe546f, 0xe5622, 0x10a41c]
+        );
+
+        // binaries without execve and other architectures
+        assert!(ELFBinary::new("test_data/bin64")
+            .unwrap()
+            .one_gadgets()
+            .is_err());
+        assert!(ELFBinary::new("test_data/libc-2.27-32.so")
+            .unwrap()
+            .one_gadgets()
+            .is_err());
+    }
+
+    #[test]
+    fn test_read_vaddr() {
+        // the first segment of a non-pie binary maps the elf header at 0x400000
+        let bin = ELFBinary::new("test_data/bin64_static").unwrap();
+        assert_eq!(bin.read_vaddr(0x400000, 4).unwrap(), b"\x7fELF");
+
+        // the bss part of the data segment is not backed by the file and reads as zeros
+        assert_eq!(bin.read_vaddr(0x4a9280, 8).unwrap(), vec![0; 8]);
+
+        // unmapped addresses and ranges leaving a segment fail
+        assert!(bin.read_vaddr(0x1000, 4).is_err());
+        assert!(bin.read_vaddr(0x400510, 0x10).is_err());
+        assert!(bin.read_vaddr(0xffff_ffff_ffff_fff0, 0x20).is_err());
+
+        // pie binaries start at 0
+        let bin = ELFBinary::new("test_data/bin64").unwrap();
+        assert_eq!(bin.read_vaddr(0, 4).unwrap(), b"\x7fELF");
+    }
+
+    #[test]
+    fn test_mitigations() {
+        let bin = ELFBinary::new("test_data/bin64").unwrap();
+        assert_eq!(
+            bin.mitigations().unwrap(),
+            Mitigations {
+                nx: true,
+                pie: true,
+                relro: Relro::Full,
+                canary: false,
+            }
+        );
+
+        let bin = ELFBinary::new("test_data/bin32").unwrap();
+        assert_eq!(bin.mitigations().unwrap().relro, Relro::Full);
+
+        // static glibc binaries link in the stack protector and are not position independent
+        let bin = ELFBinary::new("test_data/bin64_static").unwrap();
+        assert_eq!(
+            bin.mitigations().unwrap(),
+            Mitigations {
+                nx: true,
+                pie: false,
+                relro: Relro::Partial,
+                canary: true,
+            }
+        );
+    }
 }
@@ -0,0 +1,97 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+
+use super::{elf::ELFBinary, Binary};
+use crate::misc::packing::parse_number;
+
+/// the page offset of a symbol does not change with aslr
+const PAGE_MASK: u64 = 0xfff;
+
+/// whether every leaked symbol of the binary has the page offset of its leaked address
+fn matches_leaks(binary: &dyn Binary, leaks: &[(String, u64)]) -> bool {
+    leaks.iter().all(|(symbol, address)| {
+        binary
+            .get_sym_addr(symbol)
+            .map_or(false, |offset| offset & PAGE_MASK == address & PAGE_MASK)
+    })
+}
+
+/// file names of the shared objects in dir (every file with .so in its name) that match all leaked
+/// (symbol, address) pairs, sorted by name
+pub fn identify_libc(dir: &str, leaks: &[(String, u64)]) -> Result<Vec<String>> {
+    if leaks.is_empty() {
+        bail!("No leaked symbols given");
+    }
+
+    let mut matches = vec![];
+    for entry in fs::read_dir(dir).context(format!("Unable to read {}", dir))? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.contains(".so") => name.to_string(),
+            _ => continue,
+        };
+        // files that are no valid elf are skipped
+        let binary = match ELFBinary::new(&path.to_string_lossy()) {
+            Ok(binary) => binary,
+            Err(_) => continue,
+        };
+        if matches_leaks(&binary, leaks) {
+            matches.push(name);
+        }
+    }
+    matches.sort();
+    Ok(matches)
+}
+
+/// parse leaks of form puts=0x7f3c1a2b3aa0,printf=0x7f3c1a2a7f70
+pub fn parse_leaks(input: &str) -> Result<Vec<(String, u64)>> {
+    let mut leaks = vec![];
+    for leak in input.split(',').filter(|l| !l.trim().is_empty()) {
+        let (symbol, address) = leak
+            .split_once('=')
+            .context(format!("Missing address in leak {}", leak))?;
+        let address = parse_number(address).context("Unable to parse address")?;
+        leaks.push((symbol.trim().to_string(), address));
+    }
+    Ok(leaks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identify_libc() {
+        let leaks = parse_leaks("puts=0x7f1234580aa0, printf=0x7f1234564f70").unwrap();
+        assert_eq!(
+            identify_libc("test_data", &leaks).unwrap(),
+            vec!["libc-2.27-64.so"]
+        );
+
+        let leaks = parse_leaks("puts=0xf7e1eca0,system=0xf7df42e0").unwrap();
+        assert_eq!(
+            identify_libc("test_data", &leaks).unwrap(),
+            vec!["libc-2.27-32.so"]
+        );
+
+        // one mismatching symbol or an unknown one rules a libc out
+        let leaks = parse_leaks("puts=0x7f1234580aa0,printf=0x7f1234564f71").unwrap();
+        assert!(identify_libc("test_data", &leaks).unwrap().is_empty());
+        let leaks = parse_leaks("puts=0x7f1234580aa0,not_a_symbol=0").unwrap();
+        assert!(identify_libc("test_data", &leaks).unwrap().is_empty());
+
+        assert!(identify_libc("test_data", &[]).is_err());
+        assert!(identify_libc("missing_dir", &leaks).is_err());
+    }
+
+    #[test]
+    fn test_parse_leaks() {
+        assert_eq!(
+            parse_leaks(" puts = 0x10,printf=32,").unwrap(),
+            vec![("puts".to_string(), 0x10), ("printf".to_string(), 32)]
+        );
+        assert!(parse_leaks("puts").is_err());
+        assert!(parse_leaks("puts=x").is_err());
+    }
+}
@@ -1,45 +1,284 @@
 use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::misc::packing::Endian;
 
 /// trait that must be implemented for all kind of binary format handlers
 pub trait Binary {
     fn get_sym_addr(&self, sym: &str) -> Result<u64>;
+    /// get the closest symbol at or below addr and the offset of addr into it
+    fn get_sym_at_addr(&self, addr: u64) -> Result<(String, u64)>;
+    /// every symbol name the binary knows and its address, including plt./got. entries
+    fn all_symbols(&self) -> &HashMap<String, u64>;
+    /// report which exploit mitigations the binary was built with
+    fn mitigations(&self) -> Result<Mitigations> {
+        bail!("Mitigation report is not supported for this binary format")
+    }
+    /// find the addresses of a gadget (raw bytes ending in a return) in executable code
+    fn find_gadget(&self, _gadget: &[u8]) -> Result<Vec<u64>> {
+        bail!("Gadget search is not supported for this binary format")
+    }
+    /// printable ascii strings of at least min_len characters in the data sections and their
+    /// virtual addresses
+    fn strings(&self, _min_len: usize) -> Result<Vec<(u64, String)>> {
+        bail!("String extraction is not supported for this binary format")
+    }
+    /// virtual addresses of every occurrence of needle in the data the binary maps
+    fn find_bytes(&self, _needle: &[u8]) -> Result<Vec<u64>> {
+        bail!("Byte search is not supported for this binary format")
+    }
+    /// read len bytes the binary maps at the virtual address addr
+    fn read_vaddr(&self, addr: u64, len: usize) -> Result<Vec<u8>>;
+    /// byte order of the data of the binary
+    fn endian(&self) -> Endian {
+        Endian::Little
+    }
+    /// candidate one gadget offsets (execve("/bin/sh") call sites), their constraints are not checked
+    fn one_gadgets(&self) -> Result<Vec<u64>> {
+        bail!("One gadget search is not supported for this binary format")
+    }
+    /// disassemble len bytes at the virtual address addr into formatted instruction lines
+    fn disassemble(&self, _addr: u64, _len: usize) -> Result<Vec<String>> {
+        bail!("Disassembly is not supported for this binary format or the 'disasm' feature is not active")
+    }
 }
 
-#[cfg(feature = "unicorn")]
-mod elf;
+/// relocation read-only status
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relro {
+    None,
+    /// GNU_RELRO segment but lazy binding, the got stays writable
+    Partial,
+    /// GNU_RELRO segment with BIND_NOW, the got is read-only after startup
+    Full,
+}
 
-#[cfg(feature = "unicorn")]
-mod pe;
+/// exploit mitigations of a binary (checksec style)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mitigations {
+    /// non-executable stack
+    pub nx: bool,
+    /// position independent executable
+    pub pie: bool,
+    pub relro: Relro,
+    /// stack canaries (__stack_chk_fail is referenced)
+    pub canary: bool,
+}
 
-#[cfg(not(feature = "unicorn"))]
-mod without_unicorn {
-    use super::*;
-    pub fn from_path(path: &str) -> Result<Box<dyn Binary>> {
-        bail!("Activate the 'uni' feature to get access to binary parsing")
+impl fmt::Display for Mitigations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let relro = match self.relro {
+            Relro::None => "No RELRO",
+            Relro::Partial => "Partial RELRO",
+            Relro::Full => "Full RELRO",
+        };
+        writeln!(f, "RELRO:  {}", relro)?;
+        writeln!(
+            f,
+            "Stack:  {}",
+            if self.canary {
+                "Canary found"
+            } else {
+                "No canary found"
+            }
+        )?;
+        writeln!(
+            f,
+            "NX:     {}",
+            if self.nx { "NX enabled" } else { "NX disabled" }
+        )?;
+        write!(
+            f,
+            "PIE:    {}",
+            if self.pie { "PIE enabled" } else { "No PIE" }
+        )
     }
 }
 
-#[cfg(feature = "unicorn")]
-mod with_unicorn {
-    use super::*;
+/// runtime address of addr for a binary loaded at base (PIE binaries are linked at address 0)
+pub fn rebase(addr: u64, base: u64) -> u64 {
+    base.wrapping_add(addr)
+}
+
+/// build an address sorted view of a symbol map for reverse lookups
+/// if several symbols share an address, the shortest name wins (e.g. "puts" over "plt.puts")
+fn sort_symbols(symbols: &HashMap<String, u64>) -> Vec<(u64, String)> {
+    let mut sorted: Vec<(u64, String)> = symbols
+        .iter()
+        .map(|(name, addr)| (*addr, name.to_owned()))
+        .collect();
+    sorted.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(a.1.len().cmp(&b.1.len()))
+            .then(a.1.cmp(&b.1))
+    });
+    sorted.dedup_by_key(|x| x.0);
+    sorted
+}
+
+/// list all symbols sorted by address and name, unlike sort_symbols every name is kept
+pub fn symbol_list(symbols: &HashMap<String, u64>) -> Vec<(u64, String)> {
+    let mut list: Vec<(u64, String)> = symbols
+        .iter()
+        .map(|(name, addr)| (*addr, name.to_owned()))
+        .collect();
+    list.sort();
+    list
+}
 
-    pub use elf::ELFBinary;
-    pub use pe::PEBinary;
+/// entries of a symbol list whose name contains filter, ignoring case
+pub fn filter_symbols<'a>(
+    symbols: &'a [(u64, String)],
+    filter: &str,
+) -> impl Iterator<Item = &'a (u64, String)> {
+    let filter = filter.trim().to_lowercase();
+    symbols
+        .iter()
+        .filter(move |(_, name)| name.to_lowercase().contains(&filter))
+}
+
+/// addresses of every occurrence of needle in data, which starts at the virtual address address
+fn find_all(data: &[u8], needle: &[u8], address: u64) -> Vec<u64> {
+    if needle.is_empty() {
+        return vec![];
+    }
+    data.windows(needle.len())
+        .enumerate()
+        .filter(|(_, x)| *x == needle)
+        .map(|(idx, _)| address + idx as u64)
+        .collect()
+}
 
-    pub fn from_path(path: &str) -> Result<Box<dyn Binary>> {
-        if let Ok(pe) = PEBinary::new(path) {
-            return Ok(Box::new(pe));
+/// runs of at least min_len printable ascii characters (tabs included) in data, which starts at
+/// the virtual address address
+fn printable_strings(data: &[u8], address: u64, min_len: usize) -> Vec<(u64, String)> {
+    let mut strings = vec![];
+    let mut start = 0;
+    for (idx, byte) in data.iter().chain(&[0]).enumerate() {
+        if byte.is_ascii_graphic() || *byte == b' ' || *byte == b'\t' {
+            continue;
+        }
+        // empty runs never reach min_len, even if it is 0
+        if idx - start >= min_len.max(1) {
+            let string = String::from_utf8_lossy(&data[start..idx]).into_owned();
+            strings.push((address + start as u64, string));
         }
+        start = idx + 1;
+    }
+    strings
+}
+
+/// find the closest symbol at or below addr in an address sorted symbol list
+fn lookup_sorted_symbols(sorted: &[(u64, String)], addr: u64) -> Result<(String, u64)> {
+    let idx = sorted.partition_point(|x| x.0 <= addr);
+    if idx == 0 {
+        bail!("No symbol at or below address {:#x}", addr);
+    }
+    let (sym_addr, name) = &sorted[idx - 1];
+    Ok((name.to_owned(), addr - sym_addr))
+}
+
+#[cfg(feature = "capstone")]
+mod disasm;
+
+pub mod libc_db;
+pub mod one_gadget;
+
+mod plt;
+
+// without the unicorn feature only plt stubs that can be decoded statically are resolved
+mod elf;
+
+mod pe;
+
+use elf::ELFBinary;
+use pe::PEBinary;
 
-        let elf = ELFBinary::new(path);
-        Ok(Box::new(elf.context(
-            "Illegal binary type or running in network mode",
-        )?))
+/// byte order of an elf from its header (EI_DATA) without parsing the whole file, None for other files
+pub fn file_endian(path: &str) -> Option<Endian> {
+    // program names such as cat are looked up in PATH
+    let path = if Path::new(path).is_file() {
+        PathBuf::from(path)
+    } else {
+        which::which(path).ok()?
+    };
+    let mut ident = [0; 6];
+    File::open(path).ok()?.read_exact(&mut ident).ok()?;
+    elf::ident_endian(&ident)
+}
+
+pub fn from_path(path: &str) -> Result<Box<dyn Binary>> {
+    if let Ok(pe) = PEBinary::new(path) {
+        return Ok(Box::new(pe));
     }
+
+    let elf = ELFBinary::new(path);
+    Ok(Box::new(elf.context(
+        "Illegal binary type or running in network mode",
+    )?))
 }
 
-#[cfg(feature = "unicorn")]
-pub use with_unicorn::from_path;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_all() {
+        assert_eq!(
+            find_all(b"aXbaXaX", b"aX", 0x100),
+            vec![0x100, 0x103, 0x105]
+        );
+        assert_eq!(find_all(b"aaaa", b"aa", 0), vec![0, 1, 2]);
+        assert!(find_all(b"a", b"aa", 0).is_empty());
+        assert!(find_all(b"a", b"", 0).is_empty());
+    }
+
+    #[test]
+    fn test_printable_strings() {
+        let data = b"\x00/bin/sh\x00ab\x01hello world\tx\xffend";
+        assert_eq!(
+            printable_strings(data, 0x1000, 3),
+            vec![
+                (0x1001, "/bin/sh".to_string()),
+                (0x100c, "hello world\tx".to_string()),
+                (0x101a, "end".to_string()),
+            ]
+        );
+        assert_eq!(printable_strings(data, 0, 8).len(), 1);
+        assert_eq!(printable_strings(b"\x00\x00", 0, 0), vec![]);
+    }
+
+    #[test]
+    fn test_symbol_list() {
+        let symbols: HashMap<String, u64> = vec![
+            ("puts".to_string(), 0x1030),
+            ("plt.puts".to_string(), 0x1030),
+            ("got.puts".to_string(), 0x4018),
+            ("main".to_string(), 0x1139),
+        ]
+        .into_iter()
+        .collect();
 
-#[cfg(not(feature = "unicorn"))]
-pub use without_unicorn::from_path;
+        let list = symbol_list(&symbols);
+        assert_eq!(
+            list,
+            vec![
+                (0x1030, "plt.puts".to_string()),
+                (0x1030, "puts".to_string()),
+                (0x1139, "main".to_string()),
+                (0x4018, "got.puts".to_string()),
+            ]
+        );
+
+        let names: Vec<&str> = filter_symbols(&list, " PUTS")
+            .map(|(_, name)| name.as_str())
+            .collect();
+        assert_eq!(names, vec!["plt.puts", "puts", "got.puts"]);
+        assert_eq!(filter_symbols(&list, "").count(), 4);
+        assert_eq!(filter_symbols(&list, "printf").count(), 0);
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+use crate::misc::packing::parse_number;
+
+/// maximum distance between the lea of "/bin/sh" and the call of execve
+const MAX_CALL_DISTANCE: usize = 32;
+/// maximum distance of an environ load in front of the lea
+const MAX_SETUP_DISTANCE: usize = 16;
+
+fn rip_target(code: &[u8], pos: usize, address: u64) -> Option<u64> {
+    let disp = code.get(pos + 3..pos + 7)?;
+    let disp = i32::from_le_bytes([disp[0], disp[1], disp[2], disp[3]]);
+    Some((address + pos as u64 + 7).wrapping_add(disp as u64))
+}
+
+/// statically find x86_64 call sites of execve("/bin/sh", ..): a lea rdi, [rip+disp] of one of the
+/// binsh addresses followed by a call of execve. a candidate starts at a rip-relative mov (the
+/// environ load) shortly before the lea if there is one. the constraints on rsi and rdx are not checked
+pub fn find_execve_binsh(code: &[u8], address: u64, binsh: &[u64], execve: u64) -> Vec<u64> {
+    let mut candidates = vec![];
+    for pos in 0..code.len() {
+        if !code[pos..].starts_with(&[0x48, 0x8d, 0x3d]) {
+            continue;
+        }
+        match rip_target(code, pos, address) {
+            Some(target) if binsh.contains(&target) => (),
+            _ => continue,
+        }
+
+        // call rel32 of execve
+        let calls_execve = (pos + 7..pos + 7 + MAX_CALL_DISTANCE).any(|call| {
+            code.get(call) == Some(&0xe8)
+                && code.get(call + 1..call + 5).map_or(false, |rel| {
+                    let rel = i32::from_le_bytes([rel[0], rel[1], rel[2], rel[3]]);
+                    (address + call as u64 + 5).wrapping_add(rel as u64) == execve
+                })
+        });
+        if !calls_execve {
+            continue;
+        }
+
+        // mov r64, [rip+disp]
+        let setup = (7..=MAX_SETUP_DISTANCE.min(pos))
+            .rev()
+            .map(|k| pos - k)
+            .find(|&mov| {
+                code[mov] == 0x48 && code[mov + 1] == 0x8b && code[mov + 2] & 0xc7 == 0x05
+            });
+        candidates.push(address + setup.unwrap_or(pos) as u64);
+    }
+    candidates
+}
+
+/// offsets reported by the one_gadget tool, None if it is not installed
+pub fn one_gadget_tool(path: &str) -> Option<Result<Vec<u64>>> {
+    which::which("one_gadget").ok()?;
+    let run = || {
+        let output = Command::new("one_gadget")
+            .arg("--raw")
+            .arg(path)
+            .output()
+            .context("Unable to run one_gadget")?;
+        if !output.status.success() {
+            bail!(
+                "one_gadget failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .map(parse_number)
+            .collect()
+    };
+    Some(run())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_execve_binsh() {
+        // mov rax, [rip+0x10]; lea rdi, [rip+0x20]; mov rdx, [rax]; call execve
+        let code = [
+            &b"\x90\x90"[..],
+            b"\x48\x8b\x05\x10\x00\x00\x00",
+            b"\x48\x8d\x3d\x20\x00\x00\x00",
+            b"\x48\x8b\x10",
+            b"\xe8\x00\x01\x00\x00",
+        ]
+        .concat();
+        let binsh = 0x1000 + 16 + 0x20;
+        let execve = 0x1000 + 24 + 0x100;
+        assert_eq!(
+            find_execve_binsh(&code, 0x1000, &[binsh], execve),
+            vec![0x1002]
+        );
+
+        // other strings, other calls and a missing environ load
+        assert!(find_execve_binsh(&code, 0x1000, &[binsh + 1], execve).is_empty());
+        assert!(find_execve_binsh(&code, 0x1000, &[binsh], execve + 1).is_empty());
+        assert_eq!(
+            find_execve_binsh(&code[9..], 0x1009, &[binsh], execve),
+            vec![0x1009]
+        );
+    }
+}
@@ -1,8 +1,19 @@
-use super::Binary;
+use super::{find_all, lookup_sorted_symbols, printable_strings, sort_symbols, Binary};
 use anyhow::{bail, Context, Result};
-use goblin::{pe::export::Reexport::*, Object};
+use goblin::{
+    pe::{export::Reexport::*, section_table::IMAGE_SCN_MEM_EXECUTE},
+    Object,
+};
 use std::collections::HashMap;
 use std::fs;
+#[cfg(feature = "capstone")]
+use {
+    super::disasm,
+    crate::misc::shellcraft,
+    goblin::pe::header::{
+        COFF_MACHINE_ARM64, COFF_MACHINE_ARMNT, COFF_MACHINE_X86, COFF_MACHINE_X86_64,
+    },
+};
 
 pub struct PEBinary {
     /// raw bytes of the parsed ELF
@@ -13,6 +24,8 @@ pub struct PEBinary {
     pub eat: HashMap<String, u64>,
     /// symbol name to address map (iat and eat combined make up the symbols for PE binaries)
     pub symbols: HashMap<String, u64>,
+    /// (address, symbol name) sorted by address for reverse lookups
+    sorted_symbols: Vec<(u64, String)>,
 }
 
 impl PEBinary {
@@ -57,11 +70,14 @@ impl PEBinary {
             symbols.insert(name.to_owned(), *addr);
         }
 
+        let sorted_symbols = sort_symbols(&symbols);
+
         Ok(PEBinary {
             raw_bytes,
             iat,
             eat,
             symbols,
+            sorted_symbols,
         })
     }
 
@@ -121,11 +137,136 @@ impl Binary for PEBinary {
         let sym = self.symbols.get(sym).context("Symbol not found")?;
         Ok(*sym)
     }
+
+    /// given an address, retrieve the closest symbol at or below it and the offset
+    fn get_sym_at_addr(&self, addr: u64) -> Result<(String, u64)> {
+        lookup_sorted_symbols(&self.sorted_symbols, addr)
+    }
+
+    /// all symbols, see the symbols field
+    fn all_symbols(&self) -> &HashMap<String, u64> {
+        &self.symbols
+    }
+
+    /// scan the raw data of sections that are not executable, addresses are rvas
+    fn strings(&self, min_len: usize) -> Result<Vec<(u64, String)>> {
+        // parse in raw bytes as PE binary
+        let pe = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::PE(pe) => pe,
+            _ => bail!("No valid PE"),
+        };
+
+        let mut strings = vec![];
+        for section in pe.sections.iter() {
+            if section.characteristics & IMAGE_SCN_MEM_EXECUTE != 0 {
+                continue;
+            }
+
+            // the raw data is padded to the file alignment, only the virtual size is mapped
+            let start = section.pointer_to_raw_data as usize;
+            let len = section.size_of_raw_data.min(section.virtual_size) as usize;
+            let data = self
+                .raw_bytes
+                .get(start..start + len)
+                .context("Section exceeds file size")?;
+            strings.extend(printable_strings(
+                data,
+                section.virtual_address as u64,
+                min_len,
+            ));
+        }
+
+        Ok(strings)
+    }
+
+    /// search the raw data of all sections, addresses are rvas
+    fn find_bytes(&self, needle: &[u8]) -> Result<Vec<u64>> {
+        if needle.is_empty() {
+            bail!("Empty byte sequence");
+        }
+
+        // parse in raw bytes as PE binary
+        let pe = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::PE(pe) => pe,
+            _ => bail!("No valid PE"),
+        };
+
+        let mut addresses = vec![];
+        for section in pe.sections.iter() {
+            let start = section.pointer_to_raw_data as usize;
+            let len = section.size_of_raw_data.min(section.virtual_size) as usize;
+            let data = self
+                .raw_bytes
+                .get(start..start + len)
+                .context("Section exceeds file size")?;
+            addresses.extend(find_all(data, needle, section.virtual_address as u64));
+        }
+
+        Ok(addresses)
+    }
+
+    /// translate the rva to a file offset via the section table and read from there
+    fn read_vaddr(&self, addr: u64, len: usize) -> Result<Vec<u8>> {
+        // parse in raw bytes as PE binary
+        let pe = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::PE(pe) => pe,
+            _ => bail!("No valid PE"),
+        };
+
+        // the whole range has to lie within a single section, ranges past the end of the address
+        // space are never mapped
+        let end = addr.checked_add(len as u64);
+        let section = pe
+            .sections
+            .iter()
+            .find(|x| {
+                let start = x.virtual_address as u64;
+                addr >= start && end.map_or(false, |end| end <= start + x.virtual_size as u64)
+            })
+            .context(format!("Address {:#x} is not mapped by the binary", addr))?;
+
+        // memory past the raw data of a section is zero initialized
+        let mut data = vec![0; len];
+        let start = addr - section.virtual_address as u64;
+        let raw_size = section.size_of_raw_data as u64;
+        if start < raw_size {
+            let file_len = (raw_size - start).min(len as u64) as usize;
+            let offset = (section.pointer_to_raw_data as u64 + start) as usize;
+            data[..file_len].copy_from_slice(
+                self.raw_bytes
+                    .get(offset..offset + file_len)
+                    .context("Section exceeds file size")?,
+            );
+        }
+
+        Ok(data)
+    }
+
+    /// disassemble with the architecture from the coff header, addr is an rva like for read_vaddr
+    #[cfg(feature = "capstone")]
+    fn disassemble(&self, addr: u64, len: usize) -> Result<Vec<String>> {
+        // parse in raw bytes as PE binary
+        let pe = match Object::parse(&self.raw_bytes).context("Failed to parse raw data")? {
+            Object::PE(pe) => pe,
+            _ => bail!("No valid PE"),
+        };
+
+        let target = match pe.header.coff_header.machine {
+            COFF_MACHINE_X86 => shellcraft::Arch::X86,
+            COFF_MACHINE_X86_64 => shellcraft::Arch::Amd64,
+            COFF_MACHINE_ARMNT => shellcraft::Arch::Arm,
+            COFF_MACHINE_ARM64 => shellcraft::Arch::Aarch64,
+            _ => bail!("Disassembly is not supported for this architecture"),
+        };
+        let code = self.read_vaddr(addr, len)?;
+        disasm::disassemble(&code, addr, target, true)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::PEBinary;
+    use crate::binary_handling::Binary;
 
     #[test]
     fn test_iat_parser() {
@@ -177,4 +318,59 @@ mod tests {
             0x3b6e0
         );
     }
+
+    #[test]
+    fn test_sym_at_addr() {
+        let pe = PEBinary::new("test_data/kernel32_32.dll").unwrap();
+
+        // eat.name aliases lose against the shorter plain name
+        assert_eq!(
+            pe.get_sym_at_addr(0x5a8c0).unwrap(),
+            ("Module32NextW".to_string(), 0)
+        );
+        assert_eq!(pe.get_sym_at_addr(0x5a8c0 + 0x10).unwrap().1, 0x10);
+        assert!(pe.get_sym_at_addr(0).is_err());
+    }
+
+    #[test]
+    fn test_read_vaddr() {
+        let pe = PEBinary::new("test_data/kernel32_32.dll").unwrap();
+
+        // prologue of Module32NextW in .text
+        assert_eq!(
+            pe.read_vaddr(0x5a8c0, 8).unwrap(),
+            vec![0x8b, 0xff, 0x55, 0x8b, 0xec, 0x83, 0xec, 0x10]
+        );
+        assert!(pe.read_vaddr(0x200000, 4).is_err());
+        assert!(pe.read_vaddr(0xffff_ffff_ffff_fff0, 0x20).is_err());
+    }
+
+    #[test]
+    fn test_find_bytes() {
+        let pe = PEBinary::new("test_data/kernel32_32.dll").unwrap();
+
+        // prologue of Module32NextW, mov edi, edi; push ebp; mov ebp, esp starts most functions
+        let addresses = pe
+            .find_bytes(&[0x8b, 0xff, 0x55, 0x8b, 0xec, 0x83, 0xec, 0x10])
+            .unwrap();
+        assert!(addresses.contains(&0x5a8c0));
+        assert!(pe.find_bytes(&[0x8b, 0xff, 0x55]).unwrap().len() > addresses.len());
+        assert!(pe.find_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_strings() {
+        let pe = PEBinary::new("test_data/kernel32_32.dll").unwrap();
+        let strings = pe.strings(8).unwrap();
+
+        // the pdb path of the debug directory, the address is an rva like for read_vaddr
+        let (addr, string) = strings
+            .iter()
+            .find(|(_, x)| x.ends_with("kernel32.pdb"))
+            .unwrap();
+        assert_eq!(
+            pe.read_vaddr(*addr, string.len()).unwrap(),
+            string.as_bytes()
+        );
+    }
 }
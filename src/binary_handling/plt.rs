@@ -0,0 +1,117 @@
+/// endbr64 / endbr32 that starts the plt stubs of binaries built with control-flow protection
+const ENDBR64: &[u8] = &[0xf3, 0x0f, 0x1e, 0xfa];
+const ENDBR32: &[u8] = &[0xf3, 0x0f, 0x1e, 0xfb];
+/// bnd prefix of mpx plt stubs
+const BND: u8 = 0xf2;
+
+/// statically decode the got entry the x86 plt stub at address jumps through. handles
+/// jmp [rip+disp32] (64-bit), jmp [abs32] and jmp [ebx+disp32] (32-bit, ebx holds the got address)
+/// optionally preceded by endbr and bnd. None if the stub does not start with such a jump
+pub fn decode_x86_stub(code: &[u8], address: u64, got: u64, is_64: bool) -> Option<u64> {
+    let mut pos = 0;
+    if code.starts_with(ENDBR64) || code.starts_with(ENDBR32) {
+        pos += 4;
+    }
+    if code.get(pos) == Some(&BND) {
+        pos += 1;
+    }
+
+    let opcode = code.get(pos..pos + 2)?;
+    let disp = code.get(pos + 2..pos + 6)?;
+    let disp = u32::from_le_bytes([disp[0], disp[1], disp[2], disp[3]]);
+    // address of the next instruction
+    let next = address + pos as u64 + 6;
+
+    match (opcode, is_64) {
+        ([0xff, 0x25], true) => Some(next.wrapping_add(disp as i32 as u64)),
+        ([0xff, 0x25], false) => Some(disp as u64),
+        ([0xff, 0xa3], false) => Some((got as u32).wrapping_add(disp) as u64),
+        _ => None,
+    }
+}
+
+/// statically decode the stubs of an x86 plt section at the given offsets (or every 4-byte aligned
+/// offset) into (plt, got) address pairs
+pub fn decode_x86_plt(
+    section_address: u64,
+    section_data: &[u8],
+    stub_offsets: Option<Vec<u64>>,
+    got: u64,
+    is_64: bool,
+) -> Vec<(u64, u64)> {
+    let stub_offsets = stub_offsets.unwrap_or_else(|| {
+        (0..section_data.len() as u64)
+            .step_by(4)
+            .collect::<Vec<_>>()
+    });
+    stub_offsets
+        .into_iter()
+        .filter_map(|offset| {
+            let address = section_address + offset;
+            let target =
+                decode_x86_stub(section_data.get(offset as usize..)?, address, got, is_64)?;
+            Some((address, target))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_x86_stub() {
+        // jmp [rip+0x200b12]; push 0; jmp .plt
+        let stub = b"\xff\x25\x12\x0b\x20\x00\x68\x00\x00\x00\x00\xe9\xe0\xff\xff\xff";
+        assert_eq!(
+            decode_x86_stub(stub, 0x510, 0, true),
+            Some(0x510 + 6 + 0x200b12)
+        );
+
+        // endbr64; bnd jmp [rip-0x10]
+        let stub = b"\xf3\x0f\x1e\xfa\xf2\xff\x25\xf0\xff\xff\xff";
+        assert_eq!(
+            decode_x86_stub(stub, 0x1000, 0, true),
+            Some(0x1000 + 11 - 0x10)
+        );
+
+        // jmp [0x804a00c] and jmp [ebx+0xc]
+        assert_eq!(
+            decode_x86_stub(b"\xff\x25\x0c\xa0\x04\x08", 0x8048300, 0, false),
+            Some(0x804a00c)
+        );
+        assert_eq!(
+            decode_x86_stub(b"\xff\xa3\x0c\x00\x00\x00", 0x3b0, 0x1fd0, false),
+            Some(0x1fdc)
+        );
+
+        // push [rip+disp] of the plt header, truncated stubs and ebx-relative jumps on 64-bit
+        assert_eq!(
+            decode_x86_stub(b"\xff\x35\x02\x0b\x20\x00", 0, 0, true),
+            None
+        );
+        assert_eq!(decode_x86_stub(b"\xff\x25\x00", 0, 0, true), None);
+        assert_eq!(
+            decode_x86_stub(b"\xff\xa3\x0c\x00\x00\x00", 0, 0, true),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_x86_plt() {
+        let section = [
+            &b"\xff\x35\x00\x00\x00\x00"[..],
+            &[0x90; 10],
+            b"\xff\x25\x10\x00\x00\x00",
+        ]
+        .concat();
+        assert_eq!(
+            decode_x86_plt(0x500, &section, None, 0, true),
+            vec![(0x510, 0x526)]
+        );
+        assert_eq!(
+            decode_x86_plt(0x500, &section, Some(vec![0x10, 0x40]), 0, true),
+            vec![(0x510, 0x526)]
+        );
+    }
+}
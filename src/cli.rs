@@ -0,0 +1,207 @@
+use crate::config::Config;
+use crate::recipe::{parse_recipe, run_recipe, IngredientView};
+use crate::utils::{State, Target};
+
+use anyhow::{bail, Context, Result};
+use log::*;
+use std::fs;
+use std::io::Write;
+
+pub const USAGE: &str =
+    "Usage: BochumOxide run --recipe <file> (--target <program> [--pty] | --network <host:port>)
+
+Runs a saved recipe without the gui. The output of the program is written to stdout,
+errors and the exit status to stderr. Without arguments the gui is started.";
+
+/// what the headless run mode runs against
+#[derive(Clone, Debug, PartialEq)]
+pub enum CliTarget {
+    Local { program: String, pty: bool },
+    Network(String),
+}
+
+/// arguments of the run subcommand
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunOptions {
+    pub recipe: String,
+    pub target: CliTarget,
+}
+
+/// parse the arguments (without the binary name), None starts the gui
+pub fn parse_args(args: &[String]) -> Result<Option<RunOptions>> {
+    let (command, args) = match args.split_first() {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+    if command != "run" {
+        bail!("Unknown command {}\n\n{}", command, USAGE);
+    }
+
+    let mut recipe = None;
+    let mut program = None;
+    let mut network = None;
+    let mut pty = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .context(format!("Missing value of {}\n\n{}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--recipe" => recipe = Some(value()?),
+            "--target" => program = Some(value()?),
+            "--network" => network = Some(value()?),
+            "--pty" => pty = true,
+            _ => bail!("Unknown argument {}\n\n{}", arg, USAGE),
+        }
+    }
+
+    let recipe = recipe.context(format!("Missing --recipe\n\n{}", USAGE))?;
+    let target = match (program, network) {
+        (Some(program), None) => CliTarget::Local { program, pty },
+        (None, Some(address)) if !pty => CliTarget::Network(address),
+        (None, Some(_)) => bail!("--pty only works with --target"),
+        _ => bail!("Expected either --target or --network\n\n{}", USAGE),
+    };
+    Ok(Some(RunOptions { recipe, target }))
+}
+
+/// deserialize a recipe saved by the gui
+pub fn load_recipe(path: &str) -> Result<Vec<IngredientView>> {
+    let data = fs::read_to_string(path).context(format!("Unable to read recipe {}", path))?;
+    parse_recipe(&data).context(format!("Invalid recipe {}", path))
+}
+
+/// run the recipe against a newly started target and write what the program sent to out.
+/// returns whether every ingredient passed
+pub fn run(options: &RunOptions, out: &mut dyn Write) -> Result<bool> {
+    let recipe = load_recipe(&options.recipe)?;
+    let (target, program) = match &options.target {
+        CliTarget::Local { program, pty: true } => (Target::LocalPty, program),
+        CliTarget::Local { program, .. } => (Target::Local, program),
+        CliTarget::Network(address) => (Target::Network, address),
+    };
+    let mut state = State::new(target, program, &[])?;
+
+    // same registers as a recipe loaded in the gui
+    state.registers.set("program", program.as_bytes().to_vec());
+    state.allow_shell = Config::load().allow_shell;
+    for ingredient in &recipe {
+        state.registers.set(&ingredient.output, vec![]);
+    }
+
+    let result = run_recipe(&recipe, &mut state);
+    out.write_all(&state.output)
+        .and_then(|_| out.flush())
+        .context("Unable to write the program output")?;
+
+    if let Err((_, error)) = &result {
+        eprintln!("{}", error);
+    }
+    match state.exit_status {
+        Some(status) => eprintln!("Program exited: {}", status),
+        None => eprintln!("Program is still running"),
+    }
+    debug!("Headless run of {} finished: {:?}", options.recipe, result);
+    Ok(result.is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{RecvLineCmd, SendLineCmd};
+    use crate::recipe::serialize_recipe;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&[]).unwrap(), None);
+        assert_eq!(
+            parse_args(&args(&[
+                "run", "--recipe", "foo.json", "--target", "./chall"
+            ]))
+            .unwrap(),
+            Some(RunOptions {
+                recipe: "foo.json".to_string(),
+                target: CliTarget::Local {
+                    program: "./chall".to_string(),
+                    pty: false
+                },
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["run", "--network", "host:1337", "--recipe", "a"]))
+                .unwrap()
+                .unwrap()
+                .target,
+            CliTarget::Network("host:1337".to_string())
+        );
+
+        assert!(parse_args(&args(&["start"])).is_err());
+        assert!(parse_args(&args(&["run", "--target", "./chall"])).is_err());
+        assert!(parse_args(&args(&["run", "--recipe", "a"])).is_err());
+        assert!(parse_args(&args(&["run", "--recipe"])).is_err());
+        assert!(parse_args(&args(&[
+            "run",
+            "--recipe",
+            "a",
+            "--target",
+            "b",
+            "--network",
+            "c"
+        ]))
+        .is_err());
+        assert!(parse_args(&args(&["run", "--recipe", "a", "--network", "c", "--pty"])).is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let mut send = IngredientView::new::<SendLineCmd>();
+        send.input = "hello".to_string();
+        let mut recv = IngredientView::new::<RecvLineCmd>();
+        recv.output = "line".to_string();
+
+        let path =
+            std::env::temp_dir().join(format!("bochumoxide_cli_{}.json", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::write(&path, serialize_recipe(&[send, recv.clone()]).unwrap()).unwrap();
+
+        let options = RunOptions {
+            recipe: path.clone(),
+            target: CliTarget::Local {
+                program: "cat".to_string(),
+                pty: false,
+            },
+        };
+        let mut out = vec![];
+        assert!(run(&options, &mut out).unwrap());
+        assert_eq!(out, b"hello\n");
+
+        // a failing ingredient is reported, a missing program is an error
+        fs::write(&path, serde_json::to_string(&vec![recv]).unwrap()).unwrap();
+        let options = RunOptions {
+            recipe: path.clone(),
+            target: CliTarget::Local {
+                program: "true".to_string(),
+                pty: false,
+            },
+        };
+        assert!(!run(&options, &mut out).unwrap());
+
+        let options = RunOptions {
+            recipe: path.clone(),
+            target: CliTarget::Local {
+                program: "./does_not_exist".to_string(),
+                pty: false,
+            },
+        };
+        assert!(run(&options, &mut out).is_err());
+        fs::remove_file(&path).unwrap();
+
+        assert!(load_recipe(&path).is_err());
+    }
+}
@@ -1,14 +1,36 @@
-use crate::misc::cyclic::{cyclic, cyclic_find, de_bruijn_string};
-use crate::recipe::{CategoryView, IngredientView};
+use crate::binary_handling::libc_db::{identify_libc, parse_leaks};
+use crate::binary_handling::one_gadget::one_gadget_tool;
+use crate::binary_handling::{self, rebase};
+use crate::config::CONFIG_PATH;
+use crate::misc::checksum::{adler32, crc32};
+use crate::misc::compression::{gunzip, gzip, zlib_compress, zlib_decompress};
+use crate::misc::cyclic::{
+    check_alphabet, cyclic, cyclic_find, de_bruijn_string, DEFAULT_ALPHABET,
+};
+use crate::misc::fiddling::{
+    base64dec, base64enc, diff, enhex, hex_context, hexdump, rot13, rotate_bits, slice_bytes,
+    unescape_bytes, unhex, urldecode, urlencode, xor,
+};
+use crate::misc::fmtstr::{fmtstr_payload, parse_fmtstr};
+use crate::misc::hashing::{hash, solve_pow, HashAlgorithm};
+use crate::misc::packing::{
+    flat, pack, pack32, pack_f32, pack_f64, parse_flat, parse_int, parse_number, unpack_uint,
+    Endian,
+};
+use crate::misc::shellcraft::{sh, Arch};
+use crate::program_io::{describe_exit_status, Eof, Timeout};
+use crate::recipe::{parse_recipe, CategoryView, IngredientView};
 use crate::utils::State;
 use log::*;
 use regex::bytes::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::binary_handling::{self};
 use crate::lang::Ast;
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::process::Stdio;
+use std::time::Duration;
 
 pub type CmdResult = Result<Option<Vec<u8>>>;
 pub trait Command {
@@ -31,7 +53,7 @@ pub trait Command {
     fn title() -> String
     where
         Self: Sized;
-    fn from_parameter(param: &[u8], state: &State) -> Self
+    fn from_parameter(param: &[u8], state: &State) -> Result<Self>
     where
         Self: Sized;
 }
@@ -55,8 +77,55 @@ impl CommandCategory {
     }
 }
 
+// replaces every {expression} in the parameter with its evaluated result, the text around the
+// expressions is passed through literal. results are inserted as they are and never scanned again
+fn process_parameter(
+    param: &[u8],
+    state: &State,
+    literal: fn(&[u8]) -> Result<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    let re = Regex::new(r"\{(.*?)\}").expect("failed to create regex.");
+    let mut msg = vec![];
+    let mut last_end = 0;
+
+    for expr in re.find_iter(param) {
+        msg.extend(literal(&param[last_end..expr.start()])?);
+
+        let ast = &param[expr.start() + 1..expr.end() - 1];
+        let evaluated = Ast::new(&String::from_utf8(ast.to_vec()).context("Invalid utf8")?)
+            .context("Cannot parse as AST")?
+            .get_result(state)
+            .context("Cannot evaluate AST")?;
+        msg.extend(evaluated);
+        last_end = expr.end();
+    }
+    msg.extend(literal(&param[last_end..])?);
+
+    Ok(msg)
+}
+
+// default for ingredient inputs: escape sequences (\xNN, \n, \t, \0, \\, ...) are decoded in the text
+// outside of {...}, expressions are handled by the ast (which has its own escapes in string literals)
+fn parse_parameter(param: &[u8], state: &State) -> Result<Vec<u8>> {
+    process_parameter(param, state, unescape_bytes)
+}
+
+// for inputs with their own backslash syntax (regexes, scripts), only expressions are substituted
+fn substitute_expressions(param: &[u8], state: &State) -> Result<Vec<u8>> {
+    process_parameter(param, state, |text| Ok(text.to_vec()))
+}
+
+// for inputs that evaluate their {expressions} themselves, the input is kept as it is
+fn keep_parameter(param: &[u8], _state: &State) -> Result<Vec<u8>> {
+    Ok(param.to_vec())
+}
+
 macro_rules! simple_cmd {
     ($title:literal, $desc:literal, cat: $cat:ident, input: $input:expr, output: $output:expr, $name:ident => |$self:ident, $state:ident| $body:tt) => {
+        simple_cmd!($title, $desc, cat: $cat, input: $input, output: $output, parameter: parse_parameter, $name => |$self, $state| $body);
+    };
+    // parameter is the function that turns the raw input into msg, parse_parameter by default
+    ($title:literal, $desc:literal, cat: $cat:ident, input: $input:expr, output: $output:expr, parameter: $parameter:path, $name:ident => |$self:ident, $state:ident| $body:tt) => {
         pub struct $name {
             msg: Vec<u8>,
         }
@@ -90,30 +159,10 @@ macro_rules! simple_cmd {
                 $title.to_string()
             }
 
-            fn from_parameter(param: &[u8], state: &State) -> Self where Self: Sized {
-                let mut msg = param.to_owned();
-
-                let re = Regex::new(r"\{(.*?)\}").expect("failed to create regex.");
-                let mut msg_str = msg.clone();
-                while re.is_match(&msg_str) {
-                    if let Some(expr) = re.find(&msg_str) {
-                        let expr_start = expr.start();
-                        let expr_end = expr.end();
-
-                        let evaluated = {
-                            let ast = &msg[expr_start+1..expr_end-1];
-                            let evaluated = Ast::new(&String::from_utf8(ast.to_vec()).expect("Invalid utf8")).expect("Cannot parse as AST").get_result(state).expect("Cannot evaluate AST");
-                            [&msg[0..expr_start], &evaluated, &msg[expr_end..]].concat()
-                        };
-
-                        msg = evaluated.clone();
-                        msg_str = msg.clone();
-                    }
-                }
-
-                $name {
-                    msg
-                }
+            fn from_parameter(param: &[u8], state: &State) -> Result<Self> where Self: Sized {
+                Ok($name {
+                    msg: $parameter(param, state)?
+                })
             }
         }
     }
@@ -124,11 +173,11 @@ macro_rules! command_switch {
         pub fn parse_command(cmd_str: &str, param: &[u8], state: &State) -> Result<Box<dyn Command>> {
             let cmd = match cmd_str {
                 $(
-                    $cmd => Some(Box::new(<$cls>::from_parameter(param, state)) as Box<dyn Command>),
+                    $cmd => Box::new(<$cls>::from_parameter(param, state)?) as Box<dyn Command>,
                 )*
-                _ => None
+                _ => bail!("Can't parse command")
             };
-            cmd.ok_or(anyhow!("Can't parse command"))
+            Ok(cmd)
         }
 
         #[derive(Clone, Copy, Serialize, Deserialize, Debug)]
@@ -136,13 +185,13 @@ macro_rules! command_switch {
             $($cls,)* Custom
         }
 
-        pub fn create_command(cmd_type: CommandType, input: &[u8], state: &State) -> Box<dyn Command>{
-            match cmd_type {
+        pub fn create_command(cmd_type: CommandType, input: &[u8], state: &State) -> Result<Box<dyn Command>> {
+            Ok(match cmd_type {
                 $(
-                    CommandType::$cls => Box::new(<$cls>::from_parameter(input, state)) as Box<dyn Command>,
+                    CommandType::$cls => Box::new(<$cls>::from_parameter(input, state)?) as Box<dyn Command>,
                 )*
-                    CommandType::Custom => Box::new(CustomIngredient::from_parameter(input, state)) as Box<dyn Command>,
-            }
+                    CommandType::Custom => Box::new(CustomIngredient::from_parameter(input, state)?) as Box<dyn Command>,
+            })
         }
 
         pub fn available_categories() -> Vec<CategoryView> {
@@ -185,6 +234,7 @@ simple_cmd!("Send", "Sends data to the process.", cat: IO, input: true, output:
             .program
             .send(&self.msg)
             .context("Could not send to process.")?;
+        state.record_sent(&self.msg);
         Ok(None)
     }
 );
@@ -194,6 +244,51 @@ simple_cmd!("Send Line", "Sends data with an appended Newline to the process.",
             .program
             .send_line(&self.msg)
             .context("Could not send line to process.")?;
+        state.record_sent(&[&self.msg[..], b"\n"].concat());
+        Ok(None)
+    }
+);
+
+const SEND_AFTER_SEPARATOR: &[u8] = b"||";
+
+// splits delimiter||payload at the first separator
+fn split_send_after(msg: &[u8]) -> Result<(&[u8], &[u8])> {
+    let position = msg
+        .windows(SEND_AFTER_SEPARATOR.len())
+        .position(|w| w == SEND_AFTER_SEPARATOR)
+        .context("Malformed Send After Cmd, expected delimiter||payload")?;
+    Ok((
+        &msg[..position],
+        &msg[position + SEND_AFTER_SEPARATOR.len()..],
+    ))
+}
+
+// the delimiter is matched literally (escapes are decoded), only the payload has its expressions
+// substituted
+fn substitute_send_after(param: &[u8], state: &State) -> Result<Vec<u8>> {
+    let (delimiter, payload) = split_send_after(param)?;
+    Ok([
+        &unescape_bytes(delimiter)?,
+        SEND_AFTER_SEPARATOR,
+        &parse_parameter(payload, state)?,
+    ]
+    .concat())
+}
+
+simple_cmd!("Send After", "Receives until the delimiter, then sends the payload. Syntax: delimiter||payload", cat: IO, input: true, output: false, parameter: substitute_send_after, SendAfterCmd => |self, state| {
+        let (delimiter, payload) = split_send_after(&self.msg)?;
+        let received = state.program.send_after(delimiter, payload).context("Could not send to process.")?;
+        state.record_received(&received);
+        state.record_sent(payload);
+        Ok(None)
+    }
+);
+
+simple_cmd!("Send Line After", "Receives until the delimiter, then sends the payload with an appended Newline. Syntax: delimiter||payload", cat: IO, input: true, output: false, parameter: substitute_send_after, SendLineAfterCmd => |self, state| {
+        let (delimiter, payload) = split_send_after(&self.msg)?;
+        let received = state.program.send_line_after(delimiter, payload).context("Could not send line to process.")?;
+        state.record_received(&received);
+        state.record_sent(&[payload, b"\n"].concat());
         Ok(None)
     }
 );
@@ -206,27 +301,154 @@ simple_cmd!("Receive", "Receive data from the process.", cat: IO, input: true, o
         };
 
         let received = state.program.recv(read_size).context("Could not read from process")?;
-        state.output += &String::from_utf8_lossy(&received);
+        state.record_received(&received);
         Ok(Some(received))
     }
 );
 
+simple_cmd!("Peek", "Reads up to N bytes (default 4096) without consuming them, the next receive sees them again. Returns fewer bytes if less data is available.", cat: IO, input: true, output: true, PeekCmd => |self, state| {
+        let read_size = if self.msg.is_empty() {
+            4096
+        } else {
+            String::from_utf8(self.msg.clone())?.parse::<usize>()?
+        };
+
+        // nothing is consumed, so the data is not added to the output yet
+        let peeked = state.program.peek(read_size).context("Could not read from process")?;
+        Ok(Some(peeked))
+    }
+);
+
 simple_cmd!("Receive Until", "Receive data from the process until a certain sequence is found.", cat: IO, input: true, output: true, RecvUntil => |self, state| {
         let received = state.program.recv_until(&self.msg).context("Could not read from process")?;
-        state.output += &String::from_utf8(received.clone()).context("Invalid utf8")?;
+        state.record_received(&received);
+        Ok(Some(received))
+    }
+);
+
+simple_cmd!("Receive Until Any", "Receive data from the process until one of several sequences is found, the index (starting at 0) of the sequence that came first is stored in the register matched. Syntax: sequence|sequence|... (e.g. > |Invalid choice)", cat: IO, input: true, output: true, RecvUntilAnyCmd => |self, state| {
+        let terminators: Vec<&[u8]> = self.msg.split(|&b| b == b'|').collect();
+        let (received, index) = state.program.recv_until_any(&terminators).context("Could not read from process")?;
+        state.record_received(&received);
+        state.registers.set("matched", index.to_string().into_bytes());
+        Ok(Some(received))
+    }
+);
+
+simple_cmd!("Receive Until (timeout)", "Receive data until a certain sequence is found or the timeout elapsed, the data received until then is kept. Syntax: timeout_ms@sequence (e.g. 500@> )", cat: IO, input: true, output: true, RecvUntilTimeoutCmd => |self, state| {
+        let pos = self.msg.iter().position(|&b| b == b'@').context("Malformed input, expected timeout_ms@sequence")?;
+        let timeout = String::from_utf8(self.msg[..pos].to_vec())?.trim().parse::<u64>().context("Unable to parse timeout")?;
+        let terminator = &self.msg[pos + 1..];
+        if terminator.is_empty() {
+            bail!("Empty sequence");
+        }
+
+        let received = match state.program.recv_until_timeout(terminator, Duration::from_millis(timeout)) {
+            Ok(received) => received,
+            Err(e) => match e.downcast::<Timeout>() {
+                Ok(timeout) => {
+                    warn!("{}, sequence not found", timeout);
+                    timeout.received
+                }
+                Err(e) => return Err(e).context("Could not read from process"),
+            },
+        };
+        state.record_received(&received);
         Ok(Some(received))
     }
 );
 
 simple_cmd!("Receive Line", "Receives a single line from the process.", cat: IO, input: false, output: true, RecvLineCmd => |self, state| {
         let received = state.program.recv_line().context("Could not read from process")?;
-        state.output += &String::from_utf8(received.clone()).context("Invalid utf8")?;
+        state.record_received(&received);
+        Ok(Some(received))
+    }
+);
+
+simple_cmd!("Receive Lines", "Receives the given number of lines from the process. If the process closes its output before, the lines received until then are kept.", cat: IO, input: true, output: true, RecvLinesCmd => |self, state| {
+        let count = String::from_utf8(self.msg.clone())?.trim().parse::<usize>().context("Unable to parse number of lines")?;
+
+        let mut received = vec![];
+        for i in 0..count {
+            match state.program.recv_line() {
+                Ok(line) => {
+                    state.record_received(&line);
+                    received.extend(line);
+                }
+                Err(e) => match e.downcast::<Eof>() {
+                    Ok(eof) => {
+                        warn!("{}, received {} of {} lines", eof, i, count);
+                        state.record_received(&eof.received);
+                        received.extend(eof.received);
+                        break;
+                    }
+                    Err(e) => return Err(e).context("Could not read from process"),
+                },
+            }
+        }
         Ok(Some(received))
     }
 );
 
-simple_cmd!("Attach Debugger", "Attaches a debugger to the running process.", cat: Binary, input: false, output: false, AttachDbg => |self, state| {
-        state.program.attach_debugger()?;
+simple_cmd!("Receive Line (stripped)", "Receives a single line from the process without its trailing newline (\\n or \\r\\n).", cat: IO, input: false, output: true, RecvLineStrippedCmd => |self, state| {
+        let received = state.program.recv_line_stripped().context("Could not read from process")?;
+        state.record_received(&received);
+        Ok(Some(received))
+    }
+);
+
+simple_cmd!("Skip", "Receives exactly N bytes and discards them (e.g. a fixed size header), they are neither returned nor added to the output.", cat: IO, input: true, output: false, SkipCmd => |self, state| {
+        let count = String::from_utf8(self.msg.clone())?.trim().parse::<usize>().context("Unable to parse number of bytes")?;
+        let skipped = state.program.recv_n(count).context("Could not read from process")?;
+        debug!("Skipped {} bytes", skipped.len());
+        Ok(None)
+    }
+);
+
+simple_cmd!("Clean Buffer", "Discards all data the process has already sent. Optional timeout in ms (default 100).", cat: IO, input: true, output: true, CleanCmd => |self, state| {
+        let timeout = if self.msg.is_empty() {
+            100
+        } else {
+            String::from_utf8(self.msg.clone())?.parse::<u64>()?
+        };
+
+        let received = state.program.clean(Duration::from_millis(timeout)).context("Could not clean process output")?;
+        state.record_received(&received);
+        Ok(Some(received))
+    }
+);
+
+simple_cmd!("Wait For Exit", "Waits until the program terminated and logs the exit code or the terminating signal (also stored in the registers exit_code and exit_signal). Optional timeout in ms (default 1000)", cat: IO, input: true, output: true, WaitForExitCmd => |self, state| {
+    let timeout = if self.msg.is_empty() {
+        1000
+    } else {
+        String::from_utf8(self.msg.clone())?.trim().parse::<u64>().context("Unable to parse timeout")?
+    };
+
+    let status = describe_exit_status(state.wait_for_exit(Duration::from_millis(timeout))?);
+    info!("Program {}", status);
+    Ok(Some(status.into_bytes()))
+});
+
+simple_cmd!("Close Input", "Closes the input of the process (EOF) while its output can still be received.", cat: IO, input: false, output: false, CloseInputCmd => |self, state| {
+        state.program.close_stdin().context("Could not close input of process.")?;
+        Ok(None)
+    }
+);
+
+simple_cmd!("Interactive", "Hands the program over to the user. Lines can be sent from the panel below the program output.", cat: IO, input: false, output: false, InteractiveCmd => |self, state| {
+        let received = state.program.clean(Duration::from_millis(100)).context("Could not read from process")?;
+        state.record_received(&received);
+        state.interactive = true;
+        Ok(None)
+    }
+);
+
+simple_cmd!("Attach Debugger", "Attaches gdb to the running process and waits until it is attached. Optional gdb script run on attach, commands are separated by \\n (e.g. b *{$main_addr}\\ncontinue)", cat: Binary, input: true, output: false, AttachDbg => |self, state| {
+        // an empty script is a plain attach
+        let script = String::from_utf8(self.msg.clone())?;
+        let script = Some(script.as_str()).filter(|s| !s.trim().is_empty());
+        state.program.attach_debugger(script)?;
         Ok(None)
     }
 );
@@ -236,66 +458,744 @@ simple_cmd!("Log", "Logs a message", cat: Misc, input: true, output: true, LogCm
     Ok(Some(self.msg.to_vec()))
 });
 
-simple_cmd!("Regex", "Parse register content using regex. Syntax: register@regex", cat: Misc, input: true, output: true, RegexCmd => |self, state| {
-    // register@regex
+simple_cmd!("Evaluate", "Evaluates an expression (e.g. $base + 0x1234) and stores the result", cat: Misc, input: true, output: true, parameter: substitute_expressions, EvaluateCmd => |self, state| {
+    let script = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    let result = Ast::new(&script).context("Cannot parse expression")?.get_result(state).context("Cannot evaluate expression")?;
+    Ok(Some(result))
+});
+
+simple_cmd!("Regex", "Parse register content using regex. Syntax: register@regex[@group] where group is an index or name (default 0, the whole match)", cat: Misc, input: true, output: true, parameter: substitute_expressions, RegexCmd => |self, state| {
+    // register@regex[@group]
 
     let as_str = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
     let split = as_str.split_once("@").context("Malformed Regex Cmd")?;
     let register = state.registers.get(&split.0).context("Invalid Register in Regex Cmd")?;
-    let regex = split.1;
+
+    // the regex itself may contain @, so a suffix only counts as group if it is an index or a group name of the regex
+    let (regex, group) = match split.1.rsplit_once("@") {
+        Some((regex, group)) => match Regex::new(regex) {
+            Ok(re) if group.parse::<usize>().is_ok() || re.capture_names().any(|name| name == Some(group)) => (regex, group),
+            _ => (split.1, "0"),
+        },
+        None => (split.1, "0"),
+    };
 
     let re = Regex::new(regex).context("Malformed Regex")?;
+    let cpts = re.captures(register).context("Could not capture anything.")?;
 
-    if let Some(cpts) = re.captures(register) {
-        let result = cpts.get(1).context("No group captured")?.as_bytes();
-        return Ok(Some(result.to_vec()));
-    }
+    let captured = match group.parse::<usize>() {
+        Ok(index) => cpts.get(index),
+        Err(_) => cpts.name(group),
+    };
+    let captured = captured.context(format!("Group {} did not participate in the match", group))?;
+    Ok(Some(captured.as_bytes().to_vec()))
+});
+
+simple_cmd!("XOR", "Xors register content with a repeating key. Syntax: register@key (use {$register} to take the key from a register)", cat: Misc, input: true, output: true, XorCmd => |self, state| {
+    // register@key, the key is taken as raw bytes since it might come from a register
+    let split = self.msg.iter().position(|c| *c == b'@').context("Malformed XOR Cmd")?;
+    let register_name = String::from_utf8(self.msg[..split].to_vec()).context("Invalid utf8")?;
+    let register = state.registers.get(&register_name).context("Invalid Register in XOR Cmd")?;
+
+    Some(xor(register, &self.msg[split + 1..])).transpose()
+});
+
+simple_cmd!("ROT13", "Rotates the letters of the input by 13", cat: Misc, input: true, output: true, Rot13Cmd => |self, state| {
+    Ok(Some(rot13(&self.msg)))
+});
+
+simple_cmd!("Rotate Bits", "Rotates the bits of every byte of register content left by n (negative n rotates right). Syntax: register@n", cat: Misc, input: true, output: true, RotateBitsCmd => |self, state| {
+    let as_str = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    let split = as_str.split_once("@").context("Malformed Rotate Bits Cmd")?;
+    let register = state.registers.get(&split.0).context("Invalid Register in Rotate Bits Cmd")?;
+    let n: i32 = split.1.trim().parse().context("Unable to parse n")?;
+
+    Ok(Some(rotate_bits(register, n)))
+});
+
+simple_cmd!("Hex Encode", "Encodes the input as uppercase hex string", cat: Misc, input: true, output: true, EnhexCmd => |self, state| {
+    Ok(Some(enhex(&self.msg).into_bytes()))
+});
+
+simple_cmd!("Hex Decode", "Decodes a hex string (whitespace is ignored)", cat: Misc, input: true, output: true, UnhexCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    Some(unhex(&input)).transpose()
+});
 
-    bail!("Could not capture anything.");
+simple_cmd!("Base64 Encode", "Encodes the input as base64 string", cat: Misc, input: true, output: true, Base64EncCmd => |self, state| {
+    Ok(Some(base64enc(&self.msg).into_bytes()))
 });
 
-simple_cmd!("Send Padding", "Sends x amount of A", cat: IO, input: true, output: false, SendPaddingCmd => |self, state| {
-    let nr: usize = String::from_utf8(self.msg.clone()).context("invalid utf8")?.parse().context("Unable to parse nr")?;
-    let repeated_a = "A".repeat(nr);
+simple_cmd!("Base64 Decode", "Decodes a base64 string", cat: Misc, input: true, output: true, Base64DecCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    Some(base64dec(input.trim())).transpose()
+});
+
+simple_cmd!("Gzip", "Compresses the content of a register into the gzip format. Input is the register name", cat: Misc, input: true, output: true, GzipCmd => |self, state| {
+    let register = state.registers.get(&String::from_utf8(self.msg.clone())?).context("Invalid Register in Gzip Cmd")?;
+    Some(gzip(register)).transpose()
+});
+
+simple_cmd!("Gunzip", "Decompresses the gzip data of a register. Input is the register name", cat: Misc, input: true, output: true, GunzipCmd => |self, state| {
+    let register = state.registers.get(&String::from_utf8(self.msg.clone())?).context("Invalid Register in Gunzip Cmd")?;
+    Some(gunzip(register)).transpose()
+});
+
+simple_cmd!("Zlib Compress", "Compresses the content of a register into the zlib format. Input is the register name", cat: Misc, input: true, output: true, ZlibCompressCmd => |self, state| {
+    let register = state.registers.get(&String::from_utf8(self.msg.clone())?).context("Invalid Register in Zlib Compress Cmd")?;
+    Some(zlib_compress(register)).transpose()
+});
+
+simple_cmd!("Zlib Decompress", "Decompresses the zlib data of a register. Input is the register name", cat: Misc, input: true, output: true, ZlibDecompressCmd => |self, state| {
+    let register = state.registers.get(&String::from_utf8(self.msg.clone())?).context("Invalid Register in Zlib Decompress Cmd")?;
+    Some(zlib_decompress(register)).transpose()
+});
+
+simple_cmd!("CRC32", "Computes the CRC32 (as in zlib) of the content of a register as 4 bytes. Syntax: register[@le|be], defaults to little endian", cat: Misc, input: true, output: true, Crc32Cmd => |self, state| {
+    let (data, endian) = checksum_input(&self.msg, state)?;
+    Ok(Some(pack32(crc32(data), endian).to_vec()))
+});
+
+simple_cmd!("Adler-32", "Computes the Adler-32 checksum of the content of a register as 4 bytes. Syntax: register[@le|be], defaults to little endian", cat: Misc, input: true, output: true, Adler32Cmd => |self, state| {
+    let (data, endian) = checksum_input(&self.msg, state)?;
+    Ok(Some(pack32(adler32(data), endian).to_vec()))
+});
+
+simple_cmd!("Hash", "Hashes the content of a register with md5, sha1 or sha256 and outputs the raw digest bytes or their hex string. Syntax: register@algorithm[@raw|hex] (e.g. nonce@sha256@hex)", cat: Misc, input: true, output: true, HashCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let parts: Vec<&str> = input.split('@').collect();
+    let (register, algorithm, as_hex) = match parts.as_slice() {
+        [register, algorithm] => (*register, *algorithm, false),
+        [register, algorithm, format] => match format.trim() {
+            "raw" => (*register, *algorithm, false),
+            "hex" => (*register, *algorithm, true),
+            other => bail!("Unknown output format {}, expected raw or hex", other),
+        },
+        _ => bail!("Malformed input, expected register@algorithm[@raw|hex]"),
+    };
+    let algorithm: HashAlgorithm = algorithm.parse()?;
+    let data = state.registers.get(register.trim()).context(format!("Register {} does not exist", register))?;
+
+    let digest = hash(algorithm, data);
+    Ok(Some(if as_hex { hex::encode(digest).into_bytes() } else { digest }))
+});
+
+simple_cmd!("Solve PoW", "Brute-forces an alphanumeric suffix X such that hash(challenge + X) starts with the given number of zero bits, using all cpus and giving up after 2^30 tries. Outputs X. Syntax: register@algorithm@bits (e.g. challenge@sha256@20)", cat: Misc, input: true, output: true, SolvePowCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let parts: Vec<&str> = input.split('@').collect();
+    let (register, algorithm, difficulty) = match parts.as_slice() {
+        [register, algorithm, difficulty] => (register.trim(), *algorithm, *difficulty),
+        _ => bail!("Malformed input, expected register@algorithm@bits"),
+    };
+    let algorithm: HashAlgorithm = algorithm.parse()?;
+    let difficulty = difficulty.trim().parse::<u32>().context("Unable to parse number of zero bits")?;
+    let challenge = state.registers.get(register).context(format!("Register {} does not exist", register))?;
+
+    let suffix = solve_pow(algorithm, challenge, difficulty)?;
+    debug!("Solved proof of work with suffix {}", String::from_utf8_lossy(&suffix));
+    Ok(Some(suffix))
+});
+
+simple_cmd!("URL Encode", "Percent-encodes every character of the input", cat: Misc, input: true, output: true, UrlEncCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    Ok(Some(urlencode(&input).into_bytes()))
+});
+
+simple_cmd!("URL Decode", "Decodes a percent-encoded string", cat: Misc, input: true, output: true, UrlDecCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    Ok(Some(urldecode(&input)?.into_bytes()))
+});
+
+// padding of form count[:byte|:cyclic], the fill byte is hex (e.g. 90 or 0x90) and defaults to A
+fn parse_padding(input: &str) -> Result<Vec<u8>> {
+    let (count, fill) = match input.split_once(':') {
+        Some((count, fill)) => (count, Some(fill.trim())),
+        None => (input, None),
+    };
+    let count: usize = count.trim().parse().context("Unable to parse count")?;
+
+    match fill {
+        None => Ok(vec![b'A'; count]),
+        Some("cyclic") => cyclic(count, 4, DEFAULT_ALPHABET),
+        Some(byte) => {
+            let byte = u8::from_str_radix(byte.strip_prefix("0x").unwrap_or(byte), 16).context(
+                format!("Invalid fill byte {}, expected a hex byte or cyclic", byte),
+            )?;
+            Ok(vec![byte; count])
+        }
+    }
+}
+
+simple_cmd!("Send Padding", "Sends padding of the given length. Syntax: count[:byte|:cyclic] with a hex fill byte (default A, e.g. 90 for a NOP sled) or a cyclic pattern", cat: IO, input: true, output: false, SendPaddingCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone()).context("invalid utf8")?;
+    let padding = parse_padding(&input)?;
     state
         .program
-        .send(repeated_a.as_bytes())
+        .send(&padding)
         .context("Could not send to process.")?;
+    state.record_sent(&padding);
     Ok(None)
 });
 
+// runs command with sh, input is written to its stdin. fails if the command exits unsuccessfully
+fn run_shell(command: &str, input: &[u8]) -> Result<Vec<u8>> {
+    let mut child = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Unable to run sh")?;
+
+    // write in a thread so a command producing lots of output before reading all input does not block
+    let mut stdin = child.stdin.take().context("No stdin")?;
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let output = child
+        .wait_with_output()
+        .context("Unable to wait for command")?;
+    // commands that do not read their input close the pipe early
+    let _ = writer.join();
+    if !output.status.success() {
+        bail!(
+            "Command '{}' {}: {}",
+            command,
+            describe_exit_status(output.status),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+simple_cmd!("Shell", "Runs a shell command with the register as stdin and returns its stdout. Needs \"allow_shell\": true in config.json. Syntax: register@command (empty register for no input)", cat: Misc, input: true, output: true, parameter: keep_parameter, ShellCmd => |self, state| {
+    if !state.allow_shell {
+        bail!("Shell commands are disabled, set \"allow_shell\": true in {} to enable them", CONFIG_PATH);
+    }
+    let input = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    let (register, command) = input.split_once('@').context("Malformed input, expected register@command")?;
+    let register = register.trim();
+    let stdin = if register.is_empty() {
+        vec![]
+    } else {
+        state.registers.get(register).context(format!("Register {} does not exist", register))?.to_vec()
+    };
+    Some(run_shell(command, &stdin)).transpose()
+});
+
 simple_cmd!("Log Registers", "Logs all available registers", cat: Misc, input: false, output: false, LogRegCmd => |self, state| {
-    let strings: Vec<String> = state.registers.map.iter().map(|(key, value)| format!("{}: {:?}\n", key, value)).collect();
+    let strings: Vec<String> = state.registers.map.keys().map(|key| format!("{}: {}\n", key, state.registers.format(key).unwrap_or_default())).collect();
     debug!("{}", strings.join(""));
     Ok(None)
 });
 
-simple_cmd!("Get Symbol Address", "Gets address of a symbol", cat: Binary, input: true, output: true, GetSymAddrCmd => |self, state| {
-    let binary = binary_handling::from_path(&state.program_path)?;
-    Ok(Some(format!("{}", binary.get_sym_addr(&String::from_utf8(self.msg.clone())?)?).into_bytes()))
+simple_cmd!("Hexdump Register", "Logs the bytes of a register as hexdump (offset, hex and ascii)", cat: Misc, input: true, output: false, HexdumpRegCmd => |self, state| {
+    let name = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    let bytes = state.registers.get(name.trim()).context(format!("Register {} does not exist", name.trim()))?;
+    debug!("{} ({} bytes):\n{}", name.trim(), bytes.len(), hexdump(bytes));
+    Ok(None)
 });
 
-simple_cmd!("Pack Address", "Packs address into bytestring", cat: Misc, input: true, output: true, StringToAddrCmd => |self, state| {
-    let address = u32::from_str_radix(&String::from_utf8(self.msg.clone())?, 10).expect("failed decoding string");
-    Ok(Some(address.to_ne_bytes().to_vec()))
+simple_cmd!("Diff Registers", "Compares two registers and logs the first differing offset with the bytes around it. Outputs the offset or -1 if both are identical. Syntax: register@register", cat: Misc, input: true, output: true, DiffRegCmd => |self, state| {
+    let as_str = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    let (first, second) = as_str.split_once("@").context("Malformed input, expected register@register")?;
+    let (first, second) = (first.trim(), second.trim());
+    let a = state.registers.get(first).context(format!("Register {} does not exist", first))?;
+    let b = state.registers.get(second).context(format!("Register {} does not exist", second))?;
+
+    match diff(a, b) {
+        Some(offset) => {
+            debug!("{} and {} differ at offset {:#x} ({} vs {} bytes)\n{}: {}\n{}: {}", first, second, offset, a.len(), b.len(),
+                first, hex_context(a, offset, 8), second, hex_context(b, offset, 8));
+            Ok(Some(offset.to_string().into_bytes()))
+        }
+        None => {
+            debug!("{} and {} are identical", first, second);
+            Ok(Some(b"-1".to_vec()))
+        }
+    }
 });
 
-simple_cmd!("Generate Cyclic Sequence", "Generate cyclic sequence with substring size 4 and given length", cat: Misc, input: true, output: true, CyclicCmd => |self, state| {
-    let len: usize = String::from_utf8(self.msg.clone())?.parse().context("Unable to parse len")?;
-    Some(cyclic(len, 4)).transpose()
+simple_cmd!("Slice", "Outputs the bytes start..end (end excluded) of a register, negative indices count from the end and a missing one means start or end. Syntax: register@start:end (e.g. leak@-8: for the last 8 bytes)", cat: Misc, input: true, output: true, SliceCmd => |self, state| {
+    let as_str = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    let (name, range) = as_str.rsplit_once("@").context("Malformed input, expected register@start:end")?;
+    let (start, end) = range.split_once(":").context("Malformed range, expected start:end")?;
+    let parse_index = |index: &str| -> Result<Option<i64>> {
+        match index.trim() {
+            "" => Ok(None),
+            index => Ok(Some(index.parse().context(format!("Unable to parse index {}", index))?)),
+        }
+    };
+
+    let bytes = state.registers.get(name.trim()).context(format!("Register {} does not exist", name.trim()))?;
+    Ok(Some(slice_bytes(bytes, parse_index(start)?, parse_index(end)?)?.to_vec()))
 });
 
-simple_cmd!("Find Cyclic Substring", "Calculates the position of a substring", cat: Misc, input: true, output: true, CyclicFindCmd => |self, state| {
-    let substring= u32::from_str_radix(&String::from_utf8(self.msg.clone())?, 16)?.to_ne_bytes();
+simple_cmd!("Concat", "Concatenates registers and expressions. Syntax: comma separated list of register names and {expressions} (e.g. padding, {p64($ret)}, {\"/bin/sh\"})", cat: Misc, input: true, output: true, parameter: keep_parameter, ConcatCmd => |self, state| {
+    // every item is an expression or a register name, followed by a comma or the end of the input
+    let item_re = Regex::new(r"^\s*(?:\{(.*?)\}|([A-Za-z0-9_]+))\s*(?:,|$)").expect("failed to create regex.");
+    let mut concatenated = vec![];
+    let mut rest = &self.msg[..];
 
-    let position = cyclic_find(&substring, 4);
-    if let Some(pos) = position {
-        let bytes = pos.to_string().as_bytes().to_vec();
-        return Ok(Some(bytes));
+    while !rest.is_empty() {
+        let item = item_re.captures(rest).context(format!("Malformed item {}", String::from_utf8_lossy(rest)))?;
+        if let Some(expr) = item.get(1) {
+            let ast = Ast::new(&String::from_utf8(expr.as_bytes().to_vec()).context("Invalid utf8")?).context("Cannot parse as AST")?;
+            concatenated.extend(ast.get_result(state).context("Cannot evaluate AST")?);
+        } else if let Some(name) = item.get(2) {
+            let name = String::from_utf8(name.as_bytes().to_vec()).context("Invalid utf8")?;
+            concatenated.extend(state.registers.get(&name).context(format!("Register {} does not exist", name))?);
+        }
+        rest = &rest[item.get(0).unwrap().end()..];
     }
+    Ok(Some(concatenated))
+});
+
+simple_cmd!("Set Register Type", "Sets how a register is shown by Log Registers. Syntax: register@raw|hex|dec|ascii (hex and dec read the bytes as little endian integer)", cat: Misc, input: true, output: false, SetRegTypeCmd => |self, state| {
+    let as_str = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    let (register, hint) = as_str.split_once("@").context("Malformed Set Register Type Cmd")?;
+    state.registers.set_hint(register.trim(), hint.trim().parse()?)?;
     Ok(None)
 });
 
+simple_cmd!("Delete Register", "Deletes a register", cat: Misc, input: true, output: false, DeleteRegCmd => |self, state| {
+    let name = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    state.registers.remove(name.trim()).context(format!("Register {} does not exist", name.trim()))?;
+    Ok(None)
+});
+
+simple_cmd!("Rename Register", "Renames a register. Syntax: old@new", cat: Misc, input: true, output: false, RenameRegCmd => |self, state| {
+    let as_str = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    let (old, new) = as_str.split_once("@").context("Malformed Rename Register Cmd")?;
+    state.registers.rename(old.trim(), new.trim())?;
+    Ok(None)
+});
+
+simple_cmd!("Get Symbol Address", "Gets address of a symbol, rebased onto the binary base if one is set", cat: Binary, input: true, output: true, GetSymAddrCmd => |self, state| {
+    let sym = String::from_utf8(self.msg.clone())?;
+    let base = state.binary_base;
+    let binary = state.binary()?;
+    let addr = match binary.get_sym_addr(&sym) {
+        Ok(addr) => addr,
+        // plt stubs that cannot be decoded statically are only resolved by emulation
+        Err(e) if !cfg!(feature = "unicorn") => {
+            bail!("{} (plt entries of this binary may need the 'uni' feature)", e)
+        }
+        Err(e) => return Err(e),
+    };
+
+    let addr = match base {
+        Some(base) => rebase(addr, base),
+        None => {
+            if binary.mitigations().map_or(false, |m| m.pie) {
+                warn!("{} is only an offset, the binary is PIE but no binary base is set", sym);
+            }
+            addr
+        }
+    };
+    Ok(Some(format!("{}", addr).into_bytes()))
+});
+
+simple_cmd!("Set Binary Base", "Sets the runtime load address of the binary (e.g. {$leak - 0x1139}), symbol lookups are rebased onto it and base() returns it", cat: Binary, input: true, output: false, SetBaseCmd => |self, state| {
+    let base = parse_number(&String::from_utf8(self.msg.clone())?).context("Unable to parse base")?;
+    state.binary_base = Some(base);
+    Ok(None)
+});
+
+simple_cmd!("Rebase", "Adds a base to the address of a symbol. Syntax: base@symbol (e.g. {$libc_base}@system)", cat: Binary, input: true, output: true, RebaseCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let (base, sym) = input.split_once("@").context("Malformed input, expected base@symbol")?;
+    let base = parse_number(base).context("Unable to parse base")?;
+
+    let addr = state.binary()?.get_sym_addr(sym.trim())?;
+    Ok(Some(format!("{}", rebase(addr, base)).into_bytes()))
+});
+
+simple_cmd!("Symbol From Address", "Gets the symbol an address lies in as name+0xoffset (address in decimal or 0x-prefixed hex), runtime addresses are accepted if a binary base is set", cat: Binary, input: true, output: true, SymFromAddrCmd => |self, state| {
+    let addr = parse_number(&String::from_utf8(self.msg.clone())?).context("Unable to parse address")?;
+    let addr = match state.binary_base {
+        Some(base) => addr.checked_sub(base).context(format!("Address {:#x} lies below the binary base {:#x}", addr, base))?,
+        None => addr,
+    };
+
+    let binary = state.binary()?;
+    let (name, offset) = binary.get_sym_at_addr(addr)?;
+    Ok(Some(format!("{}+{:#x}", name, offset).into_bytes()))
+});
+
+simple_cmd!("Read Binary Memory", "Reads bytes the binary maps at a virtual address. Syntax: addr:len", cat: Binary, input: true, output: true, ReadVaddrCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let (addr, len) = input.split_once(":").context("Malformed input, expected addr:len")?;
+    let addr = parse_number(addr).context("Unable to parse address")?;
+    let len = parse_number(len).context("Unable to parse length")? as usize;
+
+    Some(state.binary()?.read_vaddr(addr, len)).transpose()
+});
+
+simple_cmd!("Disassemble", "Logs the instructions the binary maps at a virtual address (requires the disasm feature). Syntax: addr:len", cat: Binary, input: true, output: false, DisassembleCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let (addr, len) = input.split_once(":").context("Malformed input, expected addr:len")?;
+    let addr = parse_number(addr).context("Unable to parse address")?;
+    let len = parse_number(len).context("Unable to parse length")? as usize;
+
+    let lines = state.binary()?.disassemble(addr, len)?;
+    debug!("Disassembly of {:#x}:\n{}", addr, lines.join("\n"));
+    Ok(None)
+});
+
+simple_cmd!("Checksec", "Logs the exploit mitigations (NX, PIE, RELRO, canary) of the binary", cat: Binary, input: false, output: false, ChecksecCmd => |self, state| {
+    let mitigations = state.binary()?.mitigations()?;
+    debug!("{}:\n{}", state.program_path, mitigations);
+    Ok(None)
+});
+
+simple_cmd!("Find Gadget", "Gets the address of the first gadget matching the hex byte pattern (e.g. 5f c3 for pop rdi; ret)", cat: Binary, input: true, output: true, FindGadgetCmd => |self, state| {
+    let pattern: String = String::from_utf8(self.msg.clone())?.chars().filter(|c| !c.is_whitespace()).collect();
+    let gadget = hex::decode(&pattern).context("Invalid hex pattern")?;
+
+    let binary = state.binary()?;
+    let address = binary.find_gadget(&gadget)?.into_iter().next().context("Gadget not found")?;
+    Ok(Some(format!("{}", address).into_bytes()))
+});
+
+simple_cmd!("Find Bytes", "Gets the address of the first occurrence of a hex byte sequence (e.g. de ad be ef) in the data the binary maps, rebased onto the binary base if one is set. All matches are logged", cat: Binary, input: true, output: true, FindBytesCmd => |self, state| {
+    let pattern: String = String::from_utf8(self.msg.clone())?.chars().filter(|c| !c.is_whitespace()).collect();
+    let needle = hex::decode(&pattern).context("Invalid hex pattern")?;
+
+    let base = state.binary_base;
+    let addresses: Vec<u64> = state
+        .binary()?
+        .find_bytes(&needle)?
+        .into_iter()
+        .map(|address| base.map_or(address, |base| rebase(address, base)))
+        .collect();
+    let address = *addresses.first().context("Byte sequence not found")?;
+    let matches: Vec<String> = addresses.iter().map(|x| format!("{:#x}", x)).collect();
+    debug!("{} matches of {}: {}", addresses.len(), pattern, matches.join(", "));
+    Ok(Some(format!("{}", address).into_bytes()))
+});
+
+simple_cmd!("Find String", "Gets the address of the first occurrence of a printable string (e.g. /bin/sh) in the data sections of the binary, rebased onto the binary base if one is set", cat: Binary, input: true, output: true, FindStringCmd => |self, state| {
+    let needle = String::from_utf8(self.msg.clone())?;
+    if needle.is_empty() {
+        bail!("No string given");
+    }
+
+    let base = state.binary_base;
+    let strings = state.binary()?.strings(needle.len())?;
+    let address = strings
+        .iter()
+        .find_map(|(address, string)| Some(address + string.find(&needle)? as u64))
+        .context(format!("String '{}' not found", needle))?;
+    let address = base.map_or(address, |base| rebase(address, base));
+    Ok(Some(format!("{}", address).into_bytes()))
+});
+
+// candidate of the one gadget input base@libc_path[@index] rebased onto base. tool is the lookup
+// through the one_gadget tool (None if it is not installed), the static search is the fallback
+fn pick_one_gadget(
+    input: &str,
+    program_path: &str,
+    tool: fn(&str) -> Option<Result<Vec<u64>>>,
+) -> Result<u64> {
+    let parts: Vec<&str> = input.split('@').collect();
+    let (base, path, index) = match parts.as_slice() {
+        [base, path] => (*base, path.trim(), 0),
+        [base, path, index] => (
+            *base,
+            path.trim(),
+            index.trim().parse().context("Unable to parse index")?,
+        ),
+        _ => bail!("Malformed input, expected base@libc_path[@index]"),
+    };
+    let base = parse_number(base).context("Unable to parse base")?;
+    let path = if path.is_empty() { program_path } else { path };
+
+    let candidates = match tool(path) {
+        Some(candidates) => candidates?,
+        None => binary_handling::from_path(path)?.one_gadgets()?,
+    };
+    let lines: Vec<String> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, offset)| format!("{}: {:#x} -> {:#x}", i, offset, rebase(*offset, base)))
+        .collect();
+    warn!(
+        "One gadget candidates of {} (constraints not verified):\n{}",
+        path,
+        lines.join("\n")
+    );
+
+    let offset = candidates.get(index).context(format!(
+        "No candidate {}, found {}",
+        index,
+        candidates.len()
+    ))?;
+    Ok(rebase(*offset, base))
+}
+
+simple_cmd!("One Gadget", "Gets a candidate one gadget (execve(\"/bin/sh\")) of a libc rebased onto base, using the one_gadget tool if installed. The constraints are NOT verified, all candidates are logged. Syntax: base@libc_path[@index] (e.g. {$libc_base}@./libc.so.6@1), an empty path uses the binary", cat: Binary, input: true, output: true, OneGadgetCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let address = pick_one_gadget(&input, &state.program_path, one_gadget_tool)?;
+    Ok(Some(format!("{}", address).into_bytes()))
+});
+
+simple_cmd!("Identify Libc", "Gets the libcs (files with .so in their name) of a directory whose symbols match the page offsets of leaked addresses. Syntax: directory@symbol=address,... (e.g. libcs@puts={$puts_leak},printf={$printf_leak})", cat: Binary, input: true, output: true, IdentifyLibcCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let (dir, leaks) = input.split_once('@').context("Malformed input, expected directory@symbol=address,...")?;
+    let matches = identify_libc(dir.trim(), &parse_leaks(leaks)?)?;
+    if matches.is_empty() {
+        bail!("No libc in {} matches the leaks", dir.trim());
+    }
+    info!("Matching libcs: {}", matches.join(", "));
+    Ok(Some(matches.join("\n").into_bytes()))
+});
+
+// address held by a register (number text or up to 8 little endian bytes as received) or a literal number
+fn leaked_address(state: &State, leak: &str) -> Result<u64> {
+    let bytes = match state.registers.get(leak) {
+        Some(bytes) => bytes,
+        None => {
+            return parse_number(leak)
+                .context(format!("{} is neither a register nor a number", leak))
+        }
+    };
+    if let Ok(address) = parse_number(&String::from_utf8_lossy(bytes)) {
+        return Ok(address);
+    }
+    if bytes.len() > 8 {
+        bail!(
+            "Register {} holds {} bytes, expected at most 8",
+            leak,
+            bytes.len()
+        );
+    }
+    let mut buf = [0; 8];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+simple_cmd!("Libc Base", "Computes the libc base from a leaked symbol address, the leak is a register (number text or raw little endian bytes) or a number. Syntax: leak@symbol@libc_path (e.g. puts_leak@puts@./libc.so.6)", cat: Binary, input: true, output: true, LibcBaseCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let parts: Vec<&str> = input.split('@').map(str::trim).collect();
+    let (leak, symbol, path) = match parts.as_slice() {
+        [leak, symbol, path] => (*leak, *symbol, *path),
+        _ => bail!("Malformed input, expected leak@symbol@libc_path"),
+    };
+
+    let address = leaked_address(state, leak)?;
+    let offset = binary_handling::from_path(path)?.get_sym_addr(symbol).context(format!("Symbol {} not found in {}", symbol, path))?;
+    let base = address.checked_sub(offset).context(format!("Leak {:#x} lies below the offset {:#x} of {}", address, offset, symbol))?;
+    if base & 0xfff != 0 {
+        warn!("Libc base {:#x} is not page aligned, the symbol or libc is probably wrong", base);
+    }
+    Ok(Some(format!("{}", base).into_bytes()))
+});
+
+simple_cmd!("Pack Address", "Packs address (decimal or 0x-prefixed hex) into bytestring. Syntax: [p8|p16|p32|p64:[le|be:]]address, defaults to p32 little endian", cat: Misc, input: true, output: true, StringToAddrCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let parts: Vec<&str> = input.split(':').collect();
+
+    // split off optional width and endianness
+    let (width, endian, address) = match parts.as_slice() {
+        [address] => ("p32", "le", *address),
+        [width, address] => (*width, "le", *address),
+        [width, endian, address] => (*width, *endian, *address),
+        _ => bail!("Malformed Pack Address input"),
+    };
+
+    let bits = match width.trim() {
+        "p8" => 8,
+        "p16" => 16,
+        "p32" => 32,
+        "p64" => 64,
+        other => bail!("Unknown width {}", other),
+    };
+    let endian = parse_endian(endian)?;
+    let address = parse_number(address).context("Unable to parse address")?;
+
+    Some(pack(address, bits, endian)).transpose()
+});
+
+simple_cmd!("Parse Int", "Parses the number text of a register (e.g. a leaked 0xdeadbeef) into bytes. Syntax: register[@auto|10|16[@p8|p16|p32|p64[@le|be]]], defaults to auto (decimal or 0x-prefixed hex), p64 and the byte order of the binary", cat: Misc, input: true, output: true, ParseIntCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let parts: Vec<&str> = input.split('@').collect();
+
+    // split off optional base, width and endianness
+    let (register, base, width, endian) = match parts.as_slice() {
+        [register] => (*register, "auto", "p64", None),
+        [register, base] => (*register, *base, "p64", None),
+        [register, base, width] => (*register, *base, *width, None),
+        [register, base, width, endian] => (*register, *base, *width, Some(parse_endian(endian)?)),
+        _ => bail!("Malformed Parse Int input"),
+    };
+
+    let radix = match base.trim() {
+        "auto" => None,
+        "10" => Some(10),
+        "16" => Some(16),
+        other => bail!("Unknown base {}", other),
+    };
+    let bits = match width.trim() {
+        "p8" => 8,
+        "p16" => 16,
+        "p32" => 32,
+        "p64" => 64,
+        other => bail!("Unknown width {}", other),
+    };
+
+    let text = state.registers.get(register.trim()).context(format!("Register {} does not exist", register.trim()))?;
+    let number = parse_int(&String::from_utf8_lossy(text), radix).context("Unable to parse number")?;
+    Some(pack(number, bits, endian.unwrap_or_else(|| state.default_endian()))).transpose()
+});
+
+simple_cmd!("Unpack", "Unpacks up to 8 bytes of a register (e.g. a received pointer leak) into a decimal number. Syntax: register[@le|be], defaults to the byte order of the binary", cat: Misc, input: true, output: true, UnpackCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let (register, endian) = match input.split_once('@') {
+        Some((register, endian)) => (register.trim(), parse_endian(endian)?),
+        None => (input.trim(), state.default_endian()),
+    };
+    let bytes = state.registers.get(register).context(format!("Register {} does not exist", register))?;
+    Ok(Some(unpack_uint(bytes, endian)?.to_string().into_bytes()))
+});
+
+simple_cmd!("Pack Float", "Packs an IEEE-754 float into bytestring. Syntax: [f32|f64:[le|be:]]value, defaults to f64 little endian", cat: Misc, input: true, output: true, PackFloatCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let parts: Vec<&str> = input.split(':').collect();
+
+    // split off optional width and endianness
+    let (width, endian, value) = match parts.as_slice() {
+        [value] => ("f64", "le", *value),
+        [width, value] => (*width, "le", *value),
+        [width, endian, value] => (*width, *endian, *value),
+        _ => bail!("Malformed Pack Float input"),
+    };
+
+    let endian = parse_endian(endian)?;
+    let value: f64 = value.trim().parse().context("Unable to parse float")?;
+
+    match width.trim() {
+        "f32" => Ok(Some(pack_f32(value as f32, endian).to_vec())),
+        "f64" => Ok(Some(pack_f64(value, endian).to_vec())),
+        other => bail!("Unknown width {}", other),
+    }
+});
+
+/// parse le/be into the endianness it stands for
+fn parse_endian(input: &str) -> Result<Endian> {
+    input.parse()
+}
+
+/// content of the register and byte order of checksum ingredient input of form register[@le|be]
+fn checksum_input<'a>(input: &[u8], state: &'a State) -> Result<(&'a [u8], Endian)> {
+    let input = String::from_utf8(input.to_vec())?;
+    let (register, endian) = match input.split_once('@') {
+        Some((register, endian)) => (register.trim(), parse_endian(endian)?),
+        None => (input.trim(), Endian::Little),
+    };
+    let data = state
+        .registers
+        .get(register)
+        .context(format!("Register {} does not exist", register))?;
+    Ok((data, endian))
+}
+
+/// split cyclic ingredient input of form value[:n[:alphabet]] into its parts
+fn parse_cyclic_input(input: &str) -> Result<(&str, usize, &[u8])> {
+    let mut parts = input.splitn(3, ':');
+    let value = parts.next().unwrap_or_default();
+    let n = match parts.next() {
+        Some(n) => n.trim().parse().context("Unable to parse n")?,
+        None => 4,
+    };
+    let alphabet = match parts.next() {
+        Some(alphabet) if !alphabet.is_empty() => alphabet.as_bytes(),
+        _ => DEFAULT_ALPHABET,
+    };
+    check_alphabet(alphabet)?;
+    Ok((value, n, alphabet))
+}
+
+simple_cmd!("Flat", "Concatenates packed values. Syntax: comma separated list of b\"..\", p8/p16/p32/p64(value) (suffix be for big endian) and byte values, each optionally repeated with *count. Example: b\"AAAA\", p64(0x401234), 0x90*8", cat: Misc, input: true, output: true, parameter: substitute_expressions, FlatCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let items = parse_flat(&input).context("Unable to parse flat input")?;
+    Some(flat(&items)).transpose()
+});
+
+simple_cmd!("Format String", "Builds a format string payload writing values byte-wise with %hhn (64-bit). Syntax: offset@address=value[,address=value...][@size] with the argument offset of the payload and the bytes written per value (default 8)", cat: Misc, input: true, output: true, FmtStrCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let (offset, writes, word_size) = parse_fmtstr(&input)?;
+    Some(fmtstr_payload(offset, &writes, word_size)).transpose()
+});
+
+simple_cmd!("Shellcode", "Outputs linux shellcode that spawns /bin/sh. Input is the architecture: x86, amd64, arm or aarch64", cat: Misc, input: true, output: true, ShellcodeCmd => |self, state| {
+    let arch: Arch = String::from_utf8(self.msg.clone())?.parse()?;
+    Ok(Some(sh(arch)))
+});
+
+simple_cmd!("Generate Cyclic Sequence", "Generate cyclic sequence of given length. Syntax: length[:n[:alphabet]] with substring size n (default 4, n=8 supports lengths up to 65536) and alphabet (default abcd)", cat: Misc, input: true, output: true, CyclicCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let (len, n, alphabet) = parse_cyclic_input(&input)?;
+    let len: usize = len.trim().parse().context("Unable to parse len")?;
+    Some(cyclic(len, n, alphabet)).transpose()
+});
+
+simple_cmd!("Find Cyclic Substring", "Calculates the position of a substring given as hex value (e.g. a crashed register). Syntax: value[:n[:alphabet]] with substring size n (default 4) and alphabet (default abcd)", cat: Misc, input: true, output: true, CyclicFindCmd => |self, state| {
+    let input = String::from_utf8(self.msg.clone())?;
+    let (value, n, alphabet) = parse_cyclic_input(&input)?;
+    let value = value.trim();
+    let value = u64::from_str_radix(value.strip_prefix("0x").unwrap_or(value), 16)?;
+
+    // the value is stored little endian in memory, cut off or zero pad it to the substring size
+    let mut substring = value.to_le_bytes().to_vec();
+    substring.resize(n, 0);
+
+    let position = cyclic_find(&substring, n, alphabet).context(format!("Offset not found for {:#x} with n = {}", value, n))?;
+    Ok(Some(position.to_string().into_bytes()))
+});
+
+simple_cmd!("Assert", "Fails the recipe if the condition evaluates to 0 (e.g. $base & 0xfff == 0)", cat: Misc, input: true, output: false, parameter: substitute_expressions, AssertCmd => |self, state| {
+    let condition = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    Ast::new(&condition).context("Cannot parse condition")?.check(state)?;
+    Ok(None)
+});
+
+simple_cmd!("If", "Runs a custom ingredient if the condition evaluates to a non-zero integer. Syntax: condition@ingredient (e.g. $check == 0x1337@stage2)", cat: Misc, input: true, output: false, parameter: substitute_expressions, IfCmd => |self, state| {
+    let as_str = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    let (condition, name) = as_str.rsplit_once("@").context("Malformed If Cmd")?;
+    let value = Ast::new(condition)
+        .and_then(|ast| ast.get_int(state))
+        .context(format!("Invalid condition {}", condition.trim()))?;
+
+    if value != 0 {
+        CustomIngredient { path: name.trim().to_string() }.execute(state)?;
+    }
+    Ok(None)
+});
+
+// upper bound for Repeat so a typo does not hang the gui
+const MAX_REPEAT_COUNT: usize = 100_000;
+
+simple_cmd!("Repeat", "Runs a custom ingredient count times, the iteration index (starting at 0) is available in register i. Syntax: count@ingredient", cat: Misc, input: true, output: false, RepeatCmd => |self, state| {
+    let as_str = String::from_utf8(self.msg.clone()).context("Invalid utf8")?;
+    let (count, name) = as_str.split_once("@").context("Malformed Repeat Cmd")?;
+    let count = parse_number(count.trim())? as usize;
+    if count > MAX_REPEAT_COUNT {
+        bail!("Repeat count {} exceeds the maximum of {}", count, MAX_REPEAT_COUNT);
+    }
+
+    let ingredient = CustomIngredient { path: name.trim().to_string() };
+    for i in 0..count {
+        state.registers.set("i", i.to_string().into_bytes());
+        ingredient.execute(state).context(format!("Iteration {} of {} failed", i, name.trim()))?;
+    }
+    Ok(None)
+});
+
+// upper bound for custom ingredients running each other so a self-reference cannot overflow the stack
+const MAX_CUSTOM_DEPTH: usize = 64;
+
 pub struct CustomIngredient {
     path: String,
 }
@@ -307,12 +1207,23 @@ impl Command for CustomIngredient {
 
     fn execute(&self, state: &mut State) -> CmdResult {
         let path = format!("ingredients/{}", self.path);
-        let data = std::fs::read_to_string(&path).expect("Unable to read file");
-        let deserialized: Vec<IngredientView> = serde_json::from_str(&data).unwrap();
-        for ingredient in deserialized {
-            ingredient.run(state)?;
+        let data = std::fs::read_to_string(&path).context(format!("Unable to read {}", path))?;
+        let deserialized = parse_recipe(&data).context(format!("Invalid ingredient {}", path))?;
+        if state.custom_depth >= MAX_CUSTOM_DEPTH {
+            bail!(
+                "Custom ingredients are nested deeper than {} (does {} run itself?)",
+                MAX_CUSTOM_DEPTH,
+                self.path
+            );
         }
 
+        state.custom_depth += 1;
+        let result = deserialized
+            .into_iter()
+            .try_for_each(|ingredient| ingredient.run(state).map(|_| ()));
+        state.custom_depth -= 1;
+        result?;
+
         Ok(None)
     }
 
@@ -335,26 +1246,951 @@ impl Command for CustomIngredient {
     fn title() -> String {
         "Custom".to_string()
     }
-    fn from_parameter(param: &[u8], state: &State) -> Self {
-        CustomIngredient {
-            path: String::from_utf8(param.to_vec()).unwrap(),
-        }
+    fn from_parameter(param: &[u8], state: &State) -> Result<Self> {
+        Ok(CustomIngredient {
+            path: String::from_utf8(param.to_vec()).context("Invalid utf8")?,
+        })
     }
 }
 
 command_switch!(CommandType:
     "send" => SendCmd,
     "sendln" => SendLineCmd,
+    "sendafter" => SendAfterCmd,
+    "sendlineafter" => SendLineAfterCmd,
     "recv" => RecvCmd,
+    "peek" => PeekCmd,
     "recvuntil" => RecvUntil,
+    "recvuntil_timeout" => RecvUntilTimeoutCmd,
+    "recvuntil_any" => RecvUntilAnyCmd,
     "recvline" => RecvLineCmd,
+    "recvline_stripped" => RecvLineStrippedCmd,
+    "recvlines" => RecvLinesCmd,
+    "skip" => SkipCmd,
+    "clean" => CleanCmd,
     "sendpad" => SendPaddingCmd,
+    "interactive" => InteractiveCmd,
+    "close_input" => CloseInputCmd,
+    "wait_for_exit" => WaitForExitCmd,
     "attach_debugger" => AttachDbg,
     "get_symbol_address" => GetSymAddrCmd,
+    "set_binary_base" => SetBaseCmd,
+    "rebase" => RebaseCmd,
+    "symbol_from_address" => SymFromAddrCmd,
+    "read_binary_memory" => ReadVaddrCmd,
+    "checksec" => ChecksecCmd,
+    "disassemble" => DisassembleCmd,
+    "find_gadget" => FindGadgetCmd,
+    "find_string" => FindStringCmd,
+    "find_bytes" => FindBytesCmd,
     "log" => LogCmd,
+    "evaluate" => EvaluateCmd,
     "regex" => RegexCmd,
+    "xor" => XorCmd,
+    "rot13" => Rot13Cmd,
+    "rotate_bits" => RotateBitsCmd,
+    "enhex" => EnhexCmd,
+    "unhex" => UnhexCmd,
+    "base64enc" => Base64EncCmd,
+    "base64dec" => Base64DecCmd,
+    "urlencode" => UrlEncCmd,
+    "urldecode" => UrlDecCmd,
+    "crc32" => Crc32Cmd,
+    "adler32" => Adler32Cmd,
+    "hash" => HashCmd,
+    "solve_pow" => SolvePowCmd,
+    "gzip" => GzipCmd,
+    "gunzip" => GunzipCmd,
+    "zlib_compress" => ZlibCompressCmd,
+    "zlib_decompress" => ZlibDecompressCmd,
     "logregs" => LogRegCmd,
+    "hexdump_register" => HexdumpRegCmd,
+    "diff_registers" => DiffRegCmd,
+    "slice" => SliceCmd,
+    "concat" => ConcatCmd,
+    "shell" => ShellCmd,
+    "one_gadget" => OneGadgetCmd,
+    "identify_libc" => IdentifyLibcCmd,
+    "libc_base" => LibcBaseCmd,
+    "set_register_type" => SetRegTypeCmd,
+    "delete_register" => DeleteRegCmd,
+    "rename_register" => RenameRegCmd,
     "string_to_address" => StringToAddrCmd,
+    "pack_float" => PackFloatCmd,
+    "parse_int" => ParseIntCmd,
+    "unpack" => UnpackCmd,
+    "flat" => FlatCmd,
+    "fmtstr" => FmtStrCmd,
+    "shellcode" => ShellcodeCmd,
     "cyclic" => CyclicCmd,
     "cyclicfind" => CyclicFindCmd,
+    "repeat" => RepeatCmd,
+    "if" => IfCmd,
+    "assert" => AssertCmd,
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{Direction, Target};
+
+    /// local cat process the ingredients talk to
+    fn cat() -> State {
+        State::new(Target::Local, "cat", &[]).unwrap()
+    }
+
+    /// parses the input like the gui does and runs the ingredient
+    fn run<C: Command>(input: &[u8], state: &mut State) -> CmdResult {
+        C::from_parameter(input, state)?.execute(state)
+    }
+
+    #[test]
+    fn test_regex_groups() {
+        let mut state = cat();
+        state
+            .registers
+            .set("leak", b"user@host: leak=0x7ffd1234 canary=0xab00".to_vec());
+
+        // whole match by default
+        assert_eq!(
+            run::<RegexCmd>(b"leak@0x[0-9a-f]+", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"0x7ffd1234"
+        );
+
+        // indexed and named groups
+        assert_eq!(
+            run::<RegexCmd>(
+                b"leak@leak=(0x[0-9a-f]+) canary=(0x[0-9a-f]+)@2",
+                &mut state
+            )
+            .unwrap()
+            .unwrap(),
+            b"0xab00"
+        );
+        assert_eq!(
+            run::<RegexCmd>(b"leak@canary=(?P<canary>0x[0-9a-f]+)@canary", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"0xab00"
+        );
+
+        // an @ in the regex is not mistaken for a group
+        assert_eq!(
+            run::<RegexCmd>(b"leak@user@host", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"user@host"
+        );
+
+        // groups that did not participate or do not exist are errors
+        assert!(run::<RegexCmd>(b"leak@(nope)?leak@1", &mut state).is_err());
+        assert!(run::<RegexCmd>(b"leak@leak@3", &mut state).is_err());
+        assert!(run::<RegexCmd>(b"leak@nothing here", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_wait_for_exit() {
+        let mut state = State::new(Target::Local, "sh", &[]).unwrap();
+        assert!(run::<WaitForExitCmd>(b"50", &mut state).is_err());
+
+        state.program.send_line(b"kill -ABRT $$").unwrap();
+        let status = run::<WaitForExitCmd>(b"", &mut state).unwrap().unwrap();
+        assert!(status.starts_with(b"killed by signal 6 (SIGABRT"));
+        assert_eq!(state.registers.get("exit_signal").unwrap(), b"6");
+
+        let mut state = State::new(Target::Local, "true", &[]).unwrap();
+        assert_eq!(
+            run::<WaitForExitCmd>(b"", &mut state).unwrap().unwrap(),
+            b"exited with code 0"
+        );
+    }
+
+    #[test]
+    fn test_hexdump_register() {
+        let mut state = cat();
+        state.registers.set("leak", b"\x00\x11AB".to_vec());
+
+        assert_eq!(run::<HexdumpRegCmd>(b"leak", &mut state).unwrap(), None);
+        assert!(run::<HexdumpRegCmd>(b"missing", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_diff_registers() {
+        let mut state = cat();
+        state.registers.set("expected", b"AAAABBBB".to_vec());
+        state.registers.set("echoed", b"AAAABBB".to_vec());
+
+        assert_eq!(
+            run::<DiffRegCmd>(b"expected@echoed", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"7"
+        );
+        assert_eq!(
+            run::<DiffRegCmd>(b"expected @ expected", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"-1"
+        );
+
+        assert!(run::<DiffRegCmd>(b"expected@missing", &mut state).is_err());
+        assert!(run::<DiffRegCmd>(b"expected", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_slice() {
+        let mut state = cat();
+        state
+            .registers
+            .set("leak", b"junk\x10\x20\x30\x40junk".to_vec());
+
+        assert_eq!(
+            run::<SliceCmd>(b"leak@4:8", &mut state).unwrap().unwrap(),
+            b"\x10\x20\x30\x40"
+        );
+        assert_eq!(
+            run::<SliceCmd>(b"leak@-4:", &mut state).unwrap().unwrap(),
+            b"junk"
+        );
+        assert_eq!(
+            run::<SliceCmd>(b"leak@3:3", &mut state).unwrap().unwrap(),
+            b""
+        );
+
+        assert!(run::<SliceCmd>(b"leak@4:100", &mut state).is_err());
+        assert!(run::<SliceCmd>(b"leak@4", &mut state).is_err());
+        assert!(run::<SliceCmd>(b"leak@a:b", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_concat() {
+        let mut state = cat();
+        state.registers.set("padding", b"AAAA".to_vec());
+        state.registers.set("ret", b"\x36\x12\x40".to_vec());
+
+        let cmd = ConcatCmd::from_parameter(br#"padding, ret,{"/bin/sh, x"}, {b"\x00"}"#, &state)
+            .unwrap();
+        assert_eq!(
+            cmd.execute(&mut state).unwrap().unwrap(),
+            b"AAAA\x36\x12\x40/bin/sh, x\x00"
+        );
+
+        assert!(run::<ConcatCmd>(b"padding, missing", &mut state).is_err());
+        assert!(run::<ConcatCmd>(b"padding ret", &mut state).is_err());
+        assert_eq!(run::<ConcatCmd>(b"", &mut state).unwrap().unwrap(), b"");
+    }
+
+    #[test]
+    fn test_parse_int() {
+        let mut state = cat();
+        state.registers.set("hex", b" 0xdeadbeef\n".to_vec());
+        state.registers.set("dec", b"4660".to_vec());
+        assert_eq!(
+            run::<ParseIntCmd>(b"hex", &mut state).unwrap().unwrap(),
+            b"\xef\xbe\xad\xde\x00\x00\x00\x00"
+        );
+        assert_eq!(
+            run::<ParseIntCmd>(b"hex@16@p32", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"\xef\xbe\xad\xde"
+        );
+        assert_eq!(
+            run::<ParseIntCmd>(b"dec@10@p16", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"\x34\x12"
+        );
+        assert_eq!(
+            run::<ParseIntCmd>(b"dec@16@p16", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"\x60\x46"
+        );
+        assert_eq!(
+            run::<ParseIntCmd>(b"dec@auto@p8", &mut state)
+                .unwrap_err()
+                .to_string(),
+            "Value 0x1234 does not fit into 8 bits"
+        );
+
+        assert_eq!(
+            run::<ParseIntCmd>(b"dec@10@p32@be", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"\x00\x00\x12\x34"
+        );
+        assert!(run::<ParseIntCmd>(b"dec@10@p32@pdp", &mut state).is_err());
+
+        assert!(run::<ParseIntCmd>(b"hex@10", &mut state).is_err());
+        assert!(run::<ParseIntCmd>(b"hex@2", &mut state).is_err());
+        assert!(run::<ParseIntCmd>(b"hex@16@p24", &mut state).is_err());
+        assert!(run::<ParseIntCmd>(b"missing", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_crc32() {
+        let mut state = cat();
+        state.registers.set("payload", b"123456789".to_vec());
+
+        assert_eq!(
+            run::<Crc32Cmd>(b"payload", &mut state).unwrap().unwrap(),
+            b"\x26\x39\xf4\xcb"
+        );
+        assert_eq!(
+            run::<Crc32Cmd>(b"payload@be", &mut state).unwrap().unwrap(),
+            b"\xcb\xf4\x39\x26"
+        );
+        assert!(run::<Crc32Cmd>(b"missing", &mut state).is_err());
+        assert!(run::<Crc32Cmd>(b"payload@me", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_hash() {
+        let mut state = cat();
+        state.registers.set("nonce", b"abc".to_vec());
+
+        assert_eq!(
+            run::<HashCmd>(b"nonce@md5@hex", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"900150983cd24fb0d6963f7d28e17f72"
+        );
+        let digest = run::<HashCmd>(b"nonce@sha256", &mut state)
+            .unwrap()
+            .unwrap();
+        assert_eq!(digest.len(), 32);
+        assert_eq!(
+            run::<HashCmd>(b"nonce@sha256@raw", &mut state)
+                .unwrap()
+                .unwrap(),
+            digest
+        );
+
+        assert!(run::<HashCmd>(b"nonce@sha512", &mut state).is_err());
+        assert!(run::<HashCmd>(b"nonce@sha1@base64", &mut state).is_err());
+        assert!(run::<HashCmd>(b"missing@md5", &mut state).is_err());
+        assert!(run::<HashCmd>(b"nonce", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_solve_pow() {
+        let mut state = cat();
+        state.registers.set("challenge", b"s3cr3t".to_vec());
+
+        let suffix = run::<SolvePowCmd>(b"challenge@sha256@8", &mut state)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            hash(HashAlgorithm::Sha256, &[&b"s3cr3t"[..], &suffix].concat())[0],
+            0
+        );
+
+        assert!(run::<SolvePowCmd>(b"challenge@sha256", &mut state).is_err());
+        assert!(run::<SolvePowCmd>(b"challenge@crc@8", &mut state).is_err());
+        assert!(run::<SolvePowCmd>(b"missing@md5@8", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_unpack() {
+        let mut state = cat();
+        state
+            .registers
+            .set("leak", b"\x10\x32\x54\x76\xfd\x7f".to_vec());
+
+        // cat is a little endian binary
+        assert_eq!(
+            run::<UnpackCmd>(b"leak", &mut state).unwrap().unwrap(),
+            0x7ffd76543210u64.to_string().as_bytes()
+        );
+        assert_eq!(
+            run::<UnpackCmd>(b"leak@le", &mut state).unwrap().unwrap(),
+            0x7ffd76543210u64.to_string().as_bytes()
+        );
+        assert_eq!(
+            run::<UnpackCmd>(b"leak@be", &mut state).unwrap().unwrap(),
+            0x10325476fd7fu64.to_string().as_bytes()
+        );
+
+        state.registers.set("long", vec![0; 9]);
+        assert!(run::<UnpackCmd>(b"long", &mut state).is_err());
+        assert!(run::<UnpackCmd>(b"missing", &mut state).is_err());
+        assert!(run::<UnpackCmd>(b"leak@me", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut state = cat();
+        state.program.send(b"abc\n").unwrap();
+
+        assert_eq!(run::<PeekCmd>(b"2", &mut state).unwrap().unwrap(), b"ab");
+        assert!(state.output.is_empty());
+
+        assert_eq!(
+            run::<RecvLineCmd>(b"", &mut state).unwrap().unwrap(),
+            b"abc\n"
+        );
+    }
+
+    #[test]
+    fn test_recv_lines() {
+        let mut state = cat();
+        state.program.send(b"menu\n1) a\n2) b\n> ").unwrap();
+
+        assert_eq!(
+            run::<RecvLinesCmd>(b"2", &mut state).unwrap().unwrap(),
+            b"menu\n1) a\n"
+        );
+        assert_eq!(state.output, b"menu\n1) a\n");
+
+        // the output ends before the third line is complete
+        state.program.close_stdin().unwrap();
+        assert_eq!(
+            run::<RecvLinesCmd>(b"3", &mut state).unwrap().unwrap(),
+            b"2) b\n> "
+        );
+        assert_eq!(state.output, b"menu\n1) a\n2) b\n> ");
+
+        assert!(run::<RecvLinesCmd>(b"x", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_recv_until_any() {
+        let mut state = cat();
+        state.program.send(b"Invalid choice\n> ok> ").unwrap();
+
+        assert_eq!(
+            run::<RecvUntilAnyCmd>(b"> |Invalid", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"Invalid"
+        );
+        assert_eq!(state.registers.get("matched").unwrap(), b"1");
+        assert_eq!(
+            run::<RecvUntilAnyCmd>(b"> |Invalid", &mut state)
+                .unwrap()
+                .unwrap(),
+            b" choice\n> "
+        );
+        assert_eq!(state.registers.get("matched").unwrap(), b"0");
+        assert_eq!(state.output, b"Invalid choice\n> ");
+
+        assert!(run::<RecvUntilAnyCmd>(b"> ||x", &mut state).is_err());
+        assert!(run::<RecvUntilAnyCmd>(b"", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_skip() {
+        let mut state = cat();
+        state.program.send(b"\x7f\x00HDRpayload").unwrap();
+
+        assert!(run::<SkipCmd>(b"5", &mut state).unwrap().is_none());
+        assert!(state.output.is_empty());
+
+        assert_eq!(
+            run::<RecvCmd>(b"7", &mut state).unwrap().unwrap(),
+            b"payload"
+        );
+        assert!(run::<SkipCmd>(b"-1", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_recv_until_timeout() {
+        let mut state = cat();
+        state.program.send(b"abc> def").unwrap();
+
+        assert_eq!(
+            run::<RecvUntilTimeoutCmd>(b"1000@> ", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"abc> "
+        );
+
+        // the sequence never arrives, the partial data is kept
+        assert_eq!(
+            run::<RecvUntilTimeoutCmd>(b"100@X", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"def"
+        );
+        assert_eq!(state.output, b"abc> def");
+
+        assert!(run::<RecvUntilTimeoutCmd>(b"100", &mut state).is_err());
+        assert!(run::<RecvUntilTimeoutCmd>(b"100@", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_set_binary_base() {
+        let mut state = cat();
+        state.registers.set("leak", b"93824992235833".to_vec());
+
+        run::<SetBaseCmd>(b"{$leak - 0x1139}", &mut state).unwrap();
+        assert_eq!(state.binary_base, Some(0x555555554000));
+
+        let cmd = EvaluateCmd::from_parameter(b"base() + 0x4010", &state).unwrap();
+        assert_eq!(
+            cmd.execute(&mut state).unwrap().unwrap(),
+            format!("{}", 0x555555558010u64).into_bytes()
+        );
+
+        assert!(run::<SetBaseCmd>(b"base", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut state = cat();
+        state.registers.set("base", b"4096".to_vec());
+
+        assert_eq!(
+            run::<EvaluateCmd>(b"$base + 0x1234", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"8756"
+        );
+
+        assert!(run::<EvaluateCmd>(b"$missing + 1", &mut state).is_err());
+        assert!(run::<EvaluateCmd>(b"1 +", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_output_keeps_raw_bytes() {
+        let mut state = cat();
+
+        let payload = b"A\xff\x00\x90";
+        run::<SendLineCmd>(payload, &mut state).unwrap();
+        let received = run::<RecvLineCmd>(b"", &mut state).unwrap().unwrap();
+
+        assert_eq!(received, b"A\xff\x00\x90\n");
+        assert_eq!(state.output, received);
+    }
+
+    /// custom ingredient saved by a test, the file is removed when it is dropped (even if an
+    /// assert failed) so it does not show up in the gui
+    struct TestIngredient {
+        name: String,
+        path: String,
+    }
+
+    impl Drop for TestIngredient {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    /// saves a custom ingredient with a single Evaluate step
+    fn write_custom_ingredient(prefix: &str, script: &str, output: &str) -> TestIngredient {
+        let mut step = IngredientView::new::<EvaluateCmd>();
+        step.input = script.to_string();
+        step.output = output.to_string();
+
+        let ingredient = test_ingredient(prefix);
+        save_steps(&ingredient, &[step]);
+        ingredient
+    }
+
+    /// name and path of a custom ingredient unique to this test process
+    fn test_ingredient(prefix: &str) -> TestIngredient {
+        let name = format!("{}_{}", prefix, std::process::id());
+        TestIngredient {
+            path: format!("ingredients/{}", name),
+            name,
+        }
+    }
+
+    fn save_steps(ingredient: &TestIngredient, steps: &[IngredientView]) {
+        std::fs::create_dir_all("ingredients/").unwrap();
+        std::fs::write(&ingredient.path, serde_json::to_string(steps).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_assert() {
+        let mut state = cat();
+        let run_assert = |condition: &str, state: &mut State| {
+            AssertCmd::from_parameter(condition.as_bytes(), state)?.execute(state)
+        };
+
+        state.registers.set("x", b"16".to_vec());
+        assert!(run_assert("$x == 0x10", &mut state).is_ok());
+
+        state.registers.set("x", b"4096".to_vec());
+        let error = run_assert("$x == 0x10", &mut state).unwrap_err();
+        assert!(format!("{:#}", error).contains("0x1000 == 0x10"));
+        assert!(run_assert("$x ==", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_if() {
+        let mut state = cat();
+        state.registers.set("check", b"4919".to_vec());
+        state.registers.set("ran", b"0".to_vec());
+
+        let ingredient = write_custom_ingredient("test_if", "$ran + 1", "ran");
+        let mut run_if = |condition: &str| {
+            IfCmd::from_parameter(
+                format!("{}@{}", condition, ingredient.name).as_bytes(),
+                &state,
+            )?
+            .execute(&mut state)
+        };
+        let results = vec![
+            run_if("$check == 0x1337"),
+            run_if("$check != 0x1337"),
+            run_if("$missing == 1"),
+            run_if("b\"AAAA\""),
+            run_if("$check =="),
+        ];
+
+        assert!(results[0].is_ok());
+        assert!(results[1].is_ok());
+        assert_eq!(state.registers.get("ran").unwrap(), b"1");
+
+        // broken conditions halt instead of silently picking a branch
+        assert!(results[2..].iter().all(|r| r.is_err()));
+    }
+
+    #[test]
+    fn test_custom_recursion() {
+        let mut state = cat();
+        state.registers.set("calls", b"0".to_vec());
+
+        // counts its calls and then runs itself again
+        let ingredient = test_ingredient("test_custom_recursion");
+        let mut count = IngredientView::new::<EvaluateCmd>();
+        count.input = "$calls + 1".to_string();
+        count.output = "calls".to_string();
+        let mut recurse = IngredientView::new::<RepeatCmd>();
+        recurse.input = format!("1@{}", ingredient.name);
+        save_steps(&ingredient, &[count, recurse]);
+
+        let result = CustomIngredient {
+            path: ingredient.name.clone(),
+        }
+        .execute(&mut state);
+
+        let error = format!("{:#}", result.unwrap_err());
+        assert!(error.contains("nested deeper than"), "{}", error);
+        assert_eq!(
+            state.registers.get("calls").unwrap(),
+            MAX_CUSTOM_DEPTH.to_string().as_bytes()
+        );
+        assert_eq!(state.custom_depth, 0);
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mut state = cat();
+        state.registers.set("acc", b"0".to_vec());
+
+        // sums up the iteration indices
+        let ingredient = write_custom_ingredient("test_repeat", "$acc + $i", "acc");
+
+        let repeat = |count: &str, state: &mut State| {
+            RepeatCmd::from_parameter(format!("{}@{}", count, ingredient.name).as_bytes(), state)?
+                .execute(state)
+        };
+        let result = repeat("5", &mut state);
+        let too_many = repeat("1000000", &mut state);
+
+        result.unwrap();
+        assert_eq!(state.registers.get("acc").unwrap(), b"10");
+        assert_eq!(state.registers.get("i").unwrap(), b"4");
+        assert!(too_many.is_err());
+
+        // missing ingredients and failing iterations are errors
+        assert!(run::<RepeatCmd>(b"2@no_such_ingredient", &mut state).is_err());
+        assert!(run::<RepeatCmd>(b"2", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_parse_padding() {
+        assert_eq!(parse_padding("4").unwrap(), b"AAAA");
+        assert_eq!(parse_padding(" 3:90").unwrap(), b"\x90\x90\x90");
+        assert_eq!(parse_padding("2:0x00").unwrap(), b"\x00\x00");
+        assert_eq!(parse_padding("0:41").unwrap(), b"");
+        assert_eq!(parse_padding("12:cyclic").unwrap(), b"aaaabaaacaaa");
+
+        assert!(parse_padding("x").is_err());
+        assert!(parse_padding("4:100").is_err());
+        assert!(parse_padding("4:nop").is_err());
+    }
+
+    #[test]
+    fn test_send_padding() {
+        let mut state = cat();
+        run::<SendPaddingCmd>(b"3:42", &mut state).unwrap();
+        run::<SendPaddingCmd>(b"8:cyclic", &mut state).unwrap();
+        run::<SendPaddingCmd>(b"2", &mut state).unwrap();
+        state.program.send(b"\n").unwrap();
+        assert_eq!(state.program.recv_line().unwrap(), b"BBBaaaabaaaAA\n");
+    }
+
+    #[test]
+    fn test_one_gadget() {
+        let path = "test_data/libc-2.27-64.so";
+        let static_search = |input: &str| pick_one_gadget(input, path, |_| None);
+
+        // without the tool the candidates come from the static search
+        assert_eq!(
+            static_search("0x7f0000000000@test_data/libc-2.27-64.so").unwrap(),
+            0x7f0000000000 + 0x4f432
+        );
+        assert_eq!(static_search("0@@3").unwrap(), 0x10a41c);
+        assert!(static_search("0@@4").is_err());
+        assert!(static_search("0@test_data/libc-2.27-32.so").is_err());
+        assert!(static_search("test_data/libc-2.27-64.so").is_err());
+
+        // the tool takes precedence, its errors are not hidden by the fallback
+        let tool = |input: &str| pick_one_gadget(input, path, |_| Some(Ok(vec![0x10, 0x20])));
+        assert_eq!(tool("0x1000@@1").unwrap(), 0x1020);
+        assert!(pick_one_gadget("0@", path, |_| Some(Err(anyhow::anyhow!("failed")))).is_err());
+
+        let mut state = cat();
+        assert!(run::<OneGadgetCmd>(b"0@test_data/libc-2.27-64.so@x", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_find_bytes() {
+        let mut state = cat();
+        state.program_path = "test_data/libc-2.27-64.so".to_string();
+
+        assert_eq!(
+            run::<FindBytesCmd>(b"2f62696e 2f736800", &mut state)
+                .unwrap()
+                .unwrap(),
+            (0x1b3e1au64).to_string().as_bytes()
+        );
+        state.binary_base = Some(0x7f0000000000);
+        assert_eq!(
+            run::<FindBytesCmd>(b"2f62696e2f736800", &mut state)
+                .unwrap()
+                .unwrap(),
+            (0x7f00001b3e1au64).to_string().as_bytes()
+        );
+
+        assert!(run::<FindBytesCmd>(b"2f6", &mut state).is_err());
+        assert!(run::<FindBytesCmd>(b"", &mut state).is_err());
+        assert!(
+            run::<FindBytesCmd>(b"ffffffffffffffffffffffffffffffffffffffff", &mut state).is_err()
+        );
+    }
+
+    #[test]
+    fn test_find_string() {
+        let mut state = cat();
+        state.program_path = "test_data/libc-2.27-64.so".to_string();
+
+        assert_eq!(
+            run::<FindStringCmd>(b"/bin/sh", &mut state)
+                .unwrap()
+                .unwrap(),
+            (0x1b3e1au64).to_string().as_bytes()
+        );
+        // the needle may be part of a longer string
+        assert_eq!(
+            run::<FindStringCmd>(b"bin/sh", &mut state)
+                .unwrap()
+                .unwrap(),
+            (0x1b3e1bu64).to_string().as_bytes()
+        );
+
+        state.binary_base = Some(0x7f0000000000);
+        assert_eq!(
+            run::<FindStringCmd>(b"/bin/sh", &mut state)
+                .unwrap()
+                .unwrap(),
+            (0x7f00001b3e1au64).to_string().as_bytes()
+        );
+
+        assert!(run::<FindStringCmd>(b"not in the libc at all", &mut state).is_err());
+        assert!(run::<FindStringCmd>(b"", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_libc_base() {
+        let mut state = cat();
+        state
+            .registers
+            .set("raw", b"\xa0\x0a\x58\x34\x12\x7f".to_vec());
+        state.registers.set("text", b"0x7f1234580aa0\n".to_vec());
+
+        let base = (0x7f1234500000u64).to_string().into_bytes();
+        assert_eq!(
+            run::<LibcBaseCmd>(b"raw@puts@test_data/libc-2.27-64.so", &mut state)
+                .unwrap()
+                .unwrap(),
+            base
+        );
+        assert_eq!(
+            run::<LibcBaseCmd>(b"text@puts@test_data/libc-2.27-64.so", &mut state)
+                .unwrap()
+                .unwrap(),
+            base
+        );
+        assert_eq!(
+            run::<LibcBaseCmd>(b"0x7f1234580aa0@puts@test_data/libc-2.27-64.so", &mut state)
+                .unwrap()
+                .unwrap(),
+            base
+        );
+        // a wrong symbol still gives a (misaligned) base
+        assert_eq!(
+            run::<LibcBaseCmd>(b"raw@printf@test_data/libc-2.27-64.so", &mut state)
+                .unwrap()
+                .unwrap(),
+            (0x7f1234580aa0u64 - 0x64f70).to_string().as_bytes()
+        );
+
+        assert!(run::<LibcBaseCmd>(b"0x10@puts@test_data/libc-2.27-64.so", &mut state).is_err());
+        assert!(
+            run::<LibcBaseCmd>(b"raw@not_a_symbol@test_data/libc-2.27-64.so", &mut state).is_err()
+        );
+        assert!(run::<LibcBaseCmd>(b"missing@puts@test_data/libc-2.27-64.so", &mut state).is_err());
+        assert!(run::<LibcBaseCmd>(b"raw@puts", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_shell() {
+        let mut state = cat();
+        state.registers.set("data", b"abc\ndef\n".to_vec());
+
+        let error = run::<ShellCmd>(b"data@rev", &mut state)
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Shell commands are disabled"));
+
+        state.allow_shell = true;
+        assert_eq!(
+            run::<ShellCmd>(b"data@rev", &mut state).unwrap().unwrap(),
+            b"cba\nfed\n"
+        );
+        // braces are passed to the shell unchanged
+        assert_eq!(
+            run::<ShellCmd>(b"data@awk '{print $1 \"!\"}' | head -n1", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"abc!\n"
+        );
+        assert_eq!(
+            run::<ShellCmd>(b"@echo -n hi", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"hi"
+        );
+
+        let error = run::<ShellCmd>(b"@echo oops >&2; exit 3", &mut state)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Command 'echo oops >&2; exit 3' exited with code 3: oops"
+        );
+        assert!(run::<ShellCmd>(b"missing@cat", &mut state).is_err());
+        assert!(run::<ShellCmd>(b"cat", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_send_after() {
+        let mut state = cat();
+        state.registers.set("name", b"admin".to_vec());
+        state.program.send(b"user{1}: ").unwrap();
+
+        // braces in the delimiter are taken literally, the payload is substituted
+        run::<SendLineAfterCmd>(b"{1}: ||{$name}", &mut state).unwrap();
+        assert_eq!(state.output, b"user{1}: ");
+        assert_eq!(state.program.recv_line().unwrap(), b"admin\n");
+        assert_eq!(
+            state.transcript.chunks,
+            vec![
+                (Direction::Received, b"user{1}: ".to_vec()),
+                (Direction::Sent, b"admin\n".to_vec())
+            ]
+        );
+
+        state.program.send(b"> ").unwrap();
+        run::<SendAfterCmd>(b"> ||a||b", &mut state).unwrap();
+        assert_eq!(state.program.recv(4).unwrap(), b"a||b");
+
+        assert!(SendAfterCmd::from_parameter(b"no separator", &state).is_err());
+    }
+
+    #[test]
+    fn test_interactive() {
+        let mut state = cat();
+        state.program.send_line(b"pending").unwrap();
+
+        // output that arrived before switching to interactive mode is shown
+        run::<InteractiveCmd>(b"", &mut state).unwrap();
+        assert!(state.interactive);
+        assert_eq!(state.output, b"pending\n");
+    }
+
+    #[test]
+    fn test_attach_debugger_script() {
+        let mut state = cat();
+        state.registers.set("main_addr", b"0x401136".to_vec());
+
+        let cmd = AttachDbg::from_parameter(br"b *{$main_addr}\ncontinue", &state).unwrap();
+        assert_eq!(cmd.msg, b"b *0x401136\ncontinue");
+    }
+
+    #[test]
+    fn test_escaped_input() {
+        let mut state = cat();
+        state.registers.set("raw", b"\\x41{1}".to_vec());
+
+        let msg = |param: &[u8]| LogCmd::from_parameter(param, &state).map(|cmd| cmd.msg);
+        assert_eq!(msg(br"\x41\x41").unwrap(), b"AA");
+        assert_eq!(msg(br"\x00\t\\n").unwrap(), b"\x00\t\\n");
+        assert!(msg(br"\x4").is_err());
+
+        // escapes are decoded around expressions, expression results are inserted verbatim
+        assert_eq!(msg(br#"\x41{b"\x42"}\x43"#).unwrap(), b"ABC");
+        assert_eq!(msg(br"\n{$raw}").unwrap(), b"\n\\x41{1}");
+
+        // inputs with their own backslash syntax are left alone
+        let regex = RegexCmd::from_parameter(br"raw@\\x(\d+)", &state).unwrap();
+        assert_eq!(regex.msg, br"raw@\\x(\d+)");
+
+        // NUL bytes are transmitted
+        run::<SendCmd>(br"\x00A\x00\n", &mut state).unwrap();
+        assert_eq!(state.program.recv_line().unwrap(), b"\x00A\x00\n");
+    }
+
+    #[test]
+    fn test_from_parameter_substitution() {
+        let mut state = cat();
+        state.registers.set("num", b"41".to_vec());
+
+        assert_eq!(
+            run::<LogCmd>(b"value: {$num + 1}", &mut state)
+                .unwrap()
+                .unwrap(),
+            b"value: 42"
+        );
+
+        // malformed or failing templates are errors instead of panics
+        assert!(LogCmd::from_parameter(b"{ $nope + }", &state).is_err());
+        assert!(LogCmd::from_parameter(b"{ $nope + 1 }", &state).is_err());
+        assert!(parse_command("log", b"{ $nope + }", &state).is_err());
+        assert!(create_command(CommandType::LogCmd, b"{ $nope + }", &state).is_err());
+    }
+
+    #[test]
+    fn test_parse_cyclic_input() {
+        assert_eq!(
+            parse_cyclic_input("100").unwrap(),
+            ("100", 4, DEFAULT_ALPHABET)
+        );
+        assert_eq!(
+            parse_cyclic_input("100:3:xyz").unwrap(),
+            ("100", 3, &b"xyz"[..])
+        );
+        assert!(parse_cyclic_input("100:x").is_err());
+
+        // a repeated byte would make the pattern ambiguous
+        let error = parse_cyclic_input("100:4:aab").unwrap_err();
+        assert_eq!(error.to_string(), "Duplicate byte 'a' in the alphabet");
+    }
+}
@@ -0,0 +1,123 @@
+use crate::theme::Theme;
+use anyhow::{Context, Result};
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+pub const CONFIG_PATH: &str = "config.json";
+/// number of programs kept in the recent programs list
+pub const MAX_RECENT_PROGRAMS: usize = 10;
+
+/// program and mode of the last start, pre-filled when choosing a program
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LastProgram {
+    /// program path, remote command or connection string (host:port) in network mode
+    pub name: String,
+    pub is_network: bool,
+    pub is_pty: bool,
+    pub is_ssh: bool,
+    pub ssh_host: String,
+    pub ssh_user: String,
+}
+
+/// settings that are kept across restarts of the gui
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub theme: Theme,
+    /// whether the Shell ingredient may run commands, only enabled by editing the config file
+    pub allow_shell: bool,
+    pub last_program: LastProgram,
+    /// names of the most recently started programs, newest first
+    pub recent_programs: Vec<String>,
+}
+
+impl Config {
+    pub fn load() -> Self {
+        Self::load_from(CONFIG_PATH)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(CONFIG_PATH)
+    }
+
+    /// remember a started program, moving it to the front if it was started before
+    pub fn add_recent_program(&mut self, name: &str) {
+        self.recent_programs.retain(|recent| recent != name);
+        self.recent_programs.insert(0, name.to_string());
+        self.recent_programs.truncate(MAX_RECENT_PROGRAMS);
+    }
+
+    /// a missing or broken config file falls back to the defaults
+    pub fn load_from(path: &str) -> Self {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(_) => return Config::default(),
+        };
+
+        serde_json::from_str(&data).unwrap_or_else(|e| {
+            debug!("Ignoring invalid config {}: {}", path, e);
+            Config::default()
+        })
+    }
+
+    pub fn save_to(&self, path: &str) -> Result<()> {
+        let serialized =
+            serde_json::to_string_pretty(self).context("Failed to serialize config")?;
+        fs::write(path, serialized).context(format!("Unable to write {}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_roundtrip() {
+        let path =
+            std::env::temp_dir().join(format!("bochumoxide_config_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        assert_eq!(Config::load_from(path), Config::default());
+
+        let config = Config {
+            theme: Theme::Dark,
+            allow_shell: true,
+            last_program: LastProgram {
+                name: "localhost:1337".to_string(),
+                is_network: true,
+                ..LastProgram::default()
+            },
+            recent_programs: vec!["localhost:1337".to_string(), "./vuln".to_string()],
+        };
+        config.save_to(path).unwrap();
+        assert_eq!(Config::load_from(path), config);
+
+        // unknown or missing keys do not break loading
+        fs::write(path, r#"{"unknown": 1}"#).unwrap();
+        assert_eq!(Config::load_from(path), Config::default());
+        fs::write(path, "not json").unwrap();
+        assert_eq!(Config::load_from(path), Config::default());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_add_recent_program() {
+        let mut config = Config::default();
+        config.add_recent_program("./a");
+        config.add_recent_program("./b");
+        config.add_recent_program("./a");
+        assert_eq!(config.recent_programs, vec!["./a", "./b"]);
+
+        for i in 0..2 * MAX_RECENT_PROGRAMS {
+            config.add_recent_program(&i.to_string());
+        }
+        assert_eq!(config.recent_programs.len(), MAX_RECENT_PROGRAMS);
+        assert_eq!(
+            config.recent_programs[0],
+            (2 * MAX_RECENT_PROGRAMS - 1).to_string()
+        );
+    }
+}
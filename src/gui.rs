@@ -1,31 +1,110 @@
 use crate::command::{CommandCategory, CustomIngredient};
+use anyhow::{Context, Result};
 use std::fs;
-use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
 
 use log::*;
 
+use crate::binary_handling::{filter_symbols, symbol_list};
 use crate::command::available_categories;
-use crate::recipe::{CategoryView, IngredientView};
-use crate::utils::State;
+use crate::config::{Config, LastProgram};
+use crate::log::LogBuffer;
+use crate::misc::fiddling::hexdump;
+use crate::program_io::{parse_env, DebugMode, LocalOptions, DEFAULT_GDBSERVER_PORT};
+#[cfg(feature = "ssh2")]
+use crate::program_io::{SshAuth, SshParams};
+use crate::recipe::{
+    parse_recipe, run_recipe, run_until_success, serialize_recipe, CategoryView, IngredientStyle,
+    IngredientView,
+};
+use crate::theme::Theme;
 use crate::utils::Target;
+use crate::utils::{Direction, State, Transcript};
 use iced::{
-    button, executor, pick_list, scrollable, text_input, Align, Application, Button, Checkbox,
-    Clipboard, Column, Command, Container, Element, Length, PickList, Row, Rule, Scrollable, Text,
-    TextInput,
+    button, container, executor, pick_list, scrollable, text_input, Align, Application, Button,
+    Checkbox, Clipboard, Color, Column, Command, Container, Element, Length, PickList, Row, Rule,
+    Scrollable, Text, TextInput,
 };
 
+/// default number of runs of Run until success
+const DEFAULT_MAX_ATTEMPTS: usize = 10;
+
+/// number of symbols listed in the symbol panel, the rest has to be found through the search
+const MAX_SHOWN_SYMBOLS: usize = 500;
+
 pub enum Scene {
     ChooseProgram,
     Recipe,
 }
 
+/// file operation that waits for the user to confirm it
+pub enum PendingAction {
+    DeleteRecipe(String),
+    DeleteIngredient(String),
+    OverwriteRecipe(String),
+    OverwriteIngredient(String),
+}
+
+impl PendingAction {
+    fn path(&self) -> String {
+        match self {
+            PendingAction::DeleteRecipe(name) | PendingAction::OverwriteRecipe(name) => {
+                format!("recipes/{}", name)
+            }
+            PendingAction::DeleteIngredient(name) | PendingAction::OverwriteIngredient(name) => {
+                format!("ingredients/{}", name)
+            }
+        }
+    }
+
+    fn prompt(&self) -> String {
+        match self {
+            PendingAction::DeleteRecipe(name) => format!("Delete recipe '{}'?", name),
+            PendingAction::DeleteIngredient(name) => format!("Delete ingredient '{}'?", name),
+            PendingAction::OverwriteRecipe(name) => {
+                format!("Recipe '{}' already exists. Overwrite it?", name)
+            }
+            PendingAction::OverwriteIngredient(name) => {
+                format!("Ingredient '{}' already exists. Overwrite it?", name)
+            }
+        }
+    }
+
+    fn confirm_label(&self) -> &'static str {
+        match self {
+            PendingAction::DeleteRecipe(_) | PendingAction::DeleteIngredient(_) => "Delete",
+            PendingAction::OverwriteRecipe(_) | PendingAction::OverwriteIngredient(_) => {
+                "Overwrite"
+            }
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct GuiState {
     program_name: text_input::State,
+    recent_programs: pick_list::State<String>,
+    ssh_host: text_input::State,
+    ssh_user: text_input::State,
+    ssh_secret: text_input::State,
+    env_vars: text_input::State,
+    working_dir: text_input::State,
+    ingredient_filter: text_input::State,
+    symbol_filter: text_input::State,
+    interactive_input: text_input::State,
+    gdbserver_port: text_input::State,
+    debug_terminal: text_input::State,
+    interactive_send: button::State,
+    interactive_receive: button::State,
+    interactive_leave: button::State,
     start_button: button::State,
     run_all: button::State,
+    run_until_success: button::State,
+    max_attempts: text_input::State,
     recipe_scrollable: scrollable::State,
     ingredient_scrollable: scrollable::State,
+    symbol_scrollable: scrollable::State,
     debug_scrollable: scrollable::State,
     program_output_scrollable: scrollable::State,
     load_recipe_file: pick_list::State<String>,
@@ -33,6 +112,10 @@ pub struct GuiState {
     load_recipe: button::State,
     save_recipe: button::State,
     save_ingredient: button::State,
+    toggle_theme: button::State,
+    delete_recipe: button::State,
+    confirm_pending: button::State,
+    cancel_pending: button::State,
 }
 pub struct App {
     current_scene: Scene,
@@ -40,13 +123,51 @@ pub struct App {
     should_exit: bool,
     state: Option<State>,
     debug_output: String,
+    /// recent log lines, shown as debug output
+    log_buffer: LogBuffer,
     program_output: String,
     category_list: Vec<CategoryView>,
     recipe: Vec<IngredientView>,
     save_recipe_name: String,
     load_recipe_name: String,
     program_name: String,
+    /// programs started before, newest first
+    recent_programs: Vec<String>,
     is_network: bool,
+    is_pty: bool,
+    is_ssh: bool,
+    ssh_host: String,
+    ssh_user: String,
+    ssh_secret: String,
+    ssh_use_key: bool,
+    /// environment variables of local programs (KEY=VALUE separated by whitespace)
+    env_vars: String,
+    clear_env: bool,
+    /// working directory of local programs, empty to keep our own
+    working_dir: String,
+    /// id of the ingredient that failed during the last run and the error chain
+    recipe_error: Option<(usize, String)>,
+    restart_on_error: bool,
+    /// runs of Run until success before giving up
+    max_attempts: String,
+    /// number of runs the last Run until success took
+    attempts_status: Option<String>,
+    /// attach the debugger through gdbserver instead of a local terminal
+    use_gdbserver: bool,
+    gdbserver_port: String,
+    /// terminal command gdb is started in, empty to try the default terminals
+    debug_terminal: String,
+    theme: Theme,
+    ingredient_filter: String,
+    /// symbols of the loaded binary sorted by address, or why they could not be read
+    symbols: std::result::Result<Vec<(u64, String)>, String>,
+    symbol_filter: String,
+    show_hexdump: bool,
+    pending_action: Option<PendingAction>,
+    /// error of the last save, load, delete or interactive send
+    gui_error: Option<String>,
+    /// line typed into the interactive panel
+    interactive_line: String,
     gui_state: GuiState,
 }
 
@@ -55,27 +176,155 @@ pub enum Message {
     IngredientOutputChange(usize, String),
     IngredientDataChange(usize, String),
     IngredientOutputChangeType(usize),
+    IngredientSilentChanged(usize, bool),
     SelectIngredient(usize),
     SelectIngredientPreview(usize),
     AddIngredientPreview(usize),
     RemoveIngredient(usize),
+    DuplicateIngredient(usize),
     MoveIngredientUp(usize),
     MoveIngredientDown(usize),
+    MoveIngredientTo(usize, usize),
+    IngredientPositionChange(usize, String),
     SaveRecipe,
     LoadRecipe,
     SaveIngredient,
     ProgramNameChanged(String),
+    RecentProgramSelected(String),
     CreateRegister(usize),
     IsNetworkChanged(bool),
+    IsPtyChanged(bool),
+    #[cfg(feature = "ssh2")]
+    IsSshChanged(bool),
+    SshHostChanged(String),
+    SshUserChanged(String),
+    SshSecretChanged(String),
+    SshUseKeyChanged(bool),
+    EnvVarsChanged(String),
+    ClearEnvChanged(bool),
+    WorkingDirChanged(String),
     StartProgram,
     RunAll,
+    RunUntilSuccess,
+    MaxAttemptsChanged(String),
+    RestartOnErrorChanged(bool),
+    UseGdbserverChanged(bool),
+    GdbserverPortChanged(String),
+    DebugTerminalChanged(String),
+    ToggleTheme,
+    IngredientFilterChanged(String),
+    SymbolFilterChanged(String),
+    ShowHexdumpChanged(bool),
+    InteractiveLineChanged(String),
+    InteractiveSend,
+    InteractiveReceive,
+    InteractiveLeave,
     SaveRecipeChanged(String),
     LoadRecipeChanged(String),
+    DeleteRecipe(String),
+    DeleteIngredient(String),
+    ConfirmPendingAction,
+    CancelPendingAction,
 }
 
 impl App {
+    fn write_recipe(&self, path: &str) -> Result<()> {
+        let serialized = serialize_recipe(&self.recipe)?;
+        fs::write(path, &serialized).context(format!("Unable to write {}", path))
+    }
+
+    /// hands the selected debug mode to the program, an invalid port is reported instead
+    fn apply_debug_mode(&mut self) {
+        let mode = if self.use_gdbserver {
+            match self.gdbserver_port.trim().parse() {
+                Ok(port) => DebugMode::GdbServer(port),
+                Err(_) => {
+                    self.gui_error =
+                        Some(format!("Invalid gdbserver port '{}'", self.gdbserver_port));
+                    return;
+                }
+            }
+        } else {
+            let terminal = self.debug_terminal.trim();
+            DebugMode::Terminal(Some(terminal.to_string()).filter(|_| !terminal.is_empty()))
+        };
+
+        self.gui_error = None;
+        if let Some(state) = self.state.as_mut() {
+            state.program.set_debug_mode(mode);
+        }
+    }
+
+    /// show the error of a recipe run, the program is restarted after it if restart on error is set
+    fn finish_run(&mut self, result: std::result::Result<(), (usize, String)>) {
+        self.recipe_error = result.err().map(|(id, mut error)| {
+            if self.restart_on_error {
+                debug!("Restarting...");
+                if let Err(e) = self.state.as_mut().unwrap().program.restart() {
+                    error += &format!("\nUnable to restart program: {:#}", e);
+                }
+            }
+            (id, error)
+        });
+    }
+
+    /// saves the recipe, existing files are only overwritten after confirmation
+    fn save_recipe(&mut self, action: PendingAction) {
+        if self.save_recipe_name.is_empty() {
+            self.gui_error = Some("Enter a name to save the recipe".to_string());
+        } else if Path::new(&action.path()).exists() {
+            self.pending_action = Some(action);
+        } else {
+            self.perform(action);
+        }
+    }
+
+    fn perform(&mut self, action: PendingAction) {
+        let path = action.path();
+        let result = match action {
+            PendingAction::DeleteRecipe(_) | PendingAction::DeleteIngredient(_) => {
+                fs::remove_file(&path).context(format!("Unable to delete {}", path))
+            }
+            PendingAction::OverwriteRecipe(_) | PendingAction::OverwriteIngredient(_) => {
+                self.write_recipe(&path)
+            }
+        };
+
+        self.gui_error = result.err().map(|e| format!("{:#}", e));
+
+        // the recipe pick list is read from disk on every draw
+        match action {
+            PendingAction::DeleteRecipe(name) if name == self.load_recipe_name => {
+                self.load_recipe_name = String::default();
+            }
+            PendingAction::DeleteIngredient(_) | PendingAction::OverwriteIngredient(_) => {
+                self.load_custom_ingredients()
+            }
+            _ => {}
+        }
+    }
+
+    /// collects everything the program answers in interactive mode
+    fn receive_interactive(state: &mut State) -> Result<()> {
+        let received = state
+            .program
+            .clean(Duration::from_millis(200))
+            .context("Could not read from process")?;
+        state.record_received(&received);
+        Ok(())
+    }
+
     fn load_log(&mut self) {
-        self.debug_output = std::fs::read_to_string("log.log").unwrap();
+        self.debug_output = self.log_buffer.text();
+    }
+
+    fn load_stderr(&mut self) {
+        if let Some(state) = self.state.as_mut() {
+            // targets without stderr (e.g. network) return an error which is ignored here
+            if let Ok(stderr) = state.program.recv_stderr(usize::MAX) {
+                state.record_stderr(&stderr);
+            }
+        }
     }
 
     fn load_custom_ingredients(&mut self) {
@@ -118,6 +367,38 @@ impl App {
             .flatten()
     }
 
+    /// store the started program and its mode in the config, returns the updated config
+    fn remember_program(&mut self) -> Config {
+        let mut config = Config::load();
+        config.last_program = LastProgram {
+            name: self.program_name.clone(),
+            is_network: self.is_network,
+            is_pty: self.is_pty,
+            is_ssh: self.is_ssh,
+            ssh_host: self.ssh_host.clone(),
+            ssh_user: self.ssh_user.clone(),
+        };
+        config.add_recent_program(&self.program_name);
+        self.recent_programs = config.recent_programs.clone();
+        if let Err(e) = config.save() {
+            debug!("Could not save last program: {:?}", e);
+        }
+        config
+    }
+
+    /// connect to the ssh host and run the program name as remote command
+    #[cfg(feature = "ssh2")]
+    fn start_ssh(&self) -> Result<State> {
+        let auth = SshAuth::new(&self.ssh_secret, self.ssh_use_key)?;
+        let params = SshParams::new(&self.ssh_host, &self.ssh_user, auth)?;
+        State::new(Target::Ssh(params), &self.program_name, &[])
+    }
+
+    #[cfg(not(feature = "ssh2"))]
+    fn start_ssh(&self) -> Result<State> {
+        anyhow::bail!("SSH targets need a build with the ssh feature")
+    }
+
     fn view_choose_program(&mut self) -> Element<Message> {
         let program_name_input = TextInput::new(
             &mut self.gui_state.program_name,
@@ -135,33 +416,207 @@ impl App {
         let is_network_checkbox =
             Checkbox::new(self.is_network, "Network", Message::IsNetworkChanged);
 
+        let is_pty_checkbox = Checkbox::new(self.is_pty, "PTY", Message::IsPtyChanged);
+
         let row = Row::new()
             .push(program_name_input)
             .push(is_network_checkbox)
-            .align_items(Align::Center)
-            .spacing(10);
+            .push(is_pty_checkbox);
+        // ssh targets are only available in builds with the ssh feature
+        #[cfg(feature = "ssh2")]
+        let row = row.push(Checkbox::new(self.is_ssh, "SSH", Message::IsSshChanged));
+        let row = row.align_items(Align::Center).spacing(10);
 
-        let col = Column::new()
-            .push(row)
-            .push(start_button)
-            .align_items(Align::Center)
-            .spacing(4);
+        let mut col = Column::new().push(row);
+
+        if !self.recent_programs.is_empty() {
+            let recent_programs = PickList::new(
+                &mut self.gui_state.recent_programs,
+                self.recent_programs.clone(),
+                None,
+                Message::RecentProgramSelected,
+            );
+            let recent_row = Row::new()
+                .push(Text::new("Recent programs"))
+                .push(recent_programs)
+                .align_items(Align::Center)
+                .spacing(10);
+            col = col.push(recent_row);
+        }
+
+        // in ssh mode the program name is the remote command, the connection is configured here
+        if self.is_ssh {
+            let ssh_host_input = TextInput::new(
+                &mut self.gui_state.ssh_host,
+                "Host (ip:port)",
+                &self.ssh_host,
+                Message::SshHostChanged,
+            )
+            .width(Length::Units(200));
+
+            let ssh_user_input = TextInput::new(
+                &mut self.gui_state.ssh_user,
+                "User",
+                &self.ssh_user,
+                Message::SshUserChanged,
+            )
+            .width(Length::Units(100));
+
+            let ssh_secret_input = TextInput::new(
+                &mut self.gui_state.ssh_secret,
+                if self.ssh_use_key {
+                    "Private key file"
+                } else {
+                    "Password"
+                },
+                &self.ssh_secret,
+                Message::SshSecretChanged,
+            )
+            .width(Length::Units(200));
+            let ssh_secret_input = if self.ssh_use_key {
+                ssh_secret_input
+            } else {
+                ssh_secret_input.password()
+            };
+
+            let ssh_use_key_checkbox =
+                Checkbox::new(self.ssh_use_key, "Key file", Message::SshUseKeyChanged);
+
+            let ssh_row = Row::new()
+                .push(ssh_host_input)
+                .push(ssh_user_input)
+                .push(ssh_secret_input)
+                .push(ssh_use_key_checkbox)
+                .align_items(Align::Center)
+                .spacing(10);
+            col = col.push(ssh_row);
+        }
+
+        // environment and working directory of local programs
+        if !self.is_ssh && !self.is_network {
+            let env_vars_input = TextInput::new(
+                &mut self.gui_state.env_vars,
+                "Environment (e.g. FOO=bar LD_PRELOAD=./libc.so.6)",
+                &self.env_vars,
+                Message::EnvVarsChanged,
+            )
+            .width(Length::Units(400));
+
+            let clear_env_checkbox = Checkbox::new(
+                self.clear_env,
+                "Clear environment",
+                Message::ClearEnvChanged,
+            );
+
+            let working_dir_input = TextInput::new(
+                &mut self.gui_state.working_dir,
+                "Working directory (optional)",
+                &self.working_dir,
+                Message::WorkingDirChanged,
+            )
+            .width(Length::Units(200));
+
+            let env_row = Row::new()
+                .push(env_vars_input)
+                .push(clear_env_checkbox)
+                .push(working_dir_input)
+                .align_items(Align::Center)
+                .spacing(10);
+            col = col.push(env_row);
+        }
+
+        let mut col = col.push(start_button).align_items(Align::Center).spacing(4);
+        if let Some(error) = &self.gui_error {
+            let error_style: Box<dyn container::StyleSheet> =
+                IngredientStyle::failed(self.theme, true).into();
+            col = col.push(
+                Container::new(Text::new(error))
+                    .style(error_style)
+                    .padding(10),
+            );
+        }
 
         Container::new(col)
             .center_x()
             .center_y()
             .height(Length::Fill)
             .width(Length::Fill)
+            .style(self.theme)
             .into()
     }
 
     fn view_recipe(&mut self) -> Element<Message> {
-        let recipe_header = Container::new(Text::new("Recipe").size(50))
+        let toggle_theme_button = Button::new(
+            &mut self.gui_state.toggle_theme,
+            Text::new(self.theme.toggle_label()),
+        )
+        .on_press(Message::ToggleTheme);
+        let recipe_header_row = Row::new()
+            .align_items(Align::Center)
+            .push(Text::new("Recipe").size(50).width(Length::Fill))
+            .push(toggle_theme_button);
+        let recipe_header = Container::new(recipe_header_row)
             .width(Length::FillPortion(1))
             .padding(20);
 
         let run_button = Button::new(&mut self.gui_state.run_all, Text::new("Run all"))
             .on_press(Message::RunAll);
+        let restart_on_error_checkbox = Checkbox::new(
+            self.restart_on_error,
+            "Restart on error",
+            Message::RestartOnErrorChanged,
+        );
+        let use_gdbserver_checkbox = Checkbox::new(
+            self.use_gdbserver,
+            "Debug via gdbserver",
+            Message::UseGdbserverChanged,
+        );
+        let mut run_row = Row::new()
+            .spacing(20)
+            .align_items(Align::Center)
+            .push(run_button)
+            .push(restart_on_error_checkbox)
+            .push(use_gdbserver_checkbox);
+        if self.use_gdbserver {
+            let gdbserver_port_input = TextInput::new(
+                &mut self.gui_state.gdbserver_port,
+                "Port",
+                &self.gdbserver_port,
+                Message::GdbserverPortChanged,
+            )
+            .width(Length::Units(60));
+            run_row = run_row.push(gdbserver_port_input);
+        } else {
+            let debug_terminal_input = TextInput::new(
+                &mut self.gui_state.debug_terminal,
+                "Debugger terminal (auto)",
+                &self.debug_terminal,
+                Message::DebugTerminalChanged,
+            )
+            .width(Length::Units(200));
+            run_row = run_row.push(debug_terminal_input);
+        }
+
+        let run_until_success_button = Button::new(
+            &mut self.gui_state.run_until_success,
+            Text::new("Run until success"),
+        )
+        .on_press(Message::RunUntilSuccess);
+        let max_attempts_input = TextInput::new(
+            &mut self.gui_state.max_attempts,
+            "Attempts",
+            &self.max_attempts,
+            Message::MaxAttemptsChanged,
+        )
+        .width(Length::Units(60));
+        let mut retry_row = Row::new()
+            .spacing(20)
+            .align_items(Align::Center)
+            .push(run_until_success_button)
+            .push(max_attempts_input);
+        if let Some(status) = &self.attempts_status {
+            retry_row = retry_row.push(Text::new(status));
+        }
 
         let save_recipe_button =
             Button::new(&mut self.gui_state.save_recipe, Text::new("Save as recipe"))
@@ -184,13 +639,57 @@ impl App {
             .width(Length::FillPortion(1))
             .padding(20);
 
+        let ingredient_filter_input = TextInput::new(
+            &mut self.gui_state.ingredient_filter,
+            "Search ingredients",
+            &self.ingredient_filter,
+            Message::IngredientFilterChanged,
+        )
+        .padding(5);
+
         let mut ingredient_scroller = Scrollable::new(&mut self.gui_state.ingredient_scrollable)
             .spacing(2)
             .width(Length::Fill)
-            .height(Length::Fill);
+            .height(Length::FillPortion(2));
 
+        let filter = &self.ingredient_filter;
         for category in &mut self.category_list {
-            ingredient_scroller = ingredient_scroller.push(category.draw());
+            if filter.is_empty() || category.has_match(filter) {
+                ingredient_scroller = ingredient_scroller.push(category.draw(self.theme, filter));
+            }
+        }
+
+        let symbol_filter_input = TextInput::new(
+            &mut self.gui_state.symbol_filter,
+            "Search symbols",
+            &self.symbol_filter,
+            Message::SymbolFilterChanged,
+        )
+        .padding(5);
+
+        let mut symbol_scroller = Scrollable::new(&mut self.gui_state.symbol_scrollable)
+            .spacing(2)
+            .width(Length::Fill)
+            .height(Length::FillPortion(1));
+
+        match &self.symbols {
+            Ok(symbols) => {
+                // showing thousands of libc symbols at once makes the gui sluggish
+                let mut matches = filter_symbols(symbols, &self.symbol_filter);
+                for (address, name) in matches.by_ref().take(MAX_SHOWN_SYMBOLS) {
+                    symbol_scroller = symbol_scroller
+                        .push(Text::new(format!("{:#x} {}", address, name)).size(18));
+                }
+                let hidden = matches.count();
+                if hidden > 0 {
+                    symbol_scroller = symbol_scroller.push(
+                        Text::new(format!("... {} more, refine the search", hidden)).size(18),
+                    );
+                }
+            }
+            Err(error) => {
+                symbol_scroller = symbol_scroller.push(Text::new(error).size(18));
+            }
         }
 
         let mut recipe_scroller = Scrollable::new(&mut self.gui_state.recipe_scrollable)
@@ -200,8 +699,11 @@ impl App {
 
         let registers = self.state.as_ref().unwrap().registers.available_registers();
 
+        let failed_id = self.recipe_error.as_ref().map(|(id, _)| *id);
         for ingredient in &mut self.recipe {
-            recipe_scroller = recipe_scroller.push(ingredient.draw_active(registers.clone()));
+            let failed = failed_id == Some(ingredient.id);
+            recipe_scroller =
+                recipe_scroller.push(ingredient.draw_active(registers.clone(), failed, self.theme));
         }
 
         // save
@@ -235,23 +737,72 @@ impl App {
             move |msg| Message::LoadRecipeChanged(msg),
         );
 
+        let delete_recipe_button = Button::new(
+            &mut self.gui_state.delete_recipe,
+            Text::new("Delete recipe"),
+        )
+        .on_press(Message::DeleteRecipe(self.load_recipe_name.clone()));
+
         let load_recipe_row = Row::new()
             .spacing(20)
             .push(picklist)
-            .push(load_recipe_button);
+            .push(load_recipe_button)
+            .push(delete_recipe_button);
 
-        let recipes = Column::new()
+        let mut recipes = Column::new()
             .align_items(Align::Start)
             .width(Length::FillPortion(3))
             .spacing(10)
             .push(recipe_header)
-            .push(Rule::horizontal(0))
+            .push(Rule::horizontal(0));
+
+        if let Some(action) = &self.pending_action {
+            let confirm_button = Button::new(
+                &mut self.gui_state.confirm_pending,
+                Text::new(action.confirm_label()),
+            )
+            .on_press(Message::ConfirmPendingAction);
+            let cancel_button =
+                Button::new(&mut self.gui_state.cancel_pending, Text::new("Cancel"))
+                    .on_press(Message::CancelPendingAction);
+            let confirm_row = Row::new()
+                .spacing(20)
+                .align_items(Align::Center)
+                .push(Text::new(action.prompt()).width(Length::Fill))
+                .push(confirm_button)
+                .push(cancel_button);
+            let confirm_style: Box<dyn container::StyleSheet> =
+                IngredientStyle::selected(self.theme, true).into();
+            let confirm_banner = Container::new(confirm_row)
+                .style(confirm_style)
+                .width(Length::Fill)
+                .padding(10);
+            recipes = recipes.push(confirm_banner);
+        }
+
+        let errors = self
+            .recipe_error
+            .iter()
+            .map(|(_, error)| error)
+            .chain(self.gui_error.iter());
+        for error in errors {
+            let error_style: Box<dyn container::StyleSheet> =
+                IngredientStyle::failed(self.theme, true).into();
+            let error_banner = Container::new(Text::new(error))
+                .style(error_style)
+                .width(Length::Fill)
+                .padding(10);
+            recipes = recipes.push(error_banner);
+        }
+
+        let recipes = recipes
             .push(recipe_scroller)
             .push(Rule::horizontal(0))
             .push(save_recipe_row)
             .push(save_ingredient_container)
             .push(load_recipe_row)
-            .push(run_button);
+            .push(run_row)
+            .push(retry_row);
 
         let ingredients = Column::new()
             .align_items(Align::Start)
@@ -259,7 +810,12 @@ impl App {
             .spacing(10)
             .push(ingredients_header)
             .push(Rule::horizontal(0))
-            .push(ingredient_scroller);
+            .push(ingredient_filter_input)
+            .push(ingredient_scroller)
+            .push(Text::new("Symbols").size(30))
+            .push(Rule::horizontal(0))
+            .push(symbol_filter_input)
+            .push(symbol_scroller);
 
         let output_content = Text::new(&self.debug_output).size(18);
         let output_scroller = Scrollable::new(&mut self.gui_state.debug_scrollable)
@@ -268,7 +824,31 @@ impl App {
             .height(Length::FillPortion(4))
             .push(output_content);
 
-        let program_output = Text::new(&self.state.as_ref().unwrap().output).size(18);
+        // the hexdump only shows received bytes, the text shows sent data in its own color
+        let state = self.state.as_ref().unwrap();
+        let theme = self.theme;
+        let program_output = if self.show_hexdump {
+            Column::new().push(Text::new(hexdump(&state.output)).size(18))
+        } else {
+            state
+                .transcript
+                .chunks
+                .iter()
+                .fold(Column::new(), |column, (direction, data)| {
+                    let color = match direction {
+                        Direction::Sent => theme.sent_text(),
+                        Direction::Received => theme.text(),
+                        Direction::Stderr => theme.secondary_text(),
+                    };
+                    let text = String::from_utf8_lossy(data);
+                    // stderr lines are marked, the rest is shown as it was sent
+                    let text = match direction {
+                        Direction::Stderr => Transcript::plain_text(*direction, &text),
+                        _ => text.strip_suffix('\n').unwrap_or(&text).to_string(),
+                    };
+                    column.push(Text::new(text).size(18).color(color))
+                })
+        };
         let program_output_scroller =
             Scrollable::new(&mut self.gui_state.program_output_scrollable)
                 .spacing(2)
@@ -276,12 +856,50 @@ impl App {
                 .height(Length::FillPortion(4))
                 .push(program_output);
 
-        let output = Column::new()
+        let hexdump_checkbox =
+            Checkbox::new(self.show_hexdump, "Hexdump", Message::ShowHexdumpChanged);
+        let program_output_header = Row::new()
+            .align_items(Align::Center)
+            .push(Text::new("Program Output").size(50).width(Length::Fill))
+            .push(hexdump_checkbox);
+
+        let mut output = Column::new()
             .align_items(Align::Start)
             .width(Length::FillPortion(3))
-            .push(Text::new("Program Output").size(50))
+            .push(program_output_header)
             .push(Rule::horizontal(0))
-            .push(program_output_scroller)
+            .push(program_output_scroller);
+
+        if self.state.as_ref().unwrap().interactive {
+            let interactive_input = TextInput::new(
+                &mut self.gui_state.interactive_input,
+                "Send a line",
+                &self.interactive_line,
+                Message::InteractiveLineChanged,
+            )
+            .on_submit(Message::InteractiveSend)
+            .padding(5);
+            let send_button = Button::new(&mut self.gui_state.interactive_send, Text::new("Send"))
+                .on_press(Message::InteractiveSend);
+            let receive_button = Button::new(
+                &mut self.gui_state.interactive_receive,
+                Text::new("Receive"),
+            )
+            .on_press(Message::InteractiveReceive);
+            let leave_button =
+                Button::new(&mut self.gui_state.interactive_leave, Text::new("Leave"))
+                    .on_press(Message::InteractiveLeave);
+            let interactive_row = Row::new()
+                .spacing(10)
+                .align_items(Align::Center)
+                .push(interactive_input)
+                .push(send_button)
+                .push(receive_button)
+                .push(leave_button);
+            output = output.push(interactive_row);
+        }
+
+        let output = output
             .push(Text::new("Debug Output").size(50))
             .push(Rule::horizontal(0))
             .push(output_scroller);
@@ -298,6 +916,7 @@ impl App {
         Container::new(content)
             .width(Length::Fill)
             .height(Length::Fill)
+            .style(self.theme)
             .into()
     }
 }
@@ -305,20 +924,48 @@ impl App {
 impl Application for App {
     type Executor = executor::Default;
     type Message = Message;
-    type Flags = ();
+    type Flags = LogBuffer;
 
-    fn new(_flags: ()) -> (App, Command<Message>) {
+    fn new(log_buffer: LogBuffer) -> (App, Command<Message>) {
+        let config = Config::load();
+        let last_program = config.last_program;
         let mut app = App {
             current_scene: Scene::ChooseProgram,
             state: None,
             enabled: false,
             should_exit: false,
             debug_output: String::new(),
+            log_buffer,
             program_output: String::new(),
             category_list: available_categories(),
             recipe: Vec::new(),
-            program_name: String::default(),
-            is_network: false,
+            program_name: last_program.name,
+            recent_programs: config.recent_programs,
+            is_network: last_program.is_network,
+            is_pty: last_program.is_pty,
+            is_ssh: last_program.is_ssh && cfg!(feature = "ssh2"),
+            ssh_host: last_program.ssh_host,
+            ssh_user: last_program.ssh_user,
+            ssh_secret: String::default(),
+            ssh_use_key: false,
+            env_vars: String::default(),
+            clear_env: false,
+            working_dir: String::default(),
+            recipe_error: None,
+            restart_on_error: true,
+            max_attempts: DEFAULT_MAX_ATTEMPTS.to_string(),
+            attempts_status: None,
+            use_gdbserver: false,
+            gdbserver_port: DEFAULT_GDBSERVER_PORT.to_string(),
+            debug_terminal: String::default(),
+            theme: config.theme,
+            ingredient_filter: String::default(),
+            symbols: Ok(Vec::new()),
+            symbol_filter: String::default(),
+            show_hexdump: false,
+            pending_action: None,
+            gui_error: None,
+            interactive_line: String::default(),
             save_recipe_name: String::default(),
             load_recipe_name: String::default(),
             gui_state: Default::default(),
@@ -340,28 +987,85 @@ impl Application for App {
                 }
             }
             Message::StartProgram => {
+                let state = if self.is_ssh {
+                    self.start_ssh()
+                } else if self.is_network {
+                    State::new(Target::Network, &self.program_name, &[])
+                } else {
+                    let env = match parse_env(&self.env_vars) {
+                        Ok(env) => env,
+                        Err(e) => {
+                            self.gui_error = Some(format!("{:#}", e));
+                            return Command::none();
+                        }
+                    };
+                    let options = LocalOptions {
+                        env,
+                        clear_env: self.clear_env,
+                        cwd: Some(self.working_dir.trim().to_string())
+                            .filter(|dir| !dir.is_empty()),
+                        ..LocalOptions::default()
+                    };
+                    State::new_local(&self.program_name, &[], self.is_pty, options)
+                };
+                // a missing program or unreachable host keeps us on this scene
+                let mut state = match state {
+                    Ok(state) => state,
+                    Err(e) => {
+                        self.gui_error = Some(format!("{:#}", e));
+                        return Command::none();
+                    }
+                };
                 self.current_scene = Scene::Recipe;
-                let mut state = State::new(
-                    if self.is_network {
-                        Target::Network
-                    } else {
-                        Target::Local
-                    },
-                    &self.program_name,
-                    &[],
-                )
-                .expect("Failed to spawn program");
+                self.gui_error = None;
                 state
                     .registers
                     .set("program", self.program_name.as_bytes().to_vec());
+                state.allow_shell = self.remember_program().allow_shell;
+                self.symbols = state
+                    .binary()
+                    .map(|binary| symbol_list(binary.all_symbols()))
+                    .map_err(|e| format!("{:#}", e));
                 self.state = Some(state);
+                self.apply_debug_mode();
             }
             Message::ProgramNameChanged(name) => {
                 self.program_name = name;
             }
+            Message::RecentProgramSelected(name) => {
+                self.program_name = name;
+            }
             Message::IsNetworkChanged(enabled) => {
                 self.is_network = enabled;
             }
+            Message::IsPtyChanged(enabled) => {
+                self.is_pty = enabled;
+            }
+            #[cfg(feature = "ssh2")]
+            Message::IsSshChanged(enabled) => {
+                self.is_ssh = enabled;
+            }
+            Message::SshHostChanged(host) => {
+                self.ssh_host = host;
+            }
+            Message::SshUserChanged(user) => {
+                self.ssh_user = user;
+            }
+            Message::SshSecretChanged(secret) => {
+                self.ssh_secret = secret;
+            }
+            Message::SshUseKeyChanged(enabled) => {
+                self.ssh_use_key = enabled;
+            }
+            Message::EnvVarsChanged(env_vars) => {
+                self.env_vars = env_vars;
+            }
+            Message::ClearEnvChanged(enabled) => {
+                self.clear_env = enabled;
+            }
+            Message::WorkingDirChanged(dir) => {
+                self.working_dir = dir;
+            }
             Message::MoveIngredientUp(id) => {
                 if let Some(positon) = self.recipe.iter().position(|i| i.id == id) {
                     self.recipe.swap(positon, positon.saturating_sub(1));
@@ -374,9 +1078,30 @@ impl Application for App {
                     }
                 }
             }
+            Message::MoveIngredientTo(id, target) => {
+                if let Some(positon) = self.recipe.iter().position(|i| i.id == id) {
+                    let mut ingredient = self.recipe.remove(positon);
+                    ingredient.position.clear();
+                    // out of range targets move the ingredient to the end
+                    let target = target.min(self.recipe.len());
+                    self.recipe.insert(target, ingredient);
+                }
+            }
+            Message::IngredientPositionChange(id, msg) => {
+                if let Some(ingredient) = self.recipe.iter_mut().find(|i| i.id == id) {
+                    ingredient.position = msg;
+                }
+            }
             Message::RemoveIngredient(id) => {
                 self.recipe.retain(|i| i.id != id);
             }
+            Message::DuplicateIngredient(id) => {
+                // the clone gets a new id
+                if let Some(position) = self.recipe.iter().position(|i| i.id == id) {
+                    let duplicate = self.recipe[position].clone();
+                    self.recipe.insert(position + 1, duplicate);
+                }
+            }
             Message::SelectIngredient(id) => {}
             Message::SelectIngredientPreview(id) => {
                 for ingredient in &mut self.ingredient_list() {
@@ -392,6 +1117,11 @@ impl Application for App {
                     ingredient.toggle_output_type();
                 }
             }
+            Message::IngredientSilentChanged(id, silent) => {
+                if let Some(ingredient) = self.recipe.iter_mut().find(|i| i.id == id) {
+                    ingredient.silent = silent;
+                }
+            }
             Message::IngredientOutputChange(id, msg) => {
                 if let Some(ingredient) = self.recipe.iter_mut().find(|i| i.id == id) {
                     ingredient.set_output(msg);
@@ -403,18 +1133,96 @@ impl Application for App {
                 }
             }
             Message::RunAll => {
-                self.state.as_mut().unwrap().output = String::new();
-                for ingredient in &self.recipe {
-                    if let Err(e) = ingredient.run(&mut self.state.as_mut().unwrap()) {
-                        debug!("Error occured: '{:?}'. Restarting...", e);
-                        self.state
-                            .as_mut()
-                            .unwrap()
-                            .program
-                            .restart()
-                            .expect("Unable to restart program");
-                        break;
+                let state = self.state.as_mut().unwrap();
+                state.clear_output();
+                state.interactive = false;
+                self.attempts_status = None;
+                let result = run_recipe(&self.recipe, state);
+                self.finish_run(result);
+            }
+            Message::RunUntilSuccess => {
+                let max_attempts = match self.max_attempts.trim().parse::<usize>() {
+                    Ok(max_attempts) if max_attempts > 0 => max_attempts,
+                    _ => {
+                        self.gui_error = Some(format!(
+                            "Invalid number of attempts '{}'",
+                            self.max_attempts
+                        ));
+                        return Command::none();
                     }
+                };
+                let state = self.state.as_mut().unwrap();
+                state.interactive = false;
+                let (attempts, result) = run_until_success(&self.recipe, state, max_attempts);
+                let status = match result {
+                    Ok(()) => format!("Succeeded after {} of {} attempts", attempts, max_attempts),
+                    Err(_) => format!("Failed {} attempts", attempts),
+                };
+                info!("{}", status);
+                self.attempts_status = Some(status);
+                self.finish_run(result);
+            }
+            Message::MaxAttemptsChanged(max_attempts) => {
+                self.max_attempts = max_attempts;
+            }
+            Message::RestartOnErrorChanged(enabled) => {
+                self.restart_on_error = enabled;
+            }
+            Message::UseGdbserverChanged(enabled) => {
+                self.use_gdbserver = enabled;
+                self.apply_debug_mode();
+            }
+            Message::GdbserverPortChanged(port) => {
+                self.gdbserver_port = port;
+                self.apply_debug_mode();
+            }
+            Message::DebugTerminalChanged(terminal) => {
+                self.debug_terminal = terminal;
+                self.apply_debug_mode();
+            }
+            Message::IngredientFilterChanged(filter) => {
+                self.ingredient_filter = filter;
+            }
+            Message::SymbolFilterChanged(filter) => {
+                self.symbol_filter = filter;
+            }
+            Message::ShowHexdumpChanged(enabled) => {
+                self.show_hexdump = enabled;
+            }
+            Message::InteractiveLineChanged(line) => {
+                self.interactive_line = line;
+            }
+            Message::InteractiveSend => {
+                let line = std::mem::take(&mut self.interactive_line);
+                let state = self.state.as_mut().unwrap();
+                self.gui_error = state
+                    .program
+                    .send_line(line.as_bytes())
+                    .context("Could not send line to process")
+                    .and_then(|_| {
+                        state.record_sent(format!("{}\n", line).as_bytes());
+                        App::receive_interactive(state)
+                    })
+                    .err()
+                    .map(|e| format!("{:#}", e));
+            }
+            Message::InteractiveReceive => {
+                let state = self.state.as_mut().unwrap();
+                self.gui_error = App::receive_interactive(state)
+                    .err()
+                    .map(|e| format!("{:#}", e));
+            }
+            Message::InteractiveLeave => {
+                self.state.as_mut().unwrap().interactive = false;
+            }
+            Message::ToggleTheme => {
+                self.theme = self.theme.toggled();
+                let config = Config {
+                    theme: self.theme,
+                    ..Config::load()
+                };
+                if let Err(e) = config.save() {
+                    debug!("Could not save theme: {:?}", e);
                 }
             }
             Message::CreateRegister(id) => {
@@ -428,28 +1236,37 @@ impl Application for App {
                 }
             }
             Message::SaveRecipe => {
-                let path = format!("recipes/{}", self.save_recipe_name);
-                let file = File::create(&path).unwrap();
-                let serialized = serde_json::to_string(&self.recipe).unwrap();
-                fs::write(&path, &serialized).expect("Unable to write file");
+                self.save_recipe(PendingAction::OverwriteRecipe(
+                    self.save_recipe_name.clone(),
+                ));
             }
             Message::SaveIngredient => {
-                let path = format!("ingredients/{}", self.save_recipe_name);
-                let file = File::create(&path).unwrap();
-                let serialized = serde_json::to_string(&self.recipe).unwrap();
-                fs::write(&path, &serialized).expect("Unable to write file");
-
-                self.load_custom_ingredients();
+                self.save_recipe(PendingAction::OverwriteIngredient(
+                    self.save_recipe_name.clone(),
+                ));
             }
             Message::LoadRecipe => {
                 let path = format!("recipes/{}", self.load_recipe_name);
-                let data = std::fs::read_to_string(&path).expect("Unable to read file");
-                let deserialized = serde_json::from_str(&data);
-                self.recipe = deserialized.unwrap();
+                let loaded = fs::read_to_string(&path)
+                    .context(format!("Unable to read {}", path))
+                    .and_then(|data| parse_recipe(&data))
+                    .context(format!("Failed to load recipe {}", self.load_recipe_name));
+                match loaded {
+                    Ok(recipe) => self.recipe = recipe,
+                    Err(e) => {
+                        self.gui_error = Some(format!("{:#}", e));
+                        return Command::none();
+                    }
+                }
+                self.gui_error = None;
                 debug!("Loaded recipe {}", self.load_recipe_name);
 
                 for ingredient in &self.recipe {
-                    self.state.as_mut().unwrap().registers.set(&ingredient.output, vec![]);
+                    self.state
+                        .as_mut()
+                        .unwrap()
+                        .registers
+                        .set(&ingredient.output, vec![]);
                 }
             }
             Message::SaveRecipeChanged(msg) => {
@@ -458,9 +1275,26 @@ impl Application for App {
             Message::LoadRecipeChanged(msg) => {
                 self.load_recipe_name = msg;
             }
+            Message::DeleteRecipe(name) => {
+                if !name.is_empty() {
+                    self.pending_action = Some(PendingAction::DeleteRecipe(name));
+                }
+            }
+            Message::DeleteIngredient(name) => {
+                self.pending_action = Some(PendingAction::DeleteIngredient(name));
+            }
+            Message::ConfirmPendingAction => {
+                if let Some(action) = self.pending_action.take() {
+                    self.perform(action);
+                }
+            }
+            Message::CancelPendingAction => {
+                self.pending_action = None;
+            }
         };
 
         self.load_log();
+        self.load_stderr();
         Command::none()
     }
 
@@ -468,6 +1302,10 @@ impl Application for App {
         self.should_exit
     }
 
+    fn background_color(&self) -> Color {
+        self.theme.background()
+    }
+
     fn view(&mut self) -> Element<Message> {
         match self.current_scene {
             Scene::ChooseProgram => self.view_choose_program(),
@@ -1,12 +1,14 @@
+use crate::misc::fiddling::unescape;
+use crate::misc::packing::unpack_uint;
 use crate::utils::State;
 use anyhow::{bail, Context, Result};
-use byteorder::{ByteOrder, LittleEndian, NativeEndian};
 use pest::{
     self,
     iterators::{Pair, Pairs},
     Parser,
 };
 use pest_derive::*;
+use std::convert::TryFrom;
 use std::fmt::Debug;
 
 use super::*;
@@ -20,20 +22,48 @@ pub enum Operator {
     Add,
     And,
     Div,
+    Eq,
+    Ge,
+    Gt,
+    Le,
+    // logical shift right, operates on the value reinterpreted as u64
+    Lsr,
+    Lt,
     Mod,
     Mul,
+    Ne,
     Neg,
     Or,
     Sll,
+    // arithmetic shift right, keeps the sign
     Slr,
     Sub,
     Xor,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Function {
+    // number of bytes
+    Len,
+    // bytes[start..end]
+    Slice,
+    // unpack up to 1/2/4/8 bytes into an integer, an optional "le" or "be" selects the byte order
+    // (default: the one of the binary)
+    U8,
+    U16,
+    U32,
+    U64,
+    // runtime base of the binary set by the Set Binary Base ingredient
+    Base,
+    // bytes of all arguments joined, integers as their decimal text
+    Concat,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Node {
     Reg(String),
     Int(i64),
+    Bytes(Vec<u8>),
     UnaryExpression {
         operator: Operator,
         child: Box<Node>,
@@ -44,14 +74,40 @@ pub enum Node {
         lhs: Box<Node>,
         rhs: Box<Node>,
     },
+    Call {
+        function: Function,
+        args: Vec<Node>,
+    },
+    ConditionalExpr {
+        // condition ? then : otherwise
+        condition: Box<Node>,
+        then: Box<Node>,
+        otherwise: Box<Node>,
+    },
 }
 
+#[derive(Clone)]
 pub enum NodeResult {
     Int(i64),
     Bytes(Vec<u8>),
 }
 
 impl NodeResult {
+    /// integers are converted to their decimal text
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            NodeResult::Int(i) => format!("{}", i).into_bytes(),
+            NodeResult::Bytes(b) => b,
+        }
+    }
+
+    pub fn as_bytes(self) -> Result<Vec<u8>> {
+        match self {
+            NodeResult::Bytes(b) => Ok(b),
+            NodeResult::Int(i) => bail!("Expected bytes, got integer {}", i),
+        }
+    }
+
     pub fn as_int(self) -> Result<i64> {
         match self {
             NodeResult::Int(i) => Ok(i),
@@ -78,16 +134,43 @@ impl Ast {
     }
 
     pub fn get_result(&self, state: &State) -> Result<Vec<u8>> {
-        let res = Ast::evaluate(&self.root, state)?;
-        match res {
-            NodeResult::Int(i) => Ok(format!("{}", i).into_bytes()),
-            NodeResult::Bytes(b) => Ok(b),
+        Ok(Ast::evaluate(&self.root, state)?.into_bytes())
+    }
+
+    /// evaluates to an integer, bytes have to be decimal text
+    pub fn get_int(&self, state: &State) -> Result<i64> {
+        Ast::evaluate(&self.root, state)?.as_int()
+    }
+
+    /// fails if the expression evaluates to 0, failed comparisons report the values of both sides
+    pub fn check(&self, state: &State) -> Result<()> {
+        if self.get_int(state)? != 0 {
+            return Ok(());
         }
+
+        if let Node::BinaryExpr { operator, lhs, rhs } = self.root.as_ref() {
+            let symbol = match operator {
+                Operator::Eq => Some("=="),
+                Operator::Ne => Some("!="),
+                Operator::Lt => Some("<"),
+                Operator::Le => Some("<="),
+                Operator::Gt => Some(">"),
+                Operator::Ge => Some(">="),
+                _ => None,
+            };
+            if let Some(symbol) = symbol {
+                let lhs = Ast::evaluate(lhs, state)?.as_int()?;
+                let rhs = Ast::evaluate(rhs, state)?.as_int()?;
+                bail!("Assertion failed: {:#x} {} {:#x}", lhs, symbol, rhs);
+            }
+        }
+        bail!("Assertion failed: expression evaluated to 0")
     }
 
     fn evaluate(node: &Node, state: &State) -> Result<NodeResult> {
         match node {
             Node::Int(x) => Ok(NodeResult::Int(*x)),
+            Node::Bytes(x) => Ok(NodeResult::Bytes(x.to_vec())),
             Node::Reg(x) => {
                 let val = state.registers.get(&x[1..]).context("Invalid Register")?;
                 Ok(NodeResult::Bytes(val.to_vec()))
@@ -96,40 +179,156 @@ impl Ast {
                 let child = Ast::evaluate(child, state)?.as_int()?;
                 Ok(NodeResult::Int(match operator {
                     Operator::Add => child,
-                    Operator::Sub => -child,
+                    Operator::Sub => child.wrapping_neg(),
                     Operator::Neg => !child,
                     _ => unreachable!(),
                 }))
             }
             Node::BinaryExpr { operator, lhs, rhs } => {
+                // arithmetic wraps around like 64-bit machine registers do
+                // division and modulo by zero as well as negative shift amounts are errors
+                // shifting by 64 or more bits shifts everything out (arithmetic right shifts keep the sign)
                 let lhs = Ast::evaluate(lhs, state)?.as_int()?;
                 let rhs = Ast::evaluate(rhs, state)?.as_int()?;
                 Ok(NodeResult::Int(match operator {
-                    Operator::Add => lhs + rhs,
+                    Operator::Add => lhs.wrapping_add(rhs),
                     Operator::And => lhs & rhs,
-                    Operator::Div => lhs / rhs,
-                    Operator::Mod => lhs % rhs,
-                    Operator::Mul => lhs * rhs,
+                    Operator::Div => {
+                        if rhs == 0 {
+                            bail!("division by zero");
+                        }
+                        lhs.wrapping_div(rhs)
+                    }
+                    Operator::Mod => {
+                        if rhs == 0 {
+                            bail!("modulo by zero");
+                        }
+                        lhs.wrapping_rem(rhs)
+                    }
+                    Operator::Mul => lhs.wrapping_mul(rhs),
                     Operator::Or => lhs | rhs,
-                    Operator::Sll => lhs << rhs,
-                    Operator::Slr => lhs >> rhs,
-                    Operator::Sub => lhs - rhs,
+                    Operator::Sll => lhs.checked_shl(Ast::shift_amount(rhs)?).unwrap_or(0),
+                    Operator::Slr => lhs
+                        .checked_shr(Ast::shift_amount(rhs)?)
+                        .unwrap_or(if lhs < 0 { -1 } else { 0 }),
+                    Operator::Lsr => (lhs as u64)
+                        .checked_shr(Ast::shift_amount(rhs)?)
+                        .unwrap_or(0) as i64,
+                    Operator::Sub => lhs.wrapping_sub(rhs),
                     Operator::Xor => lhs ^ rhs,
+                    // comparisons evaluate to 1 (true) or 0 (false)
+                    Operator::Eq => (lhs == rhs) as i64,
+                    Operator::Ne => (lhs != rhs) as i64,
+                    Operator::Lt => (lhs < rhs) as i64,
+                    Operator::Le => (lhs <= rhs) as i64,
+                    Operator::Gt => (lhs > rhs) as i64,
+                    Operator::Ge => (lhs >= rhs) as i64,
                     Operator::Neg => panic!("Negation is not a binary operator"),
                 }))
             }
+            Node::Call { function, args } => {
+                let arg_count = args.len();
+                let mut args = args.iter().map(|arg| Ast::evaluate(arg, state));
+                // arity is checked when building the ast
+                let mut next_arg = || args.next().context("Missing argument")?;
+
+                match function {
+                    Function::Len => Ok(NodeResult::Int(next_arg()?.as_bytes()?.len() as i64)),
+                    Function::Concat => {
+                        let parts = args.map(|arg| Ok(arg?.into_bytes()));
+                        Ok(NodeResult::Bytes(
+                            parts.collect::<Result<Vec<_>>>()?.concat(),
+                        ))
+                    }
+                    Function::Base => Ok(NodeResult::Int(
+                        state
+                            .binary_base
+                            .context("No binary base set, use the Set Binary Base ingredient")?
+                            as i64,
+                    )),
+                    Function::Slice => {
+                        let bytes = next_arg()?.as_bytes()?;
+                        let start = usize::try_from(next_arg()?.as_int()?)?;
+                        let end = usize::try_from(next_arg()?.as_int()?)?;
+                        let slice = bytes.get(start..end).context(format!(
+                            "slice {}..{} out of range for {} bytes",
+                            start,
+                            end,
+                            bytes.len()
+                        ))?;
+                        Ok(NodeResult::Bytes(slice.to_vec()))
+                    }
+                    Function::U8 | Function::U16 | Function::U32 | Function::U64 => {
+                        let width = match function {
+                            Function::U8 => 1,
+                            Function::U16 => 2,
+                            Function::U32 => 4,
+                            _ => 8,
+                        };
+                        // shorter inputs (e.g. a leaked 6 byte pointer) are zero extended
+                        let bytes = next_arg()?.as_bytes()?;
+                        if bytes.is_empty() || bytes.len() > width {
+                            bail!("Can not unpack {} bytes into {} bytes", bytes.len(), width);
+                        }
+                        let endian = if arg_count > 1 {
+                            String::from_utf8(next_arg()?.as_bytes()?)?.parse()?
+                        } else {
+                            state.default_endian()
+                        };
+                        Ok(NodeResult::Int(unpack_uint(&bytes, endian)? as i64))
+                    }
+                }
+            }
+            Node::ConditionalExpr {
+                condition,
+                then,
+                otherwise,
+            } => {
+                // only the selected branch is evaluated
+                if Ast::evaluate(condition, state)?.as_int()? != 0 {
+                    Ast::evaluate(then, state)
+                } else {
+                    Ast::evaluate(otherwise, state)
+                }
+            }
         }
     }
 
+    /// convert the rhs of a shift into a shift amount, rejecting negative amounts
+    fn shift_amount(rhs: i64) -> Result<u32> {
+        if rhs < 0 {
+            bail!("negative shift amount {}", rhs);
+        }
+        // amounts above u32::MAX shift out everything just like 64 does
+        Ok(u32::try_from(rhs).unwrap_or(u32::MAX))
+    }
+
     fn build_from_expr(pairs: Pairs<Rule>) -> Result<Node> {
         match pairs.peek().unwrap().as_rule() {
-            Rule::AddExpr | Rule::MulExpr | Rule::BitExpr => {
+            Rule::CondExpr | Rule::CmpExpr | Rule::AddExpr | Rule::MulExpr | Rule::BitExpr => {
                 let mut pairs_iter = pairs.clone().into_iter();
 
                 let lhs_pair = pairs_iter.next();
                 let op_pair = pairs_iter.next();
                 let rhs_pair = pairs_iter.next();
 
+                // conditional expressions have the two branches instead of an operator and rhs
+                if let (Some(condition), Some(then), Some(otherwise)) =
+                    (&lhs_pair, &op_pair, &rhs_pair)
+                {
+                    if then.as_rule() == Rule::CondExpr {
+                        return Ok(Node::ConditionalExpr {
+                            condition: Box::new(Ast::build_from_expr(
+                                condition.clone().into_inner(),
+                            )?),
+                            then: Box::new(Ast::build_from_expr(then.clone().into_inner())?),
+                            otherwise: Box::new(Ast::build_from_expr(
+                                otherwise.clone().into_inner(),
+                            )?),
+                        });
+                    }
+                }
+
                 // if lhs, rhs and operator exist -> build node, continue with inner
                 if lhs_pair.is_some() && op_pair.is_some() && rhs_pair.is_some() {
                     // unpack expressions
@@ -151,12 +350,22 @@ impl Ast {
                 let child_pair = pairs_iter.next();
 
                 // if unary expr packs operator and unary expr
-                if op_pair.is_some() && child_pair.is_some() {
+                let unary = if op_pair.is_some() && child_pair.is_some() {
                     let child = Ast::build_from_term(child_pair.unwrap().into_inner())?;
-                    Ok(Ast::build_from_unary_expr(op_pair.unwrap(), child))
+                    Ast::build_from_unary_expr(op_pair.unwrap(), child)
                 } else {
                     // otherwise, unary expr packs terminal
-                    Ast::build_from_term(pairs.peek().unwrap().into_inner())
+                    Ast::build_from_term(pairs.peek().unwrap().into_inner())?
+                };
+
+                // a BitExpr starts with the unary expr and may be followed by operator and rhs
+                let mut pairs_iter = pairs.clone().into_iter().skip(1);
+                match (pairs_iter.next(), pairs_iter.next()) {
+                    (Some(operator), Some(rhs)) => {
+                        let rhs = Ast::build_from_expr(rhs.into_inner())?;
+                        Ok(Ast::build_from_binary_expr(operator, unary, rhs))
+                    }
+                    _ => Ok(unary),
                 }
             }
             Rule::Term => Ast::build_from_term(pairs),
@@ -187,8 +396,15 @@ impl Ast {
                 "/" => Operator::Div,
                 "<<" => Operator::Sll,
                 ">>" => Operator::Slr,
+                ">>>" => Operator::Lsr,
                 "^" => Operator::Xor,
                 "|" => Operator::Or,
+                "==" => Operator::Eq,
+                "!=" => Operator::Ne,
+                "<" => Operator::Lt,
+                "<=" => Operator::Le,
+                ">" => Operator::Gt,
+                ">=" => Operator::Ge,
                 _ => unreachable!(),
             },
             lhs: Box::new(lhs),
@@ -200,6 +416,18 @@ impl Ast {
         let rule = pairs.peek().unwrap().as_rule();
         let pair = pairs.peek().unwrap();
         match rule {
+            Rule::StrLit => {
+                // strip the quotes, the unescaped text has to stay valid utf8
+                let text = pair.as_str();
+                let bytes = unescape(&text[1..text.len() - 1])?;
+                std::str::from_utf8(&bytes)
+                    .context("String literal is not valid utf8, use b\"..\" for raw bytes")?;
+                Ok(Node::Bytes(bytes))
+            }
+            Rule::ByteLit => {
+                let text = pair.as_str();
+                Ok(Node::Bytes(unescape(&text[2..text.len() - 1])?))
+            }
             Rule::HexInt => {
                 let hex_int_str = &pair.as_str()[2..];
                 let hex_int = i64::from_str_radix(&hex_int_str, 16)
@@ -212,7 +440,41 @@ impl Ast {
                 Ok(Node::Int(int))
             }
             Rule::Register => Ok(Node::Reg(pair.as_str().to_owned())),
-            Rule::AddExpr | Rule::MulExpr | Rule::BitExpr => Ast::build_from_expr(pairs),
+            Rule::Call => {
+                let mut pairs_iter = pair.into_inner();
+                let name = pairs_iter.next().unwrap().as_str();
+                let args = pairs_iter
+                    .map(|arg| Ast::build_from_expr(arg.into_inner()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                // minimum and maximum number of arguments
+                let (function, min_arity, max_arity) = match name {
+                    "len" => (Function::Len, 1, 1),
+                    "slice" => (Function::Slice, 3, 3),
+                    "u8" => (Function::U8, 1, 2),
+                    "u16" => (Function::U16, 1, 2),
+                    "u32" => (Function::U32, 1, 2),
+                    "u64" => (Function::U64, 1, 2),
+                    "base" => (Function::Base, 0, 0),
+                    "concat" => (Function::Concat, 1, usize::MAX),
+                    unknown => bail!("Unknown function: {}", unknown),
+                };
+                if args.len() < min_arity || args.len() > max_arity {
+                    let arity = if min_arity == max_arity {
+                        min_arity.to_string()
+                    } else if max_arity == usize::MAX {
+                        format!("at least {}", min_arity)
+                    } else {
+                        format!("{} to {}", min_arity, max_arity)
+                    };
+                    bail!("{} expects {} arguments, got {}", name, arity, args.len());
+                }
+
+                Ok(Node::Call { function, args })
+            }
+            Rule::CondExpr | Rule::CmpExpr | Rule::AddExpr | Rule::MulExpr | Rule::BitExpr => {
+                Ast::build_from_expr(pairs)
+            }
             unknown => bail!("Unknown term: {:?}", unknown),
         }
     }
@@ -318,4 +580,305 @@ mod tests {
 
         assert_eq!(result.unwrap(), [51]);
     }
+
+    #[test]
+    fn parse_cmp_expr() {
+        parses_to! {
+            parser: GrammarParser,
+            input: "1 <= 2",
+            rule: Rule::CmpExpr,
+            tokens: [
+                CmpExpr(0, 6, [
+                    AddExpr(0, 1, [
+                        MulExpr(0, 1, [
+                            BitExpr(0, 1, [
+                                UnaryExpr(0, 1, [
+                                    DecInt(0, 1),
+                                ]),
+                            ]),
+                        ]),
+                    ]),
+                    CmpOperator(2, 4),
+                    AddExpr(5, 6, [
+                        MulExpr(5, 6, [
+                            BitExpr(5, 6, [
+                                UnaryExpr(5, 6, [
+                                    DecInt(5, 6),
+                                ]),
+                            ]),
+                        ]),
+                    ]),
+                ]),
+            ]
+        }
+    }
+
+    #[test]
+    fn ast_conditional() {
+        let ast = Ast::new("1 + 2 == 3 ? 0x10 : 0x20");
+        assert!(ast.is_ok());
+
+        // comparison binds looser than arithmetic, the conditional loosest
+        assert_eq!(
+            ast.unwrap(),
+            Ast {
+                root: Box::new(Node::ConditionalExpr {
+                    condition: Box::new(Node::BinaryExpr {
+                        operator: Operator::Eq,
+                        lhs: Box::new(Node::BinaryExpr {
+                            operator: Operator::Add,
+                            lhs: Box::new(Node::Int(1)),
+                            rhs: Box::new(Node::Int(2)),
+                        }),
+                        rhs: Box::new(Node::Int(3)),
+                    }),
+                    then: Box::new(Node::Int(0x10)),
+                    otherwise: Box::new(Node::Int(0x20)),
+                }),
+            }
+        )
+    }
+
+    #[test]
+    fn ast_evaluate_conditional() {
+        let state = State::new(Target::Local, "cat", &[]).unwrap();
+        let eval = |script: &str| Ast::new(script).unwrap().get_result(&state).unwrap();
+
+        assert_eq!(eval("1 + 2 == 3 ? 0x10 : 0x20"), b"16");
+        assert_eq!(eval("1 + 2 != 3 ? 0x10 : 0x20"), b"32");
+        assert_eq!(eval("2 < 3"), b"1");
+        assert_eq!(eval("3 <= 3"), b"1");
+        assert_eq!(eval("2 > 3"), b"0");
+        assert_eq!(eval("2 >= 3"), b"0");
+
+        // branches may nest
+        assert_eq!(eval("0 ? 1 : (2 > 1) ? 2 : 3"), b"2");
+
+        // shifts are not mistaken for comparisons
+        assert_eq!(eval("1 << 4 > 8"), b"1");
+
+        let eval_int = |script: &str| Ast::new(script).unwrap().get_int(&state);
+        assert_eq!(eval_int("3 >= 2").unwrap(), 1);
+        assert_eq!(eval_int("\"12\"").unwrap(), 12);
+        assert!(eval_int("b\"magic\"").is_err());
+    }
+
+    #[test]
+    fn ast_check() {
+        let mut state = State::new(Target::Local, "cat", &[]).unwrap();
+        state.registers.set("x", b"32".to_vec());
+        let check = |script: &str| Ast::new(script).unwrap().check(&state);
+
+        assert!(check("$x == 0x20").is_ok());
+        assert!(check("$x & 0xfff").is_ok());
+        assert_eq!(
+            check("$x == 0x10").unwrap_err().to_string(),
+            "Assertion failed: 0x20 == 0x10"
+        );
+        assert_eq!(
+            check("$x + 1 <= 0x10").unwrap_err().to_string(),
+            "Assertion failed: 0x21 <= 0x10"
+        );
+        assert_eq!(
+            check("$x & 0xf").unwrap_err().to_string(),
+            "Assertion failed: expression evaluated to 0"
+        );
+        assert!(check("$missing == 1").is_err());
+    }
+
+    #[test]
+    fn ast_evaluate_bit_expr() {
+        let state = State::new(Target::Local, "cat", &[]).unwrap();
+        let eval = |script: &str| Ast::new(script).unwrap().get_result(&state).unwrap();
+
+        assert_eq!(eval("1 << 4"), b"16");
+        assert_eq!(eval("(0x30 >> 4) | 1"), b"3");
+        assert_eq!(eval("(6 & 3) ^ 1"), b"3");
+    }
+
+    #[test]
+    fn ast_evaluate_shift_right() {
+        let state = State::new(Target::Local, "cat", &[]).unwrap();
+        let eval = |script: &str| Ast::new(script).unwrap().get_result(&state).unwrap();
+
+        // >> propagates the sign, >>> shifts in zeros
+        assert_eq!(eval("-1 >> 1"), b"-1");
+        assert_eq!(eval("-1 >>> 1"), b"9223372036854775807");
+        assert_eq!(eval("-1 >>> 60"), b"15");
+        assert_eq!(eval("-1 >>> 64"), b"0");
+
+        // both agree on positive values
+        assert_eq!(eval("0x7f >> 4"), b"7");
+        assert_eq!(eval("0x7f >>> 4"), b"7");
+    }
+
+    #[test]
+    fn ast_evaluate_errors_and_overflow() {
+        let state = State::new(Target::Local, "cat", &[]).unwrap();
+        let eval = |script: &str| Ast::new(script).unwrap().get_result(&state);
+
+        // division and modulo by zero are errors instead of panics
+        assert!(eval("10 / 0").is_err());
+        assert!(eval("10 % (1 - 1)").is_err());
+        assert!(eval("1 << -1").is_err());
+
+        // shifting out all bits
+        assert_eq!(eval("1 << 64").unwrap(), b"0");
+        assert_eq!(eval("-8 >> 100").unwrap(), b"-1");
+
+        // overflowing arithmetic wraps around
+        assert_eq!(
+            eval("0x7fffffffffffffff + 1").unwrap(),
+            b"-9223372036854775808"
+        );
+        assert_eq!(eval("0x7fffffffffffffff * 2").unwrap(), b"-2");
+    }
+
+    #[test]
+    fn ast_call() {
+        assert_eq!(
+            Ast::new("slice($leak, 8, 2 * 8)").unwrap(),
+            Ast {
+                root: Box::new(Node::Call {
+                    function: Function::Slice,
+                    args: vec![
+                        Node::Reg("$leak".to_string()),
+                        Node::Int(8),
+                        Node::BinaryExpr {
+                            operator: Operator::Mul,
+                            lhs: Box::new(Node::Int(2)),
+                            rhs: Box::new(Node::Int(8)),
+                        },
+                    ],
+                }),
+            }
+        );
+
+        // unknown functions and wrong arity are rejected when parsing
+        assert!(Ast::new("strlen($leak)").is_err());
+        assert!(Ast::new("slice($leak, 1)").is_err());
+    }
+
+    #[test]
+    fn ast_evaluate_call() {
+        let mut state = State::new(Target::Local, "cat", &[]).unwrap();
+        state.registers.set(
+            "leak",
+            b"AAAAAAAA\x10\x32\x54\x76\x98\xba\x00\x00BBBB".to_vec(),
+        );
+        let eval = |script: &str| Ast::new(script).unwrap().get_result(&state);
+
+        assert_eq!(eval("len($leak)").unwrap(), b"20");
+        assert_eq!(eval("slice($leak, 16, len($leak))").unwrap(), b"BBBB");
+        assert_eq!(
+            eval("u64(slice($leak, 8, 16)) == 0xba9876543210").unwrap(),
+            b"1"
+        );
+        // a 6 byte pointer leak is zero extended
+        assert_eq!(
+            eval("u64(slice($leak, 8, 14)) - 0x3210").unwrap(),
+            format!("{}", 0xba9876540000i64).into_bytes()
+        );
+        assert_eq!(eval("u32(slice($leak, 0, 4))").unwrap(), b"1094795585");
+        assert_eq!(eval("u8(slice($leak, 8, 9)) + 1").unwrap(), b"17");
+        assert_eq!(
+            eval(r#"u16(slice($leak, 8, 10), "le") == 0x3210"#).unwrap(),
+            b"1"
+        );
+        assert_eq!(
+            eval(r#"u64(slice($leak, 8, 16), "be") == 0x1032547698ba0000"#).unwrap(),
+            b"1"
+        );
+        assert_eq!(
+            eval(r#"u32(slice($leak, 9, 12), "be")"#).unwrap(),
+            format!("{}", 0x325476).into_bytes()
+        );
+        assert!(eval(r#"u32(slice($leak, 0, 4), "middle")"#).is_err());
+        assert!(Ast::new(r#"u32($leak, "le", 1)"#).is_err());
+
+        // big endian binaries change the default
+        let path = std::env::temp_dir().join(format!("bochumoxide_be_{}", std::process::id()));
+        std::fs::write(&path, b"\x7fELF\x01\x02\x01").unwrap();
+        state.program_path = path.to_str().unwrap().to_string();
+        let eval = |script: &str| Ast::new(script).unwrap().get_result(&state);
+        assert_eq!(
+            eval("u16(slice($leak, 8, 10))").unwrap(),
+            format!("{}", 0x1032).into_bytes()
+        );
+        assert_eq!(
+            eval(r#"u16(slice($leak, 8, 10), "le")"#).unwrap(),
+            format!("{}", 0x3210).into_bytes()
+        );
+        std::fs::remove_file(path).unwrap();
+
+        assert!(eval("base() + 0x1139").is_err());
+        state.binary_base = Some(0x555555554000);
+        let eval = |script: &str| Ast::new(script).unwrap().get_result(&state);
+        assert_eq!(
+            eval("base() + 0x1139").unwrap(),
+            format!("{}", 0x555555555139i64).into_bytes()
+        );
+
+        assert!(eval("slice($leak, 16, 32)").is_err());
+        assert!(eval("u32(slice($leak, 0, 8))").is_err());
+        assert!(eval("len(5)").is_err());
+    }
+
+    #[test]
+    fn parse_literals() {
+        parses_to! {
+            parser: GrammarParser,
+            input: r#""a\"b" + b"\x90""#,
+            rule: Rule::AddExpr,
+            tokens: [
+                AddExpr(0, 16, [
+                    MulExpr(0, 6, [
+                        BitExpr(0, 6, [
+                            UnaryExpr(0, 6, [
+                                StrLit(0, 6),
+                            ]),
+                        ]),
+                    ]),
+                    AddOperator(7, 8),
+                    AddExpr(9, 16, [
+                        MulExpr(9, 16, [
+                            BitExpr(9, 16, [
+                                UnaryExpr(9, 16, [
+                                    ByteLit(9, 16),
+                                ]),
+                            ]),
+                        ]),
+                    ]),
+                ]),
+            ]
+        }
+    }
+
+    #[test]
+    fn ast_evaluate_concat() {
+        let mut state = State::new(Target::Local, "cat", &[]).unwrap();
+        state.registers.set("prefix", b"CTF".to_vec());
+        state.registers.set("num", b"41".to_vec());
+        let eval = |script: &str| Ast::new(script).unwrap().get_result(&state).unwrap();
+
+        assert_eq!(eval(r#"concat($prefix, "{flag}")"#), b"CTF{flag}");
+        assert_eq!(eval(r#"concat(b"\x90\x90", b"\xcc")"#), b"\x90\x90\xcc");
+        assert_eq!(eval(r#""a,\"b\n""#), b"a,\"b\n");
+
+        // integers are concatenated as their decimal text, digits are never added
+        assert_eq!(eval(r#"concat("A", 0x10, 1)"#), b"A161");
+        assert_eq!(eval(r#"concat("1", "2")"#), b"12");
+        assert_eq!(eval(r#"concat($num, "1", $prefix)"#), b"411CTF");
+        assert_eq!(eval(r#"len(concat("AAAA", $prefix))"#), b"7");
+        assert!(Ast::new("concat()").is_err());
+
+        // + stays arithmetic
+        assert_eq!(eval("$num + 1"), b"42");
+        let add = Ast::new(r#"$prefix + "{flag}""#).unwrap();
+        assert!(add.get_result(&state).is_err());
+
+        // plain strings have to stay valid utf8
+        assert!(Ast::new(r#""\xff""#).is_err());
+        assert!(Ast::new(r#""unterminated"#).is_err());
+    }
 }
@@ -1,9 +1,130 @@
+use anyhow::{Context, Result};
 use log::*;
 use simplelog::*;
 
-use std::fs::File;
+use std::collections::VecDeque;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-pub fn init_logger() {
+/// environment variables overriding the log file and the maximum logged level
+pub const LOG_FILE_ENV: &str = "BOCHUMOXIDE_LOG_FILE";
+pub const LOG_LEVEL_ENV: &str = "BOCHUMOXIDE_LOG_LEVEL";
+
+const LOG_FILE_NAME: &str = "log.log";
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Debug;
+/// number of log lines kept in memory for the gui
+pub const LOG_BUFFER_LINES: usize = 5000;
+
+/// where the log is written to and how verbose it is
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogSettings {
+    pub path: PathBuf,
+    pub level: LevelFilter,
+}
+
+impl LogSettings {
+    pub fn from_env() -> Result<Self> {
+        Self::from_values(
+            env::var(LOG_FILE_ENV).ok(),
+            env::var(LOG_LEVEL_ENV).ok(),
+            data_dir(),
+        )
+    }
+
+    /// empty values are treated like unset ones, without a data dir the log is put into the cwd
+    fn from_values(
+        path: Option<String>,
+        level: Option<String>,
+        data_dir: Option<PathBuf>,
+    ) -> Result<Self> {
+        let path = match path.filter(|p| !p.is_empty()) {
+            Some(path) => PathBuf::from(path),
+            None => data_dir.unwrap_or_default().join(LOG_FILE_NAME),
+        };
+        let level = match level.filter(|l| !l.is_empty()) {
+            Some(level) => level.parse().context(format!(
+                "Invalid {} {}, expected off, error, warn, info, debug or trace",
+                LOG_LEVEL_ENV, level
+            ))?,
+            None => DEFAULT_LEVEL,
+        };
+        Ok(LogSettings { path, level })
+    }
+}
+
+/// per-user directory for data of the app, e.g. ~/.local/share/BochumOxide
+pub fn data_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    let base =
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"));
+    #[cfg(not(target_os = "macos"))]
+    let base = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    base.map(|base| base.join("BochumOxide"))
+}
+
+/// most recent log lines shared with the gui, the oldest lines are dropped beyond the capacity
+#[derive(Clone, Debug)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        LogBuffer {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() >= self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    pub fn text(&self) -> String {
+        let lines = self.lines.lock().unwrap();
+        lines
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// sink of a WriteLogger, every completed line is pushed into the buffer
+struct LogBufferWriter {
+    buffer: LogBuffer,
+    pending: Vec<u8>,
+}
+
+impl Write for LogBufferWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(data);
+        while let Some(pos) = self.pending.iter().position(|&c| c == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            self.buffer
+                .push(String::from_utf8_lossy(&line[..pos]).into_owned());
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// log into the file of the settings and the returned buffer
+pub fn init_logger(settings: &LogSettings) -> Result<LogBuffer> {
     let config = ConfigBuilder::new()
         .set_time_level(LevelFilter::Off)
         .set_target_level(LevelFilter::Off)
@@ -12,9 +133,79 @@ pub fn init_logger() {
         .add_filter_allow_str("BochumOxide")
         .build();
 
-    let _ = WriteLogger::init(
-        LevelFilter::Trace,
-        config,
-        File::create(r"log.log").unwrap(),
-    );
+    if let Some(dir) = settings
+        .path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+    {
+        fs::create_dir_all(dir).context(format!("Unable to create {}", dir.display()))?;
+    }
+    let file = File::create(&settings.path).context(format!(
+        "Unable to create log file {}",
+        settings.path.display()
+    ))?;
+
+    let buffer = LogBuffer::new(LOG_BUFFER_LINES);
+    let writer = LogBufferWriter {
+        buffer: buffer.clone(),
+        pending: vec![],
+    };
+
+    CombinedLogger::init(vec![
+        WriteLogger::new(settings.level, config.clone(), writer),
+        WriteLogger::new(settings.level, config, file),
+    ])
+    .context("Failed to initialize logger")?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_settings() {
+        let data_dir = Some(PathBuf::from("/data/BochumOxide"));
+        assert_eq!(
+            LogSettings::from_values(None, None, data_dir.clone()).unwrap(),
+            LogSettings {
+                path: PathBuf::from("/data/BochumOxide/log.log"),
+                level: LevelFilter::Debug
+            }
+        );
+        assert_eq!(
+            LogSettings::from_values(Some("/tmp/x.log".into()), Some("TRACE".into()), data_dir)
+                .unwrap(),
+            LogSettings {
+                path: PathBuf::from("/tmp/x.log"),
+                level: LevelFilter::Trace
+            }
+        );
+
+        // empty variables and a missing data dir fall back to the defaults
+        let settings = LogSettings::from_values(Some("".into()), Some("".into()), None).unwrap();
+        assert_eq!(settings.path, PathBuf::from("log.log"));
+        assert_eq!(settings.level, LevelFilter::Debug);
+
+        assert!(LogSettings::from_values(None, Some("verbose".into()), None).is_err());
+    }
+
+    #[test]
+    fn test_log_buffer() {
+        let buffer = LogBuffer::new(3);
+        let mut writer = LogBufferWriter {
+            buffer: buffer.clone(),
+            pending: vec![],
+        };
+
+        // lines are only pushed once they are complete
+        writer.write_all(b"[DEBUG] first").unwrap();
+        assert_eq!(buffer.text(), "");
+        writer.write_all(b"\n[INFO] second\nthird\n").unwrap();
+        assert_eq!(buffer.text(), "[DEBUG] first\n[INFO] second\nthird");
+
+        // the oldest lines are dropped
+        writer.write_all(b"fourth\nfifth\n").unwrap();
+        assert_eq!(buffer.text(), "third\nfourth\nfifth");
+    }
 }
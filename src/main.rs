@@ -11,16 +11,28 @@ use iced::Application;
 use iced::Settings;
 
 mod binary_handling;
+mod cli;
 mod command;
+mod config;
 mod gui;
 mod lang;
 mod log;
 mod misc;
 mod program_io;
 mod recipe;
+mod theme;
 mod utils;
 
 fn main() -> Result<()> {
-    crate::log::init_logger();
-    App::run(Settings::default()).context("Failed to launch gui")
+    let log_settings = crate::log::LogSettings::from_env()?;
+    let log_buffer = crate::log::init_logger(&log_settings)?;
+
+    // bochumoxide run ... runs a recipe without the gui
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(options) = cli::parse_args(&args)? {
+        let success = cli::run(&options, &mut std::io::stdout())?;
+        std::process::exit(if success { 0 } else { 1 });
+    }
+
+    App::run(Settings::with_flags(log_buffer)).context("Failed to launch gui")
 }
@@ -0,0 +1,53 @@
+// reversed polynomial of the crc32 used by zlib, png and ethernet
+const CRC32_POLY: u32 = 0xedb8_8320;
+
+// largest prime below 2^16, the modulus of adler-32
+const ADLER32_MOD: u32 = 65521;
+
+// Computes the crc32 (ISO-HDLC, as in zlib) of data
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (CRC32_POLY & mask);
+        }
+    }
+    !crc
+}
+
+// Computes the adler-32 checksum (as in zlib streams) of data
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % ADLER32_MOD;
+        b = (b + a) % ADLER32_MOD;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414f_a339
+        );
+        assert_eq!(crc32(&[0; 32]), 0x190a_55ad);
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(b"123456789"), 0x091e_01de);
+        // the sums wrap around the modulus
+        assert_eq!(adler32(&[0xff; 5552]), 0xf18f_9b8c);
+    }
+}
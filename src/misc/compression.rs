@@ -0,0 +1,72 @@
+use anyhow::{Context, Result};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::{Read, Write};
+
+// Compresses data into the gzip format
+pub fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+// Decompresses gzip data, fails on malformed or truncated input
+pub fn gunzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(data)
+        .read_to_end(&mut decompressed)
+        .context("Invalid gzip data")?;
+    Ok(decompressed)
+}
+
+// Compresses data into the zlib format (deflate with zlib header and checksum)
+pub fn zlib_compress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+// Decompresses zlib data, fails on malformed or truncated input
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    ZlibDecoder::new(data)
+        .read_to_end(&mut decompressed)
+        .context("Invalid zlib data")?;
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &[u8] = b"AAAAAAAAAAAAAAAA\x00\xff flag{compressed}";
+
+    #[test]
+    fn test_gzip() {
+        let compressed = gzip(DATA).unwrap();
+        assert!(compressed.starts_with(b"\x1f\x8b"));
+        assert_eq!(gunzip(&compressed).unwrap(), DATA);
+
+        // output of `printf abc | gzip -n`
+        let abc = b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\x03\x4b\x4c\x4a\x06\x00\xc2\x41\x24\x35\x03\x00\x00\x00";
+        assert_eq!(gunzip(abc).unwrap(), b"abc");
+
+        assert!(gunzip(b"not gzip").is_err());
+        assert!(gunzip(&compressed[..compressed.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn test_zlib() {
+        let compressed = zlib_compress(DATA).unwrap();
+        assert!(compressed.starts_with(b"\x78\x9c"));
+        assert_eq!(zlib_decompress(&compressed).unwrap(), DATA);
+
+        // zlib.compress(b"abc") of python
+        let abc = b"\x78\x9c\x4b\x4c\x4a\x06\x00\x02\x4d\x01\x27";
+        assert_eq!(zlib_decompress(abc).unwrap(), b"abc");
+
+        assert!(zlib_decompress(b"\x78\x9c\xff\xff").is_err());
+        assert!(zlib_decompress(&compressed[..compressed.len() / 2]).is_err());
+    }
+}
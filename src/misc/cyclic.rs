@@ -1,4 +1,5 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use std::convert::TryFrom;
 
 // recursive De Bruijn sequence builder
 fn _db(t: usize, p: usize, n: usize, k: usize, sequence: &mut Vec<u8>, a: &mut Vec<u8>) {
@@ -70,49 +71,82 @@ fn _gen_find(subseq: &[u8], generator: &[u8]) -> Option<usize> {
     None
 }
 
+// default alphabet for cyclic patterns
+pub const DEFAULT_ALPHABET: &[u8] = b"abcd";
+
+// the whole sequence is generated up front, so its length is capped to keep the memory bounded
+pub const MAX_SEQUENCE_LENGTH: usize = 1 << 24;
+
+// repeated bytes make the pattern ambiguous and the offsets found in it wrong
+pub fn check_alphabet(alphabet: &[u8]) -> Result<()> {
+    if alphabet.is_empty() {
+        bail!("The alphabet is empty");
+    }
+    if let Some(i) = (1..alphabet.len()).find(|&i| alphabet[..i].contains(&alphabet[i])) {
+        bail!("Duplicate byte {:?} in the alphabet", alphabet[i] as char);
+    }
+    Ok(())
+}
+
+// length of the De Bruijn sequence, i.e. alphabet.len()^n, or why it can not be generated
+fn sequence_length(alphabet: &[u8], n: usize) -> Result<usize> {
+    if n == 0 {
+        bail!("n has to be at least 1");
+    }
+    check_alphabet(alphabet)?;
+    let alphabet_len = alphabet.len();
+    let length = u32::try_from(n)
+        .ok()
+        .and_then(|n| alphabet_len.checked_pow(n))
+        .filter(|&length| length <= MAX_SEQUENCE_LENGTH)
+        .context(format!(
+            "The sequence for alphabet length = {} and n = {} exceeds the maximum length of {}",
+            alphabet_len, n, MAX_SEQUENCE_LENGTH
+        ))?;
+    Ok(length)
+}
+
+// De Bruijn sequence over the bytes of an arbitrary alphabet
+fn de_bruijn_bytes(alphabet: &[u8], n: usize) -> Vec<u8> {
+    de_bruijn_int(alphabet.len(), n)
+        .iter()
+        .map(|elem| alphabet[*elem as usize])
+        .collect()
+}
+
 // wrapper over de_bruijn
-pub fn cyclic(length: usize, n: usize) -> Result<Vec<u8>> {
+// the sequence is at most alphabet.len()^n long, i.e. 256 for n = 4 and 65536 for n = 8 with abcd
+pub fn cyclic(length: usize, n: usize, alphabet: &[u8]) -> Result<Vec<u8>> {
     // length: wanted length of sequence
-    // alphabet: list of bytes/ints to generate the sequence over.
     // n: length of unique subsequences
+    // alphabet: list of bytes/ints to generate the sequence over.
     // return: at most length elements of sequence
-    let alphabet = b"abcd";
-    let max_sequence = alphabet.len().pow(n as u32);
+    let max_sequence = sequence_length(alphabet, n)?;
     if max_sequence < length {
-        panic!(
+        bail!(
             "Can't create a pattern of length = {} with alphabet length = {} and n = {}",
             length,
             alphabet.len(),
             n
         );
     }
-    let generator = de_bruijn_string(alphabet, n);
-    Ok(generator[..length].as_bytes().to_vec())
+    let generator = de_bruijn_bytes(alphabet, n);
+    Ok(generator[..length].to_vec())
 }
 
 // Calculates the position of a substring into a De Bruijn sequence
-pub fn cyclic_find(subseq: &[u8], n: usize) -> Option<usize> {
+pub fn cyclic_find(subseq: &[u8], n: usize, alphabet: &[u8]) -> Option<usize> {
     // subseq: subsequence to find
-    // alphabet: listto generate the sequence over
     // n: length of unique subsequences
+    // alphabet: list to generate the sequence over
     // return: position of a substring into a De Bruijn sequence
 
-    let alphabet = b"abcd";
-    if subseq.len() != n {
-        // subseq = &subseq[..n];
-        panic!("len(subseq) != n");
-    }
-
-    if subseq.iter().any(|i| !alphabet.contains(i)) {
-        panic!(
-            "Can't create a pattern length={} with len(alphabet)=={} and n=={}",
-            alphabet.len(),
-            alphabet.len(),
-            n
-        );
+    // needles of the wrong size or with bytes outside the alphabet can not be part of the sequence
+    if subseq.len() != n || subseq.iter().any(|i| !alphabet.contains(i)) {
+        return None;
     }
-    let k = alphabet.len();
-    _gen_find(subseq, &de_bruijn_string(alphabet, n).as_bytes())
+    sequence_length(alphabet, n).ok()?;
+    _gen_find(subseq, &de_bruijn_bytes(alphabet, n))
 }
 
 #[derive(Debug)]
@@ -126,36 +160,42 @@ pub struct CyclicGen {
 
 impl CyclicGen {
     // generate cyclic generator to generate sequential chunks of de Bruijn sequences
-    pub fn new(alphabet: &[u8], n: usize) -> Self {
+    pub fn new(alphabet: &[u8], n: usize) -> Result<Self> {
         // alphabet: numbers 0 - k
         // n: length of unique subsequences
-        CyclicGen {
+        sequence_length(alphabet, n)?;
+        Ok(CyclicGen {
             _generator: de_bruijn_int(alphabet.len(), n),
             _alphabet: alphabet.to_vec(),
             _total_length: 0,
             _n: n,
             _chunks: vec![],
-        }
+        })
     }
 
     // Get the next de Bruijn sequence from this generator.
     pub fn get(&mut self, length: usize) -> Result<Vec<u8>> {
         // length: size of chunk to get
         // return: a chunk of length
-        self._chunks.append(&mut vec![length]);
-        self._total_length += length;
-        let max_sequence = self._alphabet.len().pow(self._n as u32);
-
-        if max_sequence < self._total_length {
-            panic!(
+        let total_length = self._total_length + length;
+        if self._generator.len() < length {
+            bail!(
                 "Can't create a pattern length={} with len(alphabet)=={} and n=={}",
-                self._total_length,
+                total_length,
                 self._alphabet.len(),
                 self._n
             )
         }
+        self._chunks.append(&mut vec![length]);
+        self._total_length = total_length;
 
-        let res = self._generator.drain(..length).collect();
+        // translate alphabet indices into the actual alphabet
+        let alphabet = &self._alphabet;
+        let res = self
+            ._generator
+            .drain(..length)
+            .map(|elem| alphabet[elem as usize])
+            .collect();
 
         Ok(res)
     }
@@ -164,9 +204,9 @@ impl CyclicGen {
     pub fn find(self, subseq: &[u8]) -> Option<(usize, usize, usize)> {
         // subseq: subsequence to find
         // return: tuple (total_idx, chunk_idx, inside_chunk_idx) or None if not present
-        let total_idx = cyclic_find(subseq, self._n).unwrap();
+        let total_idx = cyclic_find(subseq, self._n, &self._alphabet)?;
         let mut inside_chunk_idx = total_idx;
-        for chunk_idx in 0..=self._chunks.len() {
+        for chunk_idx in 0..self._chunks.len() {
             let chunk = self._chunks[chunk_idx];
             if inside_chunk_idx < chunk {
                 return Some((total_idx, chunk_idx, inside_chunk_idx));
@@ -195,20 +235,129 @@ mod tests {
     #[test]
     fn test_cyclic() {
         assert_eq!(
-            cyclic(10, 3).unwrap(),
+            cyclic(10, 3, DEFAULT_ALPHABET).unwrap(),
             vec![97, 97, 97, 98, 97, 97, 99, 97, 97, 100]
         );
     }
 
     #[test]
     fn test_cyclic_find() {
-        assert_eq!(cyclic_find(&[97, 97, 97, 98], 4).unwrap(), 1);
+        assert_eq!(
+            cyclic_find(&[97, 97, 97, 98], 4, DEFAULT_ALPHABET).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_cyclic_find_rejected() {
+        // wrong needle length
+        assert_eq!(cyclic_find(b"aaa", 4, DEFAULT_ALPHABET), None);
+        assert_eq!(cyclic_find(b"aaaba", 4, DEFAULT_ALPHABET), None);
+        assert_eq!(cyclic_find(b"", 4, DEFAULT_ALPHABET), None);
+
+        // bytes outside of the alphabet
+        assert_eq!(cyclic_find(b"aaaz", 4, DEFAULT_ALPHABET), None);
+
+        // valid needle which only occurs when wrapping around the sequence (aabb)
+        assert_eq!(cyclic_find(b"bb", 2, b"ab"), Some(2));
+        assert_eq!(cyclic_find(b"ba", 2, b"ab"), None);
+    }
+
+    #[test]
+    fn test_cyclic_alphabet() {
+        let pattern = cyclic(20, 3, b"0123").unwrap();
+        assert!(pattern.iter().all(|c| b"0123".contains(c)));
+        assert_eq!(&pattern[..6], b"000100");
+        assert_eq!(cyclic_find(&pattern[11..14], 3, b"0123").unwrap(), 11);
+
+        // bytes outside of ascii are kept as they are
+        let pattern = cyclic(8, 3, &[0xfe, 0xff]).unwrap();
+        assert_eq!(
+            pattern,
+            vec![0xfe, 0xfe, 0xfe, 0xff, 0xfe, 0xff, 0xff, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_cyclic_64bit() {
+        // n = 8 allows patterns long enough for 64-bit stack overflows
+        let pattern = cyclic(65536, 8, DEFAULT_ALPHABET).unwrap();
+        assert_eq!(pattern.len(), 65536);
+        assert_eq!(
+            cyclic_find(&pattern[40000..40008], 8, DEFAULT_ALPHABET).unwrap(),
+            40000
+        );
     }
 
     #[test]
     fn test_generator_get() {
-        let mut gen = CyclicGen::new(&[0, 1, 2], 3);
+        let mut gen = CyclicGen::new(&[0, 1, 2], 3).unwrap();
         assert_eq!(gen.get(2).unwrap(), vec![0, 0]);
         assert_eq!(gen.get(6).unwrap(), vec![0, 1, 0, 0, 2, 0]);
+
+        // chunks are made of the generator's alphabet and found with it
+        let mut gen = CyclicGen::new(b"xyz", 3).unwrap();
+        assert_eq!(gen.get(4).unwrap(), b"xxxy");
+        assert_eq!(gen.find(b"xxy").unwrap(), (1, 0, 1));
+    }
+
+    #[test]
+    fn test_cyclic_rejected() {
+        // longer than the sequence
+        assert!(cyclic(1000, 2, DEFAULT_ALPHABET).is_err());
+        assert!(cyclic(70000, 4, DEFAULT_ALPHABET).is_err());
+        assert_eq!(cyclic(16, 2, DEFAULT_ALPHABET).unwrap().len(), 16);
+
+        // n out of range or an empty alphabet
+        assert!(cyclic(10, 64, DEFAULT_ALPHABET).is_err());
+        assert!(cyclic(10, usize::MAX, DEFAULT_ALPHABET).is_err());
+        assert!(cyclic(10, 13, DEFAULT_ALPHABET).is_err());
+        assert!(cyclic(0, 0, DEFAULT_ALPHABET).is_err());
+        assert!(cyclic(1, 4, b"").is_err());
+        assert_eq!(cyclic_find(b"", 0, DEFAULT_ALPHABET), None);
+        assert_eq!(cyclic_find(&[b'a'; 64], 64, DEFAULT_ALPHABET), None);
+
+        assert!(CyclicGen::new(DEFAULT_ALPHABET, 0).is_err());
+        assert!(CyclicGen::new(b"", 2).is_err());
+        let mut gen = CyclicGen::new(b"ab", 2).unwrap();
+        assert!(gen.get(3).is_ok());
+        assert!(gen.get(2).is_err());
+        assert_eq!(gen.get(1).unwrap(), b"b");
+    }
+
+    #[test]
+    fn test_cyclic_duplicate_alphabet() {
+        // with a repeated byte the pattern is no De Bruijn sequence and offsets would be wrong
+        let error = cyclic(10, 2, b"aab").unwrap_err();
+        assert_eq!(error.to_string(), "Duplicate byte 'a' in the alphabet");
+        assert_eq!(cyclic_find(b"ab", 2, b"aab"), None);
+        assert!(CyclicGen::new(b"abcb", 4).is_err());
+
+        assert!(check_alphabet(b"abc").is_ok());
+        assert!(check_alphabet(&[0xff, 0, 0xff]).is_err());
+        assert!(check_alphabet(b"").is_err());
+    }
+
+    #[test]
+    fn test_generator_find() {
+        let pattern = cyclic(256, 4, DEFAULT_ALPHABET).unwrap();
+        let new_gen = || {
+            let mut gen = CyclicGen::new(DEFAULT_ALPHABET, 4).unwrap();
+            for _ in 0..3 {
+                gen.get(10).unwrap();
+            }
+            gen
+        };
+
+        // first, middle and last chunk
+        assert_eq!(new_gen().find(&pattern[2..6]), Some((2, 0, 2)));
+        assert_eq!(new_gen().find(&pattern[15..19]), Some((15, 1, 5)));
+        assert_eq!(new_gen().find(&pattern[29..33]), Some((29, 2, 9)));
+
+        // part of the sequence, but never handed out by get
+        assert_eq!(new_gen().find(&pattern[100..104]), None);
+
+        // not part of the sequence at all
+        assert_eq!(new_gen().find(b"zzzz"), None);
     }
 }
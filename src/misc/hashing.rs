@@ -0,0 +1,206 @@
+use anyhow::{bail, Context, Result};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+// characters proof of work suffixes are made of, so they can be sent as text
+const POW_ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// difficulties above this would not finish in any reasonable time
+const MAX_POW_DIFFICULTY: u32 = 48;
+
+// number of suffixes tried before giving up, so an unlucky search errors instead of hanging the gui
+// (enough for about 30 bits, which takes up to a minute)
+const MAX_POW_ATTEMPTS: u64 = 1 << 30;
+
+// hash algorithms of the Hash ingredient
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "md5" => Ok(HashAlgorithm::Md5),
+            "sha1" => Ok(HashAlgorithm::Sha1),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            other => bail!("Unknown hash algorithm {}", other),
+        }
+    }
+}
+
+// Computes the md5 digest (16 bytes) of data
+pub fn md5(data: &[u8]) -> Vec<u8> {
+    Md5::digest(data).to_vec()
+}
+
+// Computes the sha1 digest (20 bytes) of data
+pub fn sha1(data: &[u8]) -> Vec<u8> {
+    Sha1::digest(data).to_vec()
+}
+
+// Computes the sha256 digest (32 bytes) of data
+pub fn sha256(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data).to_vec()
+}
+
+// Computes the digest of data with the given algorithm
+pub fn hash(algorithm: HashAlgorithm, data: &[u8]) -> Vec<u8> {
+    match algorithm {
+        HashAlgorithm::Md5 => md5(data),
+        HashAlgorithm::Sha1 => sha1(data),
+        HashAlgorithm::Sha256 => sha256(data),
+    }
+}
+
+// Counts the leading zero bits of a digest
+pub fn leading_zero_bits(digest: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in digest {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+// Suffix number n of the proof of work search, the digits of n in base 62
+fn pow_candidate(mut n: u64) -> Vec<u8> {
+    let base = POW_ALPHABET.len() as u64;
+    let mut candidate = vec![POW_ALPHABET[(n % base) as usize]];
+    n /= base;
+    while n > 0 {
+        candidate.push(POW_ALPHABET[(n % base) as usize]);
+        n /= base;
+    }
+    candidate
+}
+
+// Finds an alphanumeric suffix such that hash(challenge + suffix) starts with difficulty zero
+// bits. the candidates are split among one thread per cpu, the first suffix found wins
+pub fn solve_pow(algorithm: HashAlgorithm, challenge: &[u8], difficulty: u32) -> Result<Vec<u8>> {
+    solve_pow_within(algorithm, challenge, difficulty, MAX_POW_ATTEMPTS)
+}
+
+// solve_pow that fails once max_attempts suffixes were tried
+fn solve_pow_within(
+    algorithm: HashAlgorithm,
+    challenge: &[u8],
+    difficulty: u32,
+    max_attempts: u64,
+) -> Result<Vec<u8>> {
+    if difficulty > MAX_POW_DIFFICULTY {
+        bail!(
+            "Difficulty {} is too high, at most {} bits are supported",
+            difficulty,
+            MAX_POW_DIFFICULTY
+        );
+    }
+    let threads = thread::available_parallelism().map_or(4, |n| n.get()) as u64;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    let workers: Vec<_> = (0..threads)
+        .map(|start| {
+            let stop = stop.clone();
+            let sender = sender.clone();
+            let mut data = challenge.to_vec();
+            let challenge_len = data.len();
+            thread::spawn(move || {
+                let mut n = start;
+                while n < max_attempts && !stop.load(Ordering::Relaxed) {
+                    let suffix = pow_candidate(n);
+                    data.truncate(challenge_len);
+                    data.extend_from_slice(&suffix);
+                    if leading_zero_bits(&hash(algorithm, &data)) >= difficulty {
+                        let _ = sender.send(suffix);
+                        return;
+                    }
+                    n += threads;
+                }
+            })
+        })
+        .collect();
+    drop(sender);
+
+    // every worker dropped its sender without a result once the attempts are used up
+    let suffix = receiver.recv().context(format!(
+        "No proof of work suffix found within {} attempts",
+        max_attempts
+    ));
+    stop.store(true, Ordering::Relaxed);
+    for worker in workers {
+        let _ = worker.join();
+    }
+    suffix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digests() {
+        assert_eq!(hex::encode(md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex::encode(md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            hex::encode(sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hex::encode(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(hash(HashAlgorithm::Sha1, b"abc"), sha1(b"abc"));
+    }
+
+    #[test]
+    fn test_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0x00, 0x00, 0x1f, 0x00]), 19);
+        assert_eq!(leading_zero_bits(&[0x80]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+
+    #[test]
+    fn test_pow_candidate() {
+        assert_eq!(pow_candidate(0), b"0");
+        assert_eq!(pow_candidate(61), b"Z");
+        assert_eq!(pow_candidate(62), b"01");
+    }
+
+    #[test]
+    fn test_solve_pow() {
+        for &algorithm in &[HashAlgorithm::Md5, HashAlgorithm::Sha256] {
+            let suffix = solve_pow(algorithm, b"challenge", 12).unwrap();
+            assert!(suffix.iter().all(|c| c.is_ascii_alphanumeric()));
+
+            let data = [&b"challenge"[..], &suffix].concat();
+            assert!(leading_zero_bits(&hash(algorithm, &data)) >= 12);
+        }
+        assert!(solve_pow(HashAlgorithm::Sha1, b"", 64).is_err());
+
+        // giving up after the attempts instead of searching forever
+        let error = solve_pow_within(HashAlgorithm::Sha256, b"challenge", 40, 1000).unwrap_err();
+        assert!(error.to_string().contains("within 1000 attempts"));
+    }
+
+    #[test]
+    fn test_hash_algorithm_from_str() {
+        assert_eq!(
+            " SHA256".parse::<HashAlgorithm>().unwrap(),
+            HashAlgorithm::Sha256
+        );
+        assert_eq!("md5".parse::<HashAlgorithm>().unwrap(), HashAlgorithm::Md5);
+        assert!("sha512".parse::<HashAlgorithm>().is_err());
+    }
+}
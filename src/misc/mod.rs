@@ -1,3 +1,8 @@
+pub mod checksum;
+pub mod compression;
 pub mod cyclic;
 pub mod fiddling;
+pub mod fmtstr;
+pub mod hashing;
 pub mod packing;
+pub mod shellcraft;
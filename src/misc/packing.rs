@@ -1,11 +1,41 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use std::str::FromStr;
 
-#[derive(Clone, Copy)]
+use super::fiddling::unescape;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Endian {
     Little,
     Big,
 }
 
+impl FromStr for Endian {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "le" => Ok(Endian::Little),
+            "be" => Ok(Endian::Big),
+            other => bail!("Unknown endianness {}, expected le or be", other),
+        }
+    }
+}
+
+// unpack 1 to 8 bytes into an unsigned integer, shorter inputs (e.g. a leaked 6 byte pointer) are
+// zero extended
+pub fn unpack_uint(bytes: &[u8], endian: Endian) -> Result<u64> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        bail!("Can not unpack {} bytes into an integer", bytes.len());
+    }
+    Ok(bytes.iter().enumerate().fold(0, |value, (i, &byte)| {
+        let shift = match endian {
+            Endian::Little => i,
+            Endian::Big => bytes.len() - 1 - i,
+        };
+        value | (byte as u64) << (shift * 8)
+    }))
+}
+
 // pack 8-bit integer
 pub fn pack8(v: u8) -> Vec<u8> {
     let wtr = vec![v];
@@ -140,10 +170,282 @@ pub fn unpack64(vec: &[u8], endian: Endian) -> Result<u64> {
     Ok(result)
 }
 
+// pack signed 8-bit integer (two's complement)
+pub fn pack_i8(v: i8) -> Vec<u8> {
+    pack8(v as u8)
+}
+
+// unpack signed 8-bit integer (two's complement)
+pub fn unpack_i8(vec: &[u8]) -> Result<i8> {
+    Ok(unpack8(vec)? as i8)
+}
+
+// pack signed 16-bit integer (two's complement)
+pub fn pack_i16(v: i16, endian: Endian) -> [u8; 2] {
+    pack16(v as u16, endian)
+}
+
+// unpack signed 16-bit integer (two's complement)
+pub fn unpack_i16(vec: &[u8], endian: Endian) -> Result<i16> {
+    Ok(unpack16(vec, endian)? as i16)
+}
+
+// pack signed 32-bit integer (two's complement)
+pub fn pack_i32(v: i32, endian: Endian) -> [u8; 4] {
+    pack32(v as u32, endian)
+}
+
+// unpack signed 32-bit integer (two's complement)
+pub fn unpack_i32(vec: &[u8], endian: Endian) -> Result<i32> {
+    Ok(unpack32(vec, endian)? as i32)
+}
+
+// pack signed 64-bit integer (two's complement)
+pub fn pack_i64(v: i64, endian: Endian) -> [u8; 8] {
+    pack64(v as u64, endian)
+}
+
+// unpack signed 64-bit integer (two's complement)
+pub fn unpack_i64(vec: &[u8], endian: Endian) -> Result<i64> {
+    Ok(unpack64(vec, endian)? as i64)
+}
+
+// pack 32-bit IEEE-754 float
+pub fn pack_f32(v: f32, endian: Endian) -> [u8; 4] {
+    pack32(v.to_bits(), endian)
+}
+
+// unpack 32-bit IEEE-754 float
+pub fn unpack_f32(vec: &[u8], endian: Endian) -> Result<f32> {
+    Ok(f32::from_bits(unpack32(vec, endian)?))
+}
+
+// pack 64-bit IEEE-754 float
+pub fn pack_f64(v: f64, endian: Endian) -> [u8; 8] {
+    pack64(v.to_bits(), endian)
+}
+
+// unpack 64-bit IEEE-754 float
+pub fn unpack_f64(vec: &[u8], endian: Endian) -> Result<f64> {
+    Ok(f64::from_bits(unpack64(vec, endian)?))
+}
+
+// pack integer into a given bit width, failing if it does not fit
+pub fn pack(v: u64, bits: usize, endian: Endian) -> Result<Vec<u8>> {
+    if bits < 64 && v >> bits != 0 {
+        bail!("Value {:#x} does not fit into {} bits", v, bits);
+    }
+
+    let packed = match bits {
+        8 => pack8(v as u8),
+        16 => pack16(v as u16, endian).to_vec(),
+        32 => pack32(v as u32, endian).to_vec(),
+        64 => pack64(v, endian).to_vec(),
+        _ => bail!("Unsupported width {}", bits),
+    };
+    Ok(packed)
+}
+
+// parse a number given either in decimal or as 0x-prefixed hex
+pub fn parse_number(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let number = match input.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16)?,
+        None => input.parse()?,
+    };
+    Ok(number)
+}
+
+// parse a number in the given radix (10 or 16, a 0x prefix is optional for 16) or like
+// parse_number if radix is None. surrounding whitespace is ignored
+pub fn parse_int(input: &str, radix: Option<u32>) -> Result<u64> {
+    let input = input.trim();
+    let number = match radix {
+        None => parse_number(input)?,
+        Some(16) => u64::from_str_radix(input.strip_prefix("0x").unwrap_or(input), 16)?,
+        Some(10) => input.parse()?,
+        Some(other) => bail!("Unsupported base {}", other),
+    };
+    Ok(number)
+}
+
+// single element of a flat payload
+#[derive(Clone, Debug, PartialEq)]
+pub enum FlatItem {
+    Bytes(Vec<u8>),
+    Int {
+        value: u64,
+        bits: usize,
+        endian: Endian,
+    },
+}
+
+// concatenate raw bytes and packed integers into one payload
+pub fn flat(items: &[FlatItem]) -> Result<Vec<u8>> {
+    let mut payload = vec![];
+    for item in items {
+        match item {
+            FlatItem::Bytes(bytes) => payload.extend_from_slice(bytes),
+            FlatItem::Int {
+                value,
+                bits,
+                endian,
+            } => payload.extend(pack(*value, *bits, *endian)?),
+        }
+    }
+    Ok(payload)
+}
+
+// parse flat input such as: b"AAAA", p64(0x401234), p32be(1), 0x90*8
+pub fn parse_flat(input: &str) -> Result<Vec<FlatItem>> {
+    let mut items = vec![];
+    for part in split_flat_items(input)? {
+        let part = part.trim();
+        // allow trailing commas
+        if part.is_empty() {
+            continue;
+        }
+
+        // parse the item itself and keep what follows it (repetition)
+        let (item, rest) = if let Some(literal) = part.strip_prefix("b\"") {
+            let (bytes, rest) = parse_byte_string(literal)?;
+            (FlatItem::Bytes(bytes), rest)
+        } else if part.starts_with('p') {
+            let open = part.find('(').context("Missing ( after pack width")?;
+            let close = part.find(')').context("Missing ) after pack value")?;
+            let (bits, endian) = match &part[..open] {
+                "p8" => (8, Endian::Little),
+                "p16" => (16, Endian::Little),
+                "p32" => (32, Endian::Little),
+                "p64" => (64, Endian::Little),
+                "p16be" => (16, Endian::Big),
+                "p32be" => (32, Endian::Big),
+                "p64be" => (64, Endian::Big),
+                other => bail!("Unknown pack width {}", other),
+            };
+            let value = parse_number(&part[open + 1..close])?;
+            (
+                FlatItem::Int {
+                    value,
+                    bits,
+                    endian,
+                },
+                &part[close + 1..],
+            )
+        } else {
+            // plain numbers are single bytes
+            let end = part.find('*').unwrap_or_else(|| part.len());
+            let value = parse_number(&part[..end])?;
+            if value > 0xff {
+                bail!(
+                    "{:#x} is not a byte, use p16/p32/p64 for wider values",
+                    value
+                );
+            }
+            (FlatItem::Bytes(vec![value as u8]), &part[end..])
+        };
+
+        // optional repetition, e.g. 0x90*8
+        let rest = rest.trim();
+        let count = match rest.strip_prefix('*') {
+            Some(count) => count.trim().parse().context("Unable to parse repetition")?,
+            None if rest.is_empty() => 1,
+            None => bail!("Unexpected {} after item", rest),
+        };
+        items.extend(std::iter::repeat(item).take(count));
+    }
+    Ok(items)
+}
+
+// split flat input at commas that are not part of a byte string or a pack call
+fn split_flat_items(input: &str) -> Result<Vec<&str>> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (idx, c) in input.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&input[start..idx]);
+                start = idx + 1;
+            }
+            _ => (),
+        }
+    }
+
+    if in_string {
+        bail!("Unterminated byte string");
+    }
+    parts.push(&input[start..]);
+    Ok(parts)
+}
+
+// parse the content of a byte string up to its closing quote, returns the bytes and the remaining input
+fn parse_byte_string(literal: &str) -> Result<(Vec<u8>, &str)> {
+    let mut escaped = false;
+    for (idx, c) in literal.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Ok((unescape(&literal[..idx])?, &literal[idx + 1..])),
+            _ => (),
+        }
+    }
+    bail!("Unterminated byte string")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_unpack_uint() {
+        assert_eq!(unpack_uint(b"\x10\x32", Endian::Little).unwrap(), 0x3210);
+        assert_eq!(unpack_uint(b"\x10\x32", Endian::Big).unwrap(), 0x1032);
+        assert_eq!(
+            unpack_uint(b"\x10\x32\x54\x76\xfd\x7f", Endian::Little).unwrap(),
+            0x7ffd76543210
+        );
+        assert_eq!(
+            unpack_uint(b"\x00\x00\x7f\xfd\x76\x54\x32\x10", Endian::Big).unwrap(),
+            0x7ffd76543210
+        );
+        assert!(unpack_uint(b"", Endian::Little).is_err());
+        assert!(unpack_uint(&[0; 9], Endian::Big).is_err());
+
+        assert_eq!(" be".parse::<Endian>().unwrap(), Endian::Big);
+        assert_eq!("le".parse::<Endian>().unwrap(), Endian::Little);
+        assert!("big".parse::<Endian>().is_err());
+    }
+
+    #[test]
+    fn test_parse_int() {
+        assert_eq!(parse_int(" 0xdeadbeef\n", None).unwrap(), 0xdeadbeef);
+        assert_eq!(parse_int("1234567", None).unwrap(), 1234567);
+        assert_eq!(parse_int("deadbeef", Some(16)).unwrap(), 0xdeadbeef);
+        assert_eq!(parse_int("0x10", Some(16)).unwrap(), 16);
+        assert_eq!(parse_int("0010\r\n", Some(10)).unwrap(), 10);
+
+        assert!(parse_int("deadbeef", None).is_err());
+        assert!(parse_int("0x10", Some(10)).is_err());
+        assert!(parse_int("", Some(16)).is_err());
+        assert!(parse_int("10", Some(8)).is_err());
+    }
+
     #[test]
     fn test_pack8() {
         assert_eq!(pack8(5), b"\x05");
@@ -248,4 +550,126 @@ mod tests {
     fn test_unpack64_panic_big_endian() {
         unpack64(b"\x00\x03\x43\x95\x4d\x60\x86\x83\x11", Endian::Big).unwrap();
     }
+
+    #[test]
+    fn test_pack() {
+        assert_eq!(
+            pack(0xdeadbeef, 64, Endian::Little).unwrap(),
+            b"\xef\xbe\xad\xde\x00\x00\x00\x00"
+        );
+        assert_eq!(
+            pack(0xdeadbeef, 32, Endian::Big).unwrap(),
+            b"\xde\xad\xbe\xef"
+        );
+        assert_eq!(pack(0x41, 8, Endian::Big).unwrap(), b"\x41");
+
+        // values exceeding the width and unknown widths are errors
+        assert!(pack(0x100, 8, Endian::Little).is_err());
+        assert!(pack(0x1, 24, Endian::Little).is_err());
+    }
+
+    #[test]
+    fn test_pack_signed() {
+        assert_eq!(pack_i8(-1), b"\xff");
+        assert_eq!(&pack_i16(-2, Endian::Little), b"\xfe\xff");
+        assert_eq!(&pack_i16(-2, Endian::Big), b"\xff\xfe");
+        assert_eq!(&pack_i32(-15, Endian::Little), b"\xf1\xff\xff\xff");
+        assert_eq!(&pack_i32(-15, Endian::Big), b"\xff\xff\xff\xf1");
+        assert_eq!(&pack_i64(-1, Endian::Little), &[0xff; 8]);
+        assert_eq!(
+            &pack_i64(i64::MIN, Endian::Big),
+            b"\x80\x00\x00\x00\x00\x00\x00\x00"
+        );
+
+        // positive values are packed like their unsigned counterparts
+        assert_eq!(&pack_i32(517, Endian::Little), &pack32(517, Endian::Little));
+    }
+
+    #[test]
+    fn test_unpack_signed() {
+        assert_eq!(unpack_i8(b"\xff").unwrap(), -1);
+        assert_eq!(unpack_i16(b"\xfe\xff", Endian::Little).unwrap(), -2);
+        assert_eq!(
+            unpack_i32(b"\xf1\xff\xff\xff", Endian::Little).unwrap(),
+            -15
+        );
+        assert_eq!(unpack_i32(b"\xff\xff\xff\xf1", Endian::Big).unwrap(), -15);
+        assert_eq!(
+            unpack_i64(b"\x80\x00\x00\x00\x00\x00\x00\x00", Endian::Big).unwrap(),
+            i64::MIN
+        );
+        assert!(unpack_i32(b"\xff\xff\xff\xff\xff", Endian::Little).is_err());
+    }
+
+    #[test]
+    fn test_pack_float() {
+        assert_eq!(&pack_f32(1.0, Endian::Little), b"\x00\x00\x80\x3f");
+        assert_eq!(&pack_f32(-2.5, Endian::Big), b"\xc0\x20\x00\x00");
+        assert_eq!(
+            &pack_f64(1.0, Endian::Big),
+            b"\x3f\xf0\x00\x00\x00\x00\x00\x00"
+        );
+        assert_eq!(
+            &pack_f64(-0.1, Endian::Little),
+            b"\x9a\x99\x99\x99\x99\x99\xb9\xbf"
+        );
+        assert_eq!(&pack_f32(f32::NAN, Endian::Big), b"\x7f\xc0\x00\x00");
+    }
+
+    #[test]
+    fn test_unpack_float() {
+        assert_eq!(
+            unpack_f32(b"\x00\x00\x80\x3f", Endian::Little).unwrap(),
+            1.0
+        );
+        assert_eq!(unpack_f32(b"\xc0\x20\x00\x00", Endian::Big).unwrap(), -2.5);
+        assert_eq!(
+            unpack_f64(b"\x9a\x99\x99\x99\x99\x99\xb9\xbf", Endian::Little).unwrap(),
+            -0.1
+        );
+        assert!(unpack_f64(&pack_f64(f64::NAN, Endian::Big), Endian::Big)
+            .unwrap()
+            .is_nan());
+    }
+
+    #[test]
+    fn test_flat() {
+        let items = vec![
+            FlatItem::Bytes(b"AB".to_vec()),
+            FlatItem::Int {
+                value: 0x401234,
+                bits: 64,
+                endian: Endian::Little,
+            },
+            FlatItem::Int {
+                value: 0x1234,
+                bits: 16,
+                endian: Endian::Big,
+            },
+        ];
+        assert_eq!(
+            flat(&items).unwrap(),
+            b"AB\x34\x12\x40\x00\x00\x00\x00\x00\x12\x34"
+        );
+    }
+
+    #[test]
+    fn test_parse_flat() {
+        let items = parse_flat(r#"b"AAAA", p64(0x401234), 0x90*2, p32be(1)"#).unwrap();
+        assert_eq!(
+            flat(&items).unwrap(),
+            b"AAAA\x34\x12\x40\x00\x00\x00\x00\x00\x90\x90\x00\x00\x00\x01"
+        );
+
+        // commas, quotes and escapes inside byte strings
+        let items = parse_flat(r#"b"a,\"\x41\n"*2,"#).unwrap();
+        assert_eq!(flat(&items).unwrap(), b"a,\"A\na,\"A\n");
+
+        // invalid input
+        assert!(parse_flat(r#"b"AAAA"#).is_err());
+        assert!(parse_flat("0x100").is_err());
+        assert!(parse_flat("p24(1)").is_err());
+        assert!(parse_flat("p8(0x100)").and_then(|x| flat(&x)).is_err());
+        assert!(parse_flat("0x90 0x90").is_err());
+    }
 }
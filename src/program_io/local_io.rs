@@ -1,78 +1,240 @@
-use anyhow::{Context, Result};
-#[cfg(features = "unicorn")]
-use timeout_readwrite::TimeoutReader;
+use anyhow::{anyhow, bail, Context, Result};
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::pty::openpty;
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
 
-use std::io::{BufRead, BufReader, Read, Write};
-use std::process::{Command, Stdio};
-use std::time::Duration;
 use log::*;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::TcpListener;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::PathBuf;
+use std::process::{Child, ChildStderr, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::program_io::ProgramIO;
+use crate::program_io::{Eof, ProgramIO, Timeout};
 
 pub struct LocalIO {
-    process_handle: std::process::Child,
-    #[cfg(features = "unicorn")]
-    stdout_reader: BufReader<TimeoutReader<std::process::ChildStdout>>,
-    #[cfg(not(features = "unicorn"))]
-    stdout_reader: BufReader<std::process::ChildStdout>,
+    process_handle: Child,
+    /// write end of the child's stdin (pipe or pty master), None once it has been closed
+    stdin_writer: Option<Box<dyn Write + Send>>,
+    /// read end of the child's stdout (pipe or pty master)
+    stdout_reader: BufReader<Box<dyn Read + Send>>,
+    /// raw file descriptor behind stdout_reader, used to poll for pending data
+    stdout_fd: RawFd,
+    /// stderr data collected by a background thread, drained by recv_stderr
+    stderr_buf: Arc<Mutex<Vec<u8>>>,
     cmd: String,
+    /// attach stdin/stdout of the child to a pseudo-terminal instead of pipes
+    pty: bool,
+    options: LocalOptions,
+    debug_mode: DebugMode,
 }
 
+/// settings for spawning the process besides the program and its arguments
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LocalOptions {
+    /// environment variables set for the process
+    pub env: Vec<(String, String)>,
+    /// start from an empty environment that only holds env, e.g. to get reproducible stack addresses
+    pub clear_env: bool,
+    /// working directory of the process instead of our own, e.g. for relative paths like flag.txt
+    pub cwd: Option<String>,
+    /// capacity of the buffer stdout is read through, None for DEFAULT_BUFFER_CAPACITY
+    pub buffer_capacity: Option<usize>,
+}
+
+/// stdout buffer capacity, larger than the std default (8 KiB) so that big binary transfers take
+/// fewer reads
+pub const DEFAULT_BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// position of terminator in data, of which the first searched bytes were already searched before.
+/// only matches that end in the new bytes are looked for
+fn find_new_terminator(data: &[u8], terminator: &[u8], searched: usize) -> Option<usize> {
+    if terminator.is_empty() {
+        return Some(0);
+    }
+    let start = searched.saturating_sub(terminator.len() - 1);
+    data[start..]
+        .windows(terminator.len())
+        .position(|x| x == terminator)
+        .map(|pos| start + pos)
+}
+
+/// parse environment variables such as: FOO=bar LD_PRELOAD=./libc.so.6
+pub fn parse_env(input: &str) -> Result<Vec<(String, String)>> {
+    input
+        .split_whitespace()
+        .map(|var| match var.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+            _ => bail!("Malformed environment variable {}, expected KEY=VALUE", var),
+        })
+        .collect()
+}
+
+/// how attach_debugger makes the process available to gdb
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DebugMode {
+    /// gdb in a local terminal window, the command gdb is appended to (e.g. tmux splitw -h) or
+    /// None to try the default terminals
+    Terminal(Option<String>),
+    /// gdbserver on the port, connect from your own gdb with target remote localhost:port
+    GdbServer(u16),
+}
+
+impl Default for DebugMode {
+    fn default() -> Self {
+        DebugMode::Terminal(None)
+    }
+}
+
+pub const DEFAULT_GDBSERVER_PORT: u16 = 1234;
+
 impl LocalIO {
     pub fn new(file: &str, args: &[&str]) -> Result<Self> {
-        let mut process_handle = Command::new(&file)
-            .args(args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .context("Couldn't spawn process")?;
-
-        #[cfg(features = "unicorn")]
-        let stdout_reader = BufReader::new(TimeoutReader::new(
-            process_handle.stdout.take().unwrap(),
-            Duration::new(5, 0),
-        ));
-
-        #[cfg(not(features = "unicorn"))]
-        let stdout_reader = BufReader::new(process_handle.stdout.take().unwrap());
+        Self::spawn(file, args, false, LocalOptions::default())
+    }
+
+    /// spawn the process attached to a pseudo-terminal so that isatty() holds for stdin/stdout
+    pub fn new_pty(file: &str, args: &[&str]) -> Result<Self> {
+        Self::spawn(file, args, true, LocalOptions::default())
+    }
+
+    /// spawn the process with the given options, which are kept across restarts
+    pub fn with_options(
+        file: &str,
+        args: &[&str],
+        pty: bool,
+        options: LocalOptions,
+    ) -> Result<Self> {
+        Self::spawn(file, args, pty, options)
+    }
+
+    fn spawn(file: &str, args: &[&str], pty: bool, options: LocalOptions) -> Result<Self> {
+        check_program(file, &options)?;
+
+        let mut command = Command::new(&file);
+        command.args(args).stderr(Stdio::piped());
+        if options.clear_env {
+            command.env_clear();
+        }
+        command.envs(options.env.iter().map(|(key, value)| (key, value)));
+        if let Some(cwd) = &options.cwd {
+            command.current_dir(cwd);
+        }
+
+        // the pty master is kept by us, the slave end is handed to the child
+        let master = if pty {
+            let pty = openpty(None, None).context("Failed to open pseudo-terminal")?;
+
+            // raw mode disables echo and newline translation, so data passes through unmodified
+            let mut termios = tcgetattr(pty.slave).context("Failed to get terminal attributes")?;
+            cfmakeraw(&mut termios);
+            tcsetattr(pty.slave, SetArg::TCSANOW, &termios)
+                .context("Failed to set terminal attributes")?;
+
+            // take ownership of the raw file descriptors so that they are closed on drop
+            let master = unsafe { File::from_raw_fd(pty.master) };
+            let slave = unsafe { File::from_raw_fd(pty.slave) };
+
+            command
+                .stdin(Stdio::from(slave.try_clone()?))
+                .stdout(Stdio::from(slave));
+            Some(master)
+        } else {
+            command.stdin(Stdio::piped()).stdout(Stdio::piped());
+            None
+        };
+
+        let mut process_handle = command.spawn().context("Couldn't spawn process")?;
+
+        // close our copy of the slave end, otherwise reading the master never notices an exit
+        drop(command);
+
+        let (stdin_writer, stdout_reader, stdout_fd): (
+            Box<dyn Write + Send>,
+            Box<dyn Read + Send>,
+            RawFd,
+        ) = match master {
+            Some(master) => {
+                let master_fd = master.as_raw_fd();
+                (
+                    Box::new(master.try_clone()?),
+                    Box::new(PtyMaster(master)),
+                    master_fd,
+                )
+            }
+            None => {
+                let stdout = process_handle.stdout.take().unwrap();
+                let stdout_fd = stdout.as_raw_fd();
+                (
+                    Box::new(process_handle.stdin.take().unwrap()),
+                    Box::new(stdout),
+                    stdout_fd,
+                )
+            }
+        };
+        let stderr_buf = Self::spawn_stderr_reader(process_handle.stderr.take().unwrap());
 
         Ok(LocalIO {
             process_handle,
-            stdout_reader,
+            stdin_writer: Some(stdin_writer),
+            stdout_reader: BufReader::with_capacity(
+                options.buffer_capacity.unwrap_or(DEFAULT_BUFFER_CAPACITY),
+                stdout_reader,
+            ),
+            stdout_fd,
+            stderr_buf,
             cmd: file.to_owned(),
+            pty,
+            options,
+            debug_mode: DebugMode::default(),
         })
     }
+
+    /// read stderr in a background thread so that polling it never blocks
+    fn spawn_stderr_reader(mut stderr: ChildStderr) -> Arc<Mutex<Vec<u8>>> {
+        let stderr_buf = Arc::new(Mutex::new(Vec::new()));
+        let thread_buf = stderr_buf.clone();
+
+        std::thread::spawn(move || {
+            let mut temp = [0u8; 4096];
+
+            // a read size of 0 means that the child closed stderr (i.e. it exited)
+            while let Ok(read_size) = stderr.read(&mut temp) {
+                if read_size == 0 {
+                    break;
+                }
+                match thread_buf.lock() {
+                    Ok(mut buf) => buf.extend_from_slice(&temp[..read_size]),
+                    Err(_) => break,
+                }
+            }
+        });
+
+        stderr_buf
+    }
+
+    fn stdin_writer(&mut self) -> Result<&mut Box<dyn Write + Send>> {
+        self.stdin_writer
+            .as_mut()
+            .context("Input of the process has been closed")
+    }
 }
 
 impl ProgramIO for LocalIO {
     fn restart(&mut self) -> Result<()> {
         let args: &[&str] = &[];
-        let mut process_handle = Command::new(&self.cmd)
-            .args(args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .context("Couldn't spawn process")?;
-
-        #[cfg(features = "unicorn")]
-        let stdout_reader = BufReader::new(TimeoutReader::new(
-            process_handle.stdout.take().unwrap(),
-            Duration::new(5, 0),
-        ));
-
-        #[cfg(not(features = "unicorn"))]
-        let stdout_reader = BufReader::new(process_handle.stdout.take().unwrap());
-
-        self.process_handle = process_handle;
-        self.stdout_reader = stdout_reader;
+        let debug_mode = std::mem::take(&mut self.debug_mode);
+        *self = Self::spawn(&self.cmd, args, self.pty, self.options.clone())?;
+        self.debug_mode = debug_mode;
         Ok(())
     }
 
     fn send(&mut self, data: &[u8]) -> Result<()> {
-        self.process_handle
-            .stdin
-            .as_mut()
-            .unwrap()
+        self.stdin_writer()?
             .write_all(data.as_ref())
             .context("Failed to send to process")?;
 
@@ -81,16 +243,11 @@ impl ProgramIO for LocalIO {
 
     fn send_line(&mut self, data: &[u8]) -> Result<()> {
         let data = data.as_ref();
-        self.process_handle
-            .stdin
-            .as_mut()
-            .unwrap()
+        let stdin_writer = self.stdin_writer()?;
+        stdin_writer
             .write_all(data)
             .context("Failed to send to process")?;
-        self.process_handle
-            .stdin
-            .as_mut()
-            .unwrap()
+        stdin_writer
             .write_all(b"\n")
             .context("Failed to send newline")?;
 
@@ -106,11 +263,26 @@ impl ProgramIO for LocalIO {
             .stdout_reader
             .read(&mut temp)
             .context("Failed to read from process")?;
+        if read_size == 0 && num_bytes > 0 {
+            return Err(Eof { received: vec![] }.into());
+        }
 
         temp.resize(read_size, 0);
         Ok(temp)
     }
 
+    fn peek(&mut self, num_bytes: usize) -> Result<Vec<u8>> {
+        // fill_buf only reads from the process if the internal bufreader buffer is empty
+        let internal_buf = self
+            .stdout_reader
+            .fill_buf()
+            .context("Failed to read from process")?;
+        if internal_buf.is_empty() && num_bytes > 0 {
+            return Err(Eof { received: vec![] }.into());
+        }
+        Ok(internal_buf[..num_bytes.min(internal_buf.len())].to_vec())
+    }
+
     fn recv_until(&mut self, terminator: &[u8]) -> Result<Vec<u8>> {
         // temporary buffer
         let mut temp_data: Vec<u8> = Vec::new();
@@ -119,15 +291,18 @@ impl ProgramIO for LocalIO {
             // access the internal bufreader buffer and append that to our temporary buffer
             let internal_buf = self.stdout_reader.fill_buf()?;
             let internal_buf_len = internal_buf.len();
+            if internal_buf_len == 0 {
+                return Err(Eof {
+                    received: temp_data,
+                }
+                .into());
+            }
             let prev_internal_buf_len = temp_data.len();
             temp_data.extend(internal_buf);
 
             // if our temporary buffer already contains the iterator, consume the bytes we are about to return
             // exclude the bytes we already consumed in previous iterations
-            if let Some(pos) = temp_data
-                .windows(terminator.len())
-                .position(|x| x == terminator)
-            {
+            if let Some(pos) = find_new_terminator(&temp_data, terminator, prev_internal_buf_len) {
                 temp_data.resize(pos + terminator.len(), 0);
                 self.stdout_reader
                     .consume(pos + terminator.len() - prev_internal_buf_len);
@@ -139,14 +314,274 @@ impl ProgramIO for LocalIO {
         }
     }
 
-    fn attach_debugger(&self) -> Result<()> {
-        Command::new("gnome-terminal")
-            .args(&["--", "gdb", "-p", &self.process_handle.id().to_string()])
-            .spawn()
-            .context("Couldn't spawn debugger")?;
-        std::thread::sleep(std::time::Duration::from_millis(2000));
+    fn recv_until_timeout(&mut self, terminator: &[u8], timeout: Duration) -> Result<Vec<u8>> {
+        let deadline = Instant::now() + timeout;
+        let mut temp_data: Vec<u8> = Vec::new();
+
+        loop {
+            // only wait for the process if the internal bufreader buffer is used up, so that
+            // fill_buf does not block
+            if self.stdout_reader.buffer().is_empty() {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let mut poll_fds = [PollFd::new(self.stdout_fd, PollFlags::POLLIN)];
+                let ready = poll(&mut poll_fds, remaining.as_millis() as i32)
+                    .context("Failed to poll process output")?;
+                if ready == 0 {
+                    return Err(Timeout {
+                        received: temp_data,
+                    }
+                    .into());
+                }
+            }
+
+            let internal_buf = self
+                .stdout_reader
+                .fill_buf()
+                .context("Failed to read from process")?;
+            let internal_buf_len = internal_buf.len();
+            if internal_buf_len == 0 {
+                return Err(Eof {
+                    received: temp_data,
+                }
+                .into());
+            }
+            let prev_internal_buf_len = temp_data.len();
+            temp_data.extend(internal_buf);
+
+            // consume only the bytes up to the terminator, like recv_until
+            if let Some(pos) = find_new_terminator(&temp_data, terminator, prev_internal_buf_len) {
+                temp_data.truncate(pos + terminator.len());
+                self.stdout_reader
+                    .consume(pos + terminator.len() - prev_internal_buf_len);
+                return Ok(temp_data);
+            }
+            self.stdout_reader.consume(internal_buf_len);
+        }
+    }
+
+    fn clean(&mut self, timeout: Duration) -> Result<Vec<u8>> {
+        // start with whatever is already sitting in the internal bufreader buffer
+        let mut data = self.stdout_reader.buffer().to_vec();
+        self.stdout_reader.consume(data.len());
+
+        // keep reading as long as more data arrives within the timeout
+        loop {
+            let mut poll_fds = [PollFd::new(self.stdout_fd, PollFlags::POLLIN)];
+            let ready = poll(&mut poll_fds, timeout.as_millis() as i32)
+                .context("Failed to poll process output")?;
+            if ready == 0 {
+                break;
+            }
+
+            // the fd is readable, so this does not block (an empty buffer means EOF)
+            let internal_buf = self
+                .stdout_reader
+                .fill_buf()
+                .context("Failed to read from process")?;
+            let internal_buf_len = internal_buf.len();
+            if internal_buf_len == 0 {
+                break;
+            }
+            data.extend(internal_buf);
+            self.stdout_reader.consume(internal_buf_len);
+        }
+
+        Ok(data)
+    }
+
+    fn recv_stderr(&mut self, num_bytes: usize) -> Result<Vec<u8>> {
+        let mut stderr_buf = self
+            .stderr_buf
+            .lock()
+            .map_err(|_| anyhow!("stderr reader thread panicked"))?;
+
+        // only hand out what has already been collected, never block
+        let read_size = num_bytes.min(stderr_buf.len());
+        Ok(stderr_buf.drain(..read_size).collect())
+    }
+
+    fn close_stdin(&mut self) -> Result<()> {
+        // the pty master is shared with stdout and raw mode has no EOF character
+        if self.pty {
+            bail!("Closing the input is not supported in pty mode");
+        }
+
+        // dropping the write end of the pipe makes the child read EOF
+        self.stdin_writer = None;
         Ok(())
     }
+
+    fn attach_debugger(&self, script: Option<&str>) -> Result<()> {
+        let pid = self.process_handle.id();
+        if tracer_pid(pid)? != 0 {
+            bail!("A debugger is already attached to process {}", pid);
+        }
+        let script_path = match script {
+            Some(script) => Some(write_gdb_script(pid, script)?),
+            None => None,
+        };
+
+        match &self.debug_mode {
+            DebugMode::Terminal(terminal) => {
+                let terminal = match terminal {
+                    Some(terminal) => terminal.split_whitespace().map(str::to_string).collect(),
+                    None => find_terminal(DEFAULT_TERMINALS)?,
+                };
+                let (program, args) = terminal.split_first().context("Empty terminal command")?;
+                let mut command = Command::new(program);
+                command.args(args).args(&["gdb", "-p", &pid.to_string()]);
+                if let Some(path) = &script_path {
+                    command.arg("-x").arg(path);
+                }
+                command
+                    .spawn()
+                    .context(format!("Couldn't spawn debugger in {}", program))?;
+            }
+            &DebugMode::GdbServer(port) => {
+                // gdbserver only reports a busy port on its own output, so check it up front
+                TcpListener::bind(("0.0.0.0", port))
+                    .context(format!("Port {} is already in use", port))?;
+                Command::new("gdbserver")
+                    .args(&["--attach", &format!(":{}", port), &pid.to_string()])
+                    .spawn()
+                    .context("Couldn't spawn gdbserver")?;
+            }
+        }
+
+        // the debugger starts in the background, wait until it has actually attached
+        let start = Instant::now();
+        while tracer_pid(pid)? == 0 {
+            if start.elapsed() > DEBUGGER_ATTACH_TIMEOUT {
+                // no gdb will run the script anymore
+                if let Some(path) = &script_path {
+                    let _ = std::fs::remove_file(path);
+                }
+                bail!(
+                    "Debugger did not attach within {}s",
+                    DEBUGGER_ATTACH_TIMEOUT.as_secs()
+                );
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        // the script is run by the gdb of the user in gdbserver mode
+        if let DebugMode::GdbServer(port) = self.debug_mode {
+            match &script_path {
+                Some(path) => info!(
+                    "gdbserver is attached, connect with: gdb -ex 'target remote localhost:{}' -x {}",
+                    port,
+                    path.display()
+                ),
+                None => info!(
+                    "gdbserver is attached, connect with: target remote localhost:{}",
+                    port
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    fn set_debug_mode(&mut self, mode: DebugMode) {
+        self.debug_mode = mode;
+    }
+
+    fn exit_status(&mut self) -> Result<Option<ExitStatus>> {
+        self.process_handle
+            .try_wait()
+            .context("Failed to query the process status")
+    }
+}
+
+/// read end of a pty master, reading fails with EIO instead of returning 0 once the child closed
+/// its side, so that is turned into the usual end of file
+struct PtyMaster(File);
+
+impl Read for PtyMaster {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.read(buf) {
+            Err(e) if e.raw_os_error() == Some(nix::libc::EIO) => Ok(0),
+            result => result,
+        }
+    }
+}
+
+/// terminals that are tried in order for the debugger, each with the arguments preceding the command
+pub const DEFAULT_TERMINALS: &[&str] = &[
+    "gnome-terminal --",
+    "konsole -e",
+    "xterm -e",
+    "x-terminal-emulator -e",
+];
+
+/// how long to wait for the debugger to attach
+const DEBUGGER_ATTACH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// split the first terminal command whose program is installed into its words
+fn find_terminal(terminals: &[&str]) -> Result<Vec<String>> {
+    terminals
+        .iter()
+        .map(|terminal| {
+            terminal
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .find(|words| {
+            words
+                .first()
+                .map_or(false, |program| which::which(program).is_ok())
+        })
+        .context(format!(
+            "No terminal found to run the debugger in, tried: {}",
+            terminals.join(", ")
+        ))
+}
+
+/// how many file names write_gdb_script tries before giving up
+const GDB_SCRIPT_ATTEMPTS: u32 = 100;
+
+/// write the gdb commands for the process with pid to a new temporary file only the user can read
+/// and return its path. gdb removes the file as soon as it starts running it
+fn write_gdb_script(pid: u32, script: &str) -> Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    for attempt in 0..GDB_SCRIPT_ATTEMPTS {
+        let name = format!("bochumoxide_{}_{}_{}.gdb", pid, nanos, attempt);
+        let path = std::env::temp_dir().join(name);
+        // create_new never follows a symlink or reuses a file someone else placed there
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).context(format!("Unable to create {}", path.display())),
+        };
+
+        // gdb has the file open once it runs the first line, so it can be unlinked right away
+        let quoted = path.display().to_string().replace('\'', "'\\''");
+        write!(file, "shell rm -f '{}'\n{}\n", quoted, script)
+            .context(format!("Unable to write {}", path.display()))?;
+        return Ok(path);
+    }
+    bail!(
+        "Unable to create a gdb script in {}",
+        std::env::temp_dir().display()
+    )
+}
+
+/// pid of the process tracing pid (0 if it is not traced)
+fn tracer_pid(pid: u32) -> Result<u32> {
+    let path = format!("/proc/{}/status", pid);
+    let status = std::fs::read_to_string(&path).context(format!("Unable to read {}", path))?;
+    let tracer = status
+        .lines()
+        .find_map(|line| line.strip_prefix("TracerPid:"))
+        .context("Missing TracerPid")?;
+    tracer.trim().parse().context("Invalid TracerPid")
 }
 
 impl Drop for LocalIO {
@@ -155,9 +590,368 @@ impl Drop for LocalIO {
     }
 }
 
+/// fail early with a readable error if the program does not exist or is not executable. like the
+/// spawned process, relative paths are resolved in its working directory and the PATH of env wins
+fn check_program(file: &str, options: &LocalOptions) -> Result<()> {
+    let cwd = match &options.cwd {
+        Some(cwd) => PathBuf::from(cwd),
+        None => std::env::current_dir().context("Failed to get the working directory")?,
+    };
+    let path = options
+        .env
+        .iter()
+        .find(|(key, _)| key == "PATH")
+        .map(|(_, value)| value.into())
+        .or_else(|| std::env::var_os("PATH"));
+
+    which::which_in(file, path, cwd)
+        .map(|_| ())
+        .map_err(|e| anyhow!("Program {} not found: {}", file, e))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_find_terminal() {
+        assert_eq!(
+            find_terminal(&["no-such-terminal -e", "sh -c"]).unwrap(),
+            vec!["sh", "-c"]
+        );
+
+        let err = find_terminal(&["no-such-terminal -e", "no-such-terminal2 --"]).unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("tried: no-such-terminal -e, no-such-terminal2 --"));
+    }
+
+    #[test]
+    fn test_gdbserver_port_in_use() {
+        let mut local_io = LocalIO::new("cat", &[]).expect("Failed to create LocalIO object");
+        let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        local_io.set_debug_mode(DebugMode::GdbServer(port));
+        let err = local_io.attach_debugger(None).unwrap_err();
+        assert_eq!(err.to_string(), format!("Port {} is already in use", port));
+
+        // the mode survives a restart
+        local_io.restart().expect("restart() failed");
+        assert_eq!(local_io.debug_mode, DebugMode::GdbServer(port));
+    }
+
+    #[test]
+    fn test_write_gdb_script() {
+        let path = write_gdb_script(u32::MAX, "b *0x401136\ncontinue").unwrap();
+        let other = write_gdb_script(u32::MAX, "continue").unwrap();
+        assert_ne!(path, other);
+        std::fs::remove_file(other).unwrap();
+
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+
+        // the first line removes the script again
+        let content = std::fs::read_to_string(&path).unwrap();
+        let (remove, script) = content.split_once('\n').unwrap();
+        assert_eq!(script, "b *0x401136\ncontinue\n");
+        let status = Command::new("sh")
+            .args(&["-c", remove.strip_prefix("shell ").unwrap()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_tracer_pid() {
+        let local_io = LocalIO::new("cat", &[]).expect("Failed to create LocalIO object");
+        assert_eq!(tracer_pid(local_io.process_handle.id()).unwrap(), 0);
+        assert!(tracer_pid(u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_eof() {
+        let mut local_io =
+            LocalIO::new("printf", &["a\nb"]).expect("Failed to create LocalIO object");
+        assert_eq!(local_io.recv_line().expect("recv_line() failed"), b"a\n");
+
+        // the partial line is handed out with the eof
+        let err = local_io.recv_line().unwrap_err();
+        assert_eq!(err.downcast_ref::<Eof>().unwrap().received, b"b");
+        let err = local_io.recv(4).unwrap_err();
+        assert_eq!(err.downcast_ref::<Eof>().unwrap().received, b"");
+    }
+
+    #[test]
+    fn test_pty_eof() {
+        let mut local_io =
+            LocalIO::new_pty("printf", &["a\nb"]).expect("Failed to create LocalIO object");
+        assert_eq!(local_io.recv_line().expect("recv_line() failed"), b"a\n");
+
+        // the master reports EIO once the child is gone, which is an eof like with pipes
+        let err = local_io.recv_line().unwrap_err();
+        assert_eq!(err.downcast_ref::<Eof>().unwrap().received, b"b");
+        let err = local_io.recv(4).unwrap_err();
+        assert_eq!(err.downcast_ref::<Eof>().unwrap().received, b"");
+        assert!(crate::program_io::is_eof(&local_io.peek(4).unwrap_err()));
+    }
+
+    #[test]
+    fn test_env() {
+        let options = LocalOptions {
+            env: vec![("BOCHUMOXIDE_TEST".to_string(), "a=b".to_string())],
+            ..LocalOptions::default()
+        };
+        let mut local_io = LocalIO::with_options("env", &[], false, options).unwrap();
+        let output = local_io.clean(Duration::from_millis(500)).unwrap();
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .any(|line| line == "BOCHUMOXIDE_TEST=a=b"));
+
+        // a cleared environment only holds the given variables, also after a restart
+        let options = LocalOptions {
+            env: vec![("FOO".to_string(), "bar".to_string())],
+            clear_env: true,
+            ..LocalOptions::default()
+        };
+        let mut local_io = LocalIO::with_options("env", &[], false, options).unwrap();
+        assert_eq!(
+            local_io.clean(Duration::from_millis(500)).unwrap(),
+            b"FOO=bar\n"
+        );
+        local_io.restart().unwrap();
+        assert_eq!(
+            local_io.clean(Duration::from_millis(500)).unwrap(),
+            b"FOO=bar\n"
+        );
+    }
+
+    #[test]
+    fn test_cwd() {
+        let dir = std::env::temp_dir().join(format!("bochumoxide_cwd_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let options = LocalOptions {
+            cwd: Some(dir.to_str().unwrap().to_string()),
+            ..LocalOptions::default()
+        };
+
+        // the directory is kept across restarts
+        let mut local_io = LocalIO::with_options("pwd", &[], false, options).unwrap();
+        let expected = format!("{}\n", dir.display()).into_bytes();
+        assert_eq!(local_io.recv_line().unwrap(), expected);
+        local_io.restart().unwrap();
+        assert_eq!(local_io.recv_line().unwrap(), expected);
+        std::fs::remove_dir(&dir).unwrap();
+
+        // a missing directory fails to spawn
+        let options = LocalOptions {
+            cwd: Some(dir.to_str().unwrap().to_string()),
+            ..LocalOptions::default()
+        };
+        assert!(LocalIO::with_options("pwd", &[], false, options).is_err());
+    }
+
+    #[test]
+    fn test_check_program() {
+        assert!(check_program("cat", &LocalOptions::default()).is_ok());
+        assert!(check_program("test_data/bin64", &LocalOptions::default()).is_ok());
+
+        let error = LocalIO::new("./does_not_exist", &[]).err().unwrap();
+        assert!(format!("{:#}", error).contains("Program ./does_not_exist not found"));
+        // not executable
+        assert!(check_program("Cargo.toml", &LocalOptions::default()).is_err());
+
+        // relative paths are resolved in the working directory
+        let options = LocalOptions {
+            cwd: Some("test_data".to_string()),
+            ..LocalOptions::default()
+        };
+        assert!(check_program("./bin64", &options).is_ok());
+        assert!(check_program("./test_data/bin64", &options).is_err());
+    }
+
+    #[test]
+    fn test_find_new_terminator() {
+        assert_eq!(find_new_terminator(b"abc\ndef\n", b"\n", 0), Some(3));
+        // the first newline lies in the already searched bytes
+        assert_eq!(find_new_terminator(b"abc\ndef\n", b"\n", 4), Some(7));
+        // a terminator that started in the searched bytes
+        assert_eq!(find_new_terminator(b"abc> ", b"> ", 4), Some(3));
+        assert_eq!(find_new_terminator(b"abc", b"x", 0), None);
+    }
+
+    #[test]
+    fn test_buffer_capacity() {
+        // about 3.4 MB of numbered lines
+        let expected: Vec<u8> = (1..=500000)
+            .flat_map(|i| format!("{}\n", i).into_bytes())
+            .collect();
+        let middle = expected.len() / 2;
+
+        for &capacity in &[7, 4096, DEFAULT_BUFFER_CAPACITY, 1 << 20] {
+            let options = LocalOptions {
+                buffer_capacity: Some(capacity),
+                ..LocalOptions::default()
+            };
+            let mut local_io =
+                LocalIO::with_options("seq", &["1", "500000"], false, options).unwrap();
+
+            assert_eq!(local_io.recv_n(middle).unwrap(), &expected[..middle]);
+            // the rest minus the last line
+            let rest = local_io.recv_until(b"\n499999\n").unwrap();
+            assert_eq!(rest, &expected[middle..expected.len() - 7]);
+            assert_eq!(local_io.recv_n(7).unwrap(), b"500000\n");
+            assert!(crate::program_io::is_eof(&local_io.recv(1).unwrap_err()));
+        }
+    }
+
+    #[test]
+    fn test_parse_env() {
+        assert_eq!(
+            parse_env(" FOO=bar  LD_PRELOAD=./libc.so.6 EMPTY=").unwrap(),
+            vec![
+                ("FOO".to_string(), "bar".to_string()),
+                ("LD_PRELOAD".to_string(), "./libc.so.6".to_string()),
+                ("EMPTY".to_string(), "".to_string()),
+            ]
+        );
+        assert!(parse_env("").unwrap().is_empty());
+        assert!(parse_env("FOO").is_err());
+        assert!(parse_env("=bar").is_err());
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut local_io =
+            LocalIO::new("printf", &["abc"]).expect("Failed to create LocalIO object");
+        assert_eq!(local_io.peek(2).expect("peek() failed"), b"ab");
+
+        // fewer bytes than requested are buffered
+        assert_eq!(local_io.peek(10).expect("peek() failed"), b"abc");
+        assert_eq!(local_io.recv(1).expect("recv() failed"), b"a");
+        assert_eq!(local_io.peek(10).expect("peek() failed"), b"bc");
+        assert_eq!(local_io.recv(10).expect("recv() failed"), b"bc");
+        assert!(crate::program_io::is_eof(&local_io.peek(1).unwrap_err()));
+    }
+
+    #[test]
+    fn test_recv_until_timeout() {
+        let mut local_io =
+            LocalIO::new("sh", &["-c", "printf 'AB'; sleep 1; printf 'CD'"]).unwrap();
+
+        // the terminator is absent, the partial data is handed out with the timeout
+        let start = Instant::now();
+        let err = local_io
+            .recv_until_timeout(b"X", Duration::from_millis(200))
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<Timeout>().unwrap().received, b"AB");
+        assert!(start.elapsed() < Duration::from_millis(900));
+
+        assert_eq!(
+            local_io
+                .recv_until_timeout(b"C", Duration::from_secs(5))
+                .unwrap(),
+            b"C"
+        );
+        assert_eq!(local_io.recv(1).unwrap(), b"D");
+    }
+
+    #[test]
+    fn test_recv_n() {
+        let mut local_io =
+            LocalIO::new("sh", &["-c", "printf 'AB'; sleep 0.2; printf 'CDE'"]).unwrap();
+
+        // the bytes arrive in two parts
+        assert_eq!(local_io.recv_n(3).unwrap(), b"ABC");
+        assert_eq!(local_io.recv_n(0).unwrap(), b"");
+
+        let err = local_io.recv_n(4).unwrap_err();
+        assert_eq!(err.downcast_ref::<Eof>().unwrap().received, b"DE");
+    }
+
+    #[test]
+    fn test_recv_until_any() {
+        let mut local_io = LocalIO::new(
+            "sh",
+            &["-c", "printf 'Menu\\n> '; sleep 0.2; printf 'Wrong!\\nBye'"],
+        )
+        .unwrap();
+        let terminators: &[&[u8]] = &[b"> ", b"Wrong!", b"Bye"];
+
+        assert_eq!(
+            local_io.recv_until_any(terminators).unwrap(),
+            (b"Menu\n> ".to_vec(), 0)
+        );
+        // only the data up to the first terminator is consumed
+        assert_eq!(
+            local_io.recv_until_any(terminators).unwrap(),
+            (b"Wrong!".to_vec(), 1)
+        );
+        assert_eq!(
+            local_io.recv_until_any(terminators).unwrap(),
+            (b"\nBye".to_vec(), 2)
+        );
+
+        let err = local_io.recv_until_any(terminators).unwrap_err();
+        assert!(crate::program_io::is_eof(&err));
+        assert!(local_io.recv_until_any(&[b"a", b""]).is_err());
+    }
+
+    #[test]
+    fn test_recv_line_stripped() {
+        let mut local_io =
+            LocalIO::new("printf", &["a\nb\r\n\nc"]).expect("Failed to create LocalIO object");
+        assert_eq!(local_io.recv_line_stripped().unwrap(), b"a");
+        assert_eq!(local_io.recv_line_stripped().unwrap(), b"b");
+        assert_eq!(local_io.recv_line_stripped().unwrap(), b"");
+
+        // the last line has no newline, after it only the eof is left
+        assert_eq!(local_io.recv_line_stripped().unwrap(), b"c");
+        let err = local_io.recv_line_stripped().unwrap_err();
+        assert!(crate::program_io::is_eof(&err));
+    }
+
+    #[test]
+    fn test_exit_status() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let wait_for_exit = |local_io: &mut LocalIO| loop {
+            if let Some(status) = local_io.exit_status().unwrap() {
+                return status;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+
+        let mut local_io = LocalIO::new("sh", &["-c", "exit 3"]).unwrap();
+        assert_eq!(wait_for_exit(&mut local_io).code(), Some(3));
+
+        let mut local_io = LocalIO::new("sh", &["-c", "kill -SEGV $$"]).unwrap();
+        assert_eq!(wait_for_exit(&mut local_io).signal(), Some(11));
+
+        let mut local_io = LocalIO::new("cat", &[]).unwrap();
+        assert_eq!(local_io.exit_status().unwrap(), None);
+    }
+
+    #[test]
+    fn test_close_stdin() {
+        // sort only prints once it has read everything
+        let mut local_io = LocalIO::new("sort", &[]).expect("Failed to create LocalIO object");
+        local_io.send(b"b\na\n").expect("send() failed");
+        local_io.close_stdin().expect("close_stdin() failed");
+
+        assert_eq!(local_io.recv_line().expect("recv_line() failed"), b"a\n");
+        assert_eq!(local_io.recv_line().expect("recv_line() failed"), b"b\n");
+        assert!(local_io.send(b"c\n").is_err());
+        assert!(local_io.send_line(b"c").is_err());
+
+        // a restart brings back the input
+        local_io.restart().expect("restart() failed");
+        local_io.send(b"c\n").expect("send() failed");
+    }
 
     #[test]
     fn test_send_recv() {
@@ -263,4 +1057,113 @@ mod tests {
             b"Test_Str_456?"
         );
     }
+
+    #[test]
+    fn test_recv_stderr() {
+        let mut local_io = LocalIO::new("sh", &["-c", "echo Test_Err_123 >&2; cat"])
+            .expect("Failed to create LocalIO object");
+
+        // stderr is collected in the background, so poll until the data shows up
+        let mut stderr = Vec::new();
+        for _ in 0..100 {
+            stderr.extend(local_io.recv_stderr(100).expect("recv_stderr() failed"));
+            if stderr.ends_with(b"\n") {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(stderr, b"Test_Err_123\n");
+
+        // stdout is unaffected by stderr output
+        local_io
+            .send_line(b"Test_Str_123?")
+            .expect("send_line() failed");
+        assert_eq!(
+            local_io.recv_line().expect("recv_line() failed"),
+            b"Test_Str_123?\n"
+        );
+    }
+
+    #[test]
+    fn test_recv_stderr_exited() {
+        let mut local_io = LocalIO::new("true", &[]).expect("Failed to create LocalIO object");
+
+        // wait for the child to exit, reading stderr must not hang afterwards
+        local_io.process_handle.wait().expect("wait() failed");
+        assert_eq!(
+            local_io.recv_stderr(100).expect("recv_stderr() failed"),
+            b""
+        );
+    }
+
+    #[test]
+    fn test_pty_isatty() {
+        // without a pty, stdin and stdout are pipes
+        let mut local_io = LocalIO::new(
+            "sh",
+            &["-c", "test -t 0 && test -t 1 && echo tty || echo notty"],
+        )
+        .expect("Failed to create LocalIO object");
+        assert_eq!(
+            local_io.recv_line().expect("recv_line() failed"),
+            b"notty\n"
+        );
+
+        // with a pty, the child believes that it talks to a terminal
+        let mut local_io = LocalIO::new_pty(
+            "sh",
+            &["-c", "test -t 0 && test -t 1 && echo tty || echo notty"],
+        )
+        .expect("Failed to create LocalIO object");
+        assert_eq!(local_io.recv_line().expect("recv_line() failed"), b"tty\n");
+    }
+
+    #[test]
+    fn test_pty_sendline_recvline() {
+        let mut local_io = LocalIO::new_pty("cat", &[]).expect("Failed to create LocalIO object");
+
+        // raw mode: the input is neither echoed back nor are newlines translated
+        local_io
+            .send_line(b"Test_Str_123?")
+            .expect("send_line() failed");
+        assert_eq!(
+            local_io.recv_line().expect("recv_line() failed"),
+            b"Test_Str_123?\n"
+        );
+    }
+
+    #[test]
+    fn test_clean() {
+        let mut local_io = LocalIO::new("cat", &[]).expect("Failed to create LocalIO object");
+
+        // cleaning without pending data returns nothing
+        assert_eq!(
+            local_io
+                .clean(Duration::from_millis(50))
+                .expect("clean() failed"),
+            b""
+        );
+
+        // data that is partially buffered after recv_line is drained as well
+        local_io
+            .send(b"Test_Str_123?\nTest_Str_456?")
+            .expect("send() failed");
+        assert_eq!(
+            local_io.recv_line().expect("recv_line() failed"),
+            b"Test_Str_123?\n"
+        );
+        assert_eq!(
+            local_io
+                .clean(Duration::from_millis(50))
+                .expect("clean() failed"),
+            b"Test_Str_456?"
+        );
+
+        // the stream is in sync again afterwards
+        local_io.send(b"Test_Str_789?").expect("send() failed");
+        assert_eq!(
+            local_io.recv_until(b"?").expect("recv_until() failed"),
+            b"Test_Str_789?"
+        );
+    }
 }
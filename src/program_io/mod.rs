@@ -1,12 +1,91 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use nix::sys::signal::Signal;
+use std::convert::TryFrom;
+use std::fmt;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::Duration;
 
 mod local_io;
 mod network_io;
+#[cfg(feature = "ssh2")]
+mod ssh_io;
 
 // make sure that LocalIO can be imported using crate::program_io::LocalIO
 // otherwise we would need to import it using the "full path" to the type
-pub use local_io::LocalIO;
-pub use network_io::NetworkIO;
+pub use local_io::{parse_env, DebugMode, LocalIO, LocalOptions, DEFAULT_GDBSERVER_PORT};
+pub use network_io::{NetworkIO, DEFAULT_CONNECT_ATTEMPTS, DEFAULT_CONNECT_DELAY};
+#[cfg(feature = "ssh2")]
+pub use ssh_io::{SshAuth, SshIO, SshParams};
+
+/// error of reads that reached the end of the program output, the bytes received before it are kept
+#[derive(Debug)]
+pub struct Eof {
+    pub received: Vec<u8>,
+}
+
+impl fmt::Display for Eof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Program closed its output (EOF) after {} bytes",
+            self.received.len()
+        )
+    }
+}
+
+impl std::error::Error for Eof {}
+
+/// check whether an error (or its cause) is an EOF of the program output
+pub fn is_eof(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Eof>().is_some()
+}
+
+/// error of reads that timed out before they were complete, the bytes received before it are kept
+#[derive(Debug)]
+pub struct Timeout {
+    pub received: Vec<u8>,
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Timed out after receiving {} bytes", self.received.len())
+    }
+}
+
+impl std::error::Error for Timeout {}
+
+/// drop a trailing \n or \r\n from a line
+pub fn strip_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// end of the first occurrence of any terminator in data and the index of that terminator. if
+/// several terminators end at the same position, the first one in the list wins
+pub fn find_terminator(data: &[u8], terminators: &[&[u8]]) -> Option<(usize, usize)> {
+    (1..=data.len()).find_map(|end| {
+        let index = terminators.iter().position(|t| data[..end].ends_with(t))?;
+        Some((end, index))
+    })
+}
+
+/// human readable exit status such as "exited with code 0" or "killed by signal 11 (SIGSEGV)"
+pub fn describe_exit_status(status: ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited with code {}", code),
+        (None, Some(signal)) => {
+            let name = Signal::try_from(signal).map_or("unknown", |s| s.as_str());
+            let core = if status.core_dumped() {
+                ", core dumped"
+            } else {
+                ""
+            };
+            format!("killed by signal {} ({}{})", signal, name, core)
+        }
+        (None, None) => format!("{}", status),
+    }
+}
 
 /// trait that must be implemented for all kind of I/O
 pub trait ProgramIO {
@@ -15,18 +94,133 @@ pub trait ProgramIO {
     /// send bytes and additional newline to the stream
     fn send_line(&mut self, data: &[u8]) -> Result<()>;
 
-    /// receive up to num_bytes of data and return as soon as any data is read
+    /// receive up to num_bytes of data and return as soon as any data is read, fails with Eof if
+    /// the program closed its output
     fn recv(&mut self, num_bytes: usize) -> Result<Vec<u8>>;
-    /// receive until terminator is read
+    /// receive exactly num_bytes of data, fails with Eof (holding the partial data) if the program
+    /// closed its output before
+    fn recv_n(&mut self, num_bytes: usize) -> Result<Vec<u8>> {
+        let mut received = Vec::with_capacity(num_bytes);
+        while received.len() < num_bytes {
+            match self.recv(num_bytes - received.len()) {
+                Ok(data) => received.extend(data),
+                Err(e) if is_eof(&e) => return Err(Eof { received }.into()),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(received)
+    }
+    /// return up to num_bytes of data without consuming it, a later recv sees the same bytes. waits
+    /// like recv until any data is available, but returns fewer bytes if less is buffered
+    fn peek(&mut self, _num_bytes: usize) -> Result<Vec<u8>> {
+        bail!("Peeking is not supported")
+    }
+    /// receive until terminator is read, fails with Eof (holding the partial data) if the program
+    /// closed its output before
     fn recv_until(&mut self, terminator: &[u8]) -> Result<Vec<u8>>;
+    /// receive until terminator is read or timeout elapsed, fails with Timeout (holding the
+    /// partial data) in the latter case
+    fn recv_until_timeout(&mut self, terminator: &[u8], timeout: Duration) -> Result<Vec<u8>>;
+    /// receive until any of the terminators is read, returns the data and the index of the
+    /// terminator that came first. only the bytes up to it are consumed by peeking ahead, fails with
+    /// Eof (holding the partial data) if the program closed its output before
+    fn recv_until_any(&mut self, terminators: &[&[u8]]) -> Result<(Vec<u8>, usize)> {
+        if terminators.is_empty() || terminators.iter().any(|t| t.is_empty()) {
+            bail!("Empty terminator");
+        }
+        let longest = terminators.iter().map(|t| t.len()).max().unwrap_or(0);
+
+        let mut received: Vec<u8> = Vec::new();
+        loop {
+            let peeked = match self.peek(4096) {
+                Ok(peeked) => peeked,
+                Err(e) if is_eof(&e) => return Err(Eof { received }.into()),
+                Err(e) => return Err(e),
+            };
+
+            // a terminator may have started in the data of earlier iterations
+            let start = received.len().saturating_sub(longest - 1);
+            let mut window = received[start..].to_vec();
+            window.extend_from_slice(&peeked);
+            match find_terminator(&window, terminators) {
+                Some((end, index)) => {
+                    let consumed = self.recv(end + start - received.len())?;
+                    received.extend(consumed);
+                    return Ok((received, index));
+                }
+                None => {
+                    let consumed = self.recv(peeked.len())?;
+                    received.extend(consumed);
+                }
+            }
+        }
+    }
     /// receive until newline is found
     fn recv_line(&mut self) -> Result<Vec<u8>> {
         self.recv_until(b"\n")
     }
-    /// attach a debugger to the process (only works for localio)
-    fn attach_debugger(&self) -> Result<()>;
-
-    fn restart(&mut self) -> Result<()> {
+    /// receive a line without its trailing \n or \r\n. a last line without newline is returned
+    /// once the program closed its output
+    fn recv_line_stripped(&mut self) -> Result<Vec<u8>> {
+        match self.recv_line() {
+            Ok(line) => Ok(strip_line_ending(&line).to_vec()),
+            Err(e) => match e.downcast::<Eof>() {
+                Ok(eof) if !eof.received.is_empty() => Ok(eof.received),
+                Ok(eof) => Err(eof.into()),
+                Err(e) => Err(e),
+            },
+        }
+    }
+    /// receive until delimiter is read, then send data. returns the received bytes
+    fn send_after(&mut self, delimiter: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let received = self.recv_until(delimiter)?;
+        self.send(data)?;
+        Ok(received)
+    }
+    /// receive until delimiter is read, then send data with an additional newline
+    fn send_line_after(&mut self, delimiter: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let received = self.recv_until(delimiter)?;
+        self.send_line(data)?;
+        Ok(received)
+    }
+    /// drain all data that arrives until nothing new shows up within timeout
+    fn clean(&mut self, timeout: Duration) -> Result<Vec<u8>>;
+    /// receive up to num_bytes of data from stderr without blocking (empty if nothing is available)
+    fn recv_stderr(&mut self, _num_bytes: usize) -> Result<Vec<u8>> {
+        bail!("no stderr")
+    }
+    /// signal EOF to the program while its output can still be read, later sends fail
+    fn close_stdin(&mut self) -> Result<()> {
         Ok(())
     }
+    /// attach a debugger to the process (only works for localio), script holds gdb commands that
+    /// are run once it is attached
+    fn attach_debugger(&self, script: Option<&str>) -> Result<()>;
+    /// choose how attach_debugger makes the process available (only used by localio)
+    fn set_debug_mode(&mut self, _mode: DebugMode) {}
+    /// exit status of the program once it has terminated (only known for localio)
+    fn exit_status(&mut self) -> Result<Option<ExitStatus>> {
+        Ok(None)
+    }
+    /// run the program again from the start (a new connection for network targets), data of the
+    /// previous run that was not received yet is dropped
+    fn restart(&mut self) -> Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_terminator() {
+        let terminators: &[&[u8]] = &[b"> ", b"Bye", b"!"];
+        assert_eq!(find_terminator(b"Menu\n> ", terminators), Some((7, 0)));
+        assert_eq!(find_terminator(b"Bye!\n> ", terminators), Some((3, 1)));
+        assert_eq!(find_terminator(b"Wrong!", terminators), Some((6, 2)));
+        assert_eq!(find_terminator(b"abc", terminators), None);
+        assert_eq!(find_terminator(b"", terminators), None);
+
+        // terminators ending at the same position
+        assert_eq!(find_terminator(b"Bye", &[b"ye", b"Bye"]), Some((3, 0)));
+    }
 }
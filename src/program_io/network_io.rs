@@ -1,21 +1,63 @@
 use anyhow::{bail, Context, Result};
 
-use std::io::{Read, Write};
+use std::io::{ErrorKind, Read, Write};
 use std::net::{Shutdown, TcpStream};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use super::ProgramIO;
+use super::{Eof, ProgramIO, Timeout};
+use log::*;
 
 pub struct NetworkIO {
     stream: TcpStream,
+    /// data that has been read from the stream but not yet returned
+    buffer: Vec<u8>,
+    /// timeout for a single read from the stream
+    read_timeout: Duration,
+    /// the write half of the stream has been shut down
+    input_closed: bool,
+    /// address and connection attempts, used again to reconnect on a restart
+    connection: String,
+    connect_attempts: u32,
+    connect_delay: Duration,
 }
 
+/// connection attempts used for network targets, services often come up a bit after (re)starting
+pub const DEFAULT_CONNECT_ATTEMPTS: u32 = 3;
+pub const DEFAULT_CONNECT_DELAY: Duration = Duration::from_millis(500);
+
 impl NetworkIO {
     /// connection must be of form ip:port
     pub fn new(connection: &str) -> Result<Self> {
+        Self::connect_with_retries(connection, 1, Duration::default())
+    }
+
+    /// try to connect up to attempts times, waiting delay between the attempts
+    pub fn connect_with_retries(connection: &str, attempts: u32, delay: Duration) -> Result<Self> {
+        if attempts == 0 {
+            bail!("At least one connection attempt is required");
+        }
+
         // create a TCP stream with the given connection parameter
-        let stream = TcpStream::connect(connection)
-            .context(format!("Failed to open connection to {}", connection))?;
+        let mut attempt = 1;
+        let stream = loop {
+            match TcpStream::connect(connection) {
+                Ok(stream) => break stream,
+                Err(e) if attempt < attempts => {
+                    debug!(
+                        "Connection attempt {} to {} failed: {}",
+                        attempt, connection, e
+                    );
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(e).context(format!(
+                        "Failed to open connection to {} after {} attempt(s)",
+                        connection, attempts
+                    ))
+                }
+            }
+        };
 
         stream
             .set_read_timeout(Some(Duration::new(5, 0)))
@@ -24,12 +66,48 @@ impl NetworkIO {
             .set_write_timeout(Some(Duration::new(5, 0)))
             .expect("failed to set read timeout for TCP connection");
 
-        Ok(NetworkIO { stream })
+        Ok(NetworkIO {
+            stream,
+            buffer: Vec::new(),
+            read_timeout: Duration::new(5, 0),
+            input_closed: false,
+            connection: connection.to_string(),
+            connect_attempts: attempts,
+            connect_delay: delay,
+        })
+    }
+
+    /// change how long a single read may block before it fails
+    pub fn set_read_timeout(&mut self, timeout: Duration) -> Result<()> {
+        self.stream
+            .set_read_timeout(Some(timeout))
+            .context("Failed to set read timeout for TCP connection")?;
+        self.read_timeout = timeout;
+        Ok(())
+    }
+
+    fn check_input_open(&self) -> Result<()> {
+        if self.input_closed {
+            bail!("Input of the connection has been closed");
+        }
+        Ok(())
     }
 }
 
 impl ProgramIO for NetworkIO {
+    fn restart(&mut self) -> Result<()> {
+        // the old connection is closed when it is dropped
+        let read_timeout = self.read_timeout;
+        *self = Self::connect_with_retries(
+            &self.connection,
+            self.connect_attempts,
+            self.connect_delay,
+        )?;
+        self.set_read_timeout(read_timeout)
+    }
+
     fn send(&mut self, data: &[u8]) -> Result<()> {
+        self.check_input_open()?;
         self.stream
             .write_all(data.as_ref())
             .context("Failed to send to process")?;
@@ -38,6 +116,7 @@ impl ProgramIO for NetworkIO {
     }
 
     fn send_line(&mut self, data: &[u8]) -> Result<()> {
+        self.check_input_open()?;
         let data = data.as_ref();
         self.stream
             .write_all(data)
@@ -50,6 +129,12 @@ impl ProgramIO for NetworkIO {
     }
 
     fn recv(&mut self, num_bytes: usize) -> Result<Vec<u8>> {
+        // hand out buffered data first
+        if !self.buffer.is_empty() {
+            let read_size = num_bytes.min(self.buffer.len());
+            return Ok(self.buffer.drain(..read_size).collect());
+        }
+
         // create a new vector that holds up to num_bytes
         let mut x = Vec::new();
         x.resize(num_bytes, 0);
@@ -59,42 +144,160 @@ impl ProgramIO for NetworkIO {
             .stream
             .read(&mut x)
             .context("Failed to read from process")?;
+        if read_size == 0 && num_bytes > 0 {
+            return Err(Eof { received: vec![] }.into());
+        }
         // cut of unwritten bytes
         x.resize(read_size, 0);
 
         Ok(x)
     }
 
+    fn peek(&mut self, num_bytes: usize) -> Result<Vec<u8>> {
+        // buffered data comes first, so only look at the stream if there is none
+        if !self.buffer.is_empty() {
+            let read_size = num_bytes.min(self.buffer.len());
+            return Ok(self.buffer[..read_size].to_vec());
+        }
+
+        let mut x = vec![0; num_bytes];
+        let read_size = self
+            .stream
+            .peek(&mut x)
+            .context("Failed to read from process")?;
+        if read_size == 0 && num_bytes > 0 {
+            return Err(Eof { received: vec![] }.into());
+        }
+        x.truncate(read_size);
+        Ok(x)
+    }
+
     fn recv_until(&mut self, terminator: &[u8]) -> Result<Vec<u8>> {
         // temporary buffer
-        let mut temp: Vec<u8> = Vec::new();
-        temp.resize(4096, 0);
+        let mut temp = [0u8; 4096];
 
         loop {
-            // read data from the stream without removing it
-            let read_size = self.stream.peek(&mut temp)?;
-
-            // if peeked data contains terminator, read bytes from the stream up to and including the terminator
-            if let Some(pos) = temp[0..read_size]
+            // if the buffered data contains the terminator, return everything up to and including it
+            if let Some(pos) = self
+                .buffer
                 .windows(terminator.len())
                 .position(|x| x == terminator)
             {
-                return self.recv(pos + terminator.len());
+                return Ok(self.buffer.drain(..pos + terminator.len()).collect());
+            }
+
+            // otherwise block until more data arrives, this fails once the read timeout elapses
+            // the data received so far stays buffered in that case
+            let read_size = self
+                .stream
+                .read(&mut temp)
+                .context("Failed to read from process")?;
+            if read_size == 0 {
+                return Err(Eof {
+                    received: self.buffer.drain(..).collect(),
+                }
+                .into());
             }
+            self.buffer.extend_from_slice(&temp[..read_size]);
         }
     }
 
-    fn attach_debugger(&self) -> Result<()> {
+    fn recv_until_timeout(&mut self, terminator: &[u8], timeout: Duration) -> Result<Vec<u8>> {
+        let deadline = Instant::now() + timeout;
+        let mut temp = [0u8; 4096];
+
+        let result = loop {
+            if let Some(pos) = self
+                .buffer
+                .windows(terminator.len())
+                .position(|x| x == terminator)
+            {
+                break Ok(self.buffer.drain(..pos + terminator.len()).collect());
+            }
+
+            // a zero duration is not a valid socket timeout
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::default() {
+                break Err(Timeout {
+                    received: self.buffer.drain(..).collect(),
+                }
+                .into());
+            }
+            if let Err(e) = self.stream.set_read_timeout(Some(remaining)) {
+                break Err(e).context("Failed to set read timeout for TCP connection");
+            }
+
+            match self.stream.read(&mut temp) {
+                Ok(0) => {
+                    break Err(Eof {
+                        received: self.buffer.drain(..).collect(),
+                    }
+                    .into())
+                }
+                Ok(read_size) => self.buffer.extend_from_slice(&temp[..read_size]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                    break Err(Timeout {
+                        received: self.buffer.drain(..).collect(),
+                    }
+                    .into())
+                }
+                Err(e) => break Err(e).context("Failed to read from process"),
+            }
+        };
+
+        self.stream
+            .set_read_timeout(Some(self.read_timeout))
+            .context("Failed to set read timeout for TCP connection")?;
+        result
+    }
+
+    fn clean(&mut self, timeout: Duration) -> Result<Vec<u8>> {
+        // start with the buffered data
+        let mut data: Vec<u8> = self.buffer.drain(..).collect();
+        let mut temp = [0u8; 4096];
+
+        // a zero duration is not a valid socket timeout
+        self.stream
+            .set_read_timeout(Some(timeout.max(Duration::from_millis(1))))
+            .context("Failed to set read timeout for TCP connection")?;
+
+        // read until the timeout elapses without any new data (or the peer closed the connection)
+        let result = loop {
+            match self.stream.read(&mut temp) {
+                Ok(0) => break Ok(()),
+                Ok(read_size) => data.extend_from_slice(&temp[..read_size]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                    break Ok(())
+                }
+                Err(e) => break Err(e),
+            }
+        };
+
+        self.stream
+            .set_read_timeout(Some(self.read_timeout))
+            .context("Failed to set read timeout for TCP connection")?;
+        result.context("Failed to read from process")?;
+
+        Ok(data)
+    }
+
+    fn close_stdin(&mut self) -> Result<()> {
+        self.stream
+            .shutdown(Shutdown::Write)
+            .context("Failed to shutdown TCP stream")?;
+        self.input_closed = true;
+        Ok(())
+    }
+
+    fn attach_debugger(&self, _script: Option<&str>) -> Result<()> {
         bail!("Not implemented")
     }
 }
 
 impl Drop for NetworkIO {
     fn drop(&mut self) {
-        // close connection on drop
-        self.stream
-            .shutdown(Shutdown::Both)
-            .expect("Failed to shutdown TCP stream");
+        // close connection on drop, errors are ignored since the peer might already be gone
+        let _ = self.stream.shutdown(Shutdown::Both);
     }
 }
 
@@ -110,6 +313,8 @@ mod tests {
         let mut data = [0 as u8; 50];
 
         while match stream.read(&mut data) {
+            // the client shut down its write half
+            Ok(0) => false,
             Ok(size) => {
                 // send back the received data
                 stream.write_all(&data[0..size]).unwrap();
@@ -142,6 +347,38 @@ mod tests {
         local_addr
     }
 
+    #[test]
+    fn test_connect_with_retries() {
+        // find a free port, nothing listens on it until the server thread starts
+        let local_addr = TcpListener::bind("127.0.0.1:0")
+            .expect("Failed to set up listener")
+            .local_addr()
+            .expect("Failed to unwrap local address");
+
+        assert!(NetworkIO::connect_with_retries(
+            &local_addr.to_string(),
+            2,
+            Duration::from_millis(10)
+        )
+        .is_err());
+        assert!(
+            NetworkIO::connect_with_retries(&local_addr.to_string(), 0, Duration::default())
+                .is_err()
+        );
+
+        // the service comes up while we are still retrying
+        let server = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            let listener = TcpListener::bind(local_addr).expect("Failed to set up listener");
+            listener
+                .accept()
+                .expect("Failed to accept incoming connection");
+        });
+        NetworkIO::connect_with_retries(&local_addr.to_string(), 50, Duration::from_millis(50))
+            .expect("Failed to connect while retrying");
+        server.join().unwrap();
+    }
+
     #[test]
     fn test_send_recv() {
         // spawn the tcp echo server
@@ -188,6 +425,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_send_after() {
+        // spawn the tcp echo server
+        let local_addr = setup_server();
+
+        let mut network_io =
+            NetworkIO::new(&local_addr.to_string()).expect("Failed to create NetworkIO object");
+        network_io.send(b"name: ").expect("send() failed");
+
+        // the prompt is consumed before the payload is sent
+        assert_eq!(
+            network_io
+                .send_after(b": ", b"AAAA")
+                .expect("send_after() failed"),
+            b"name: "
+        );
+        assert_eq!(
+            network_io.recv_until(b"AAAA").expect("recv_until() failed"),
+            b"AAAA"
+        );
+
+        network_io.send(b"age: ").expect("send() failed");
+        assert_eq!(
+            network_io
+                .send_line_after(b": ", b"42")
+                .expect("send_line_after() failed"),
+            b"age: "
+        );
+        assert_eq!(network_io.recv_line().expect("recv_line() failed"), b"42\n");
+    }
+
+    #[test]
+    fn test_close_stdin() {
+        // spawn the tcp echo server
+        let local_addr = setup_server();
+
+        let mut network_io =
+            NetworkIO::new(&local_addr.to_string()).expect("Failed to create NetworkIO object");
+        network_io.send(b"AAAAB").expect("send() failed");
+        network_io.close_stdin().expect("close_stdin() failed");
+
+        // data sent before closing is still echoed back
+        assert_eq!(
+            network_io.recv_until(b"AAAA").expect("recv_until() failed"),
+            b"AAAA"
+        );
+        assert!(network_io.send(b"BBBB").is_err());
+        assert!(network_io.send_line(b"BBBB").is_err());
+
+        // the server closes the connection once our input is closed
+        let err = network_io.recv_line().unwrap_err();
+        assert_eq!(err.downcast_ref::<Eof>().unwrap().received, b"B");
+        let err = network_io.recv(4).unwrap_err();
+        assert_eq!(err.downcast_ref::<Eof>().unwrap().received, b"");
+    }
+
+    #[test]
+    fn test_restart() {
+        // echo server that accepts a second connection for the restart
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to set up listener");
+        let local_addr = listener
+            .local_addr()
+            .expect("Failed to unwrap local address");
+        thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let stream = stream.expect("Failed to accept incoming connection");
+                thread::spawn(move || echo_server(stream));
+            }
+        });
+
+        let mut network_io =
+            NetworkIO::new(&local_addr.to_string()).expect("Failed to create NetworkIO object");
+        network_io
+            .set_read_timeout(Duration::from_millis(800))
+            .expect("set_read_timeout() failed");
+        network_io.send(b"AAAA").expect("send() failed");
+        network_io.close_stdin().expect("close_stdin() failed");
+
+        // the new connection can be written again and drops the unread data of the old one
+        network_io.restart().expect("restart() failed");
+        network_io.send(b"BBBB").expect("send() failed");
+        assert_eq!(network_io.recv(4).expect("recv() failed"), b"BBBB");
+        assert_eq!(network_io.read_timeout, Duration::from_millis(800));
+
+        // the server is gone now, so the next restart fails
+        assert!(network_io.restart().is_err());
+    }
+
     #[test]
     fn test_sendline_recvline() {
         // spawn the tcp echo server
@@ -234,6 +559,120 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_clean() {
+        // spawn the tcp echo server
+        let local_addr = setup_server();
+
+        // open connection to the server
+        let mut network_io =
+            NetworkIO::new(&local_addr.to_string()).expect("Failed to create NetworkIO object");
+
+        // nothing has been sent yet, so cleaning returns nothing
+        assert_eq!(
+            network_io
+                .clean(Duration::from_millis(50))
+                .expect("clean() failed"),
+            b""
+        );
+
+        // everything echoed back is drained
+        network_io.send(b"test_data\n").expect("send() failed");
+        assert_eq!(
+            network_io
+                .clean(Duration::from_millis(200))
+                .expect("clean() failed"),
+            b"test_data\n"
+        );
+
+        // the stream is in sync again afterwards
+        network_io.send(b"ABC").expect("send() failed");
+        assert_eq!(
+            network_io.recv_until(b"C").expect("recv_until() failed"),
+            b"ABC"
+        );
+    }
+
+    #[test]
+    fn test_send_recvuntil_timeout() {
+        // spawn the tcp echo server
+        let local_addr = setup_server();
+
+        // open connection to the server with a short read timeout
+        let mut network_io =
+            NetworkIO::new(&local_addr.to_string()).expect("Failed to create NetworkIO object");
+        network_io
+            .set_read_timeout(Duration::from_millis(100))
+            .expect("set_read_timeout() failed");
+        network_io.send(b"ABCD").expect("send() failed");
+
+        // the terminator never arrives, so the call must fail instead of hanging
+        let start = std::time::Instant::now();
+        assert!(network_io.recv_until(b"X").is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // the data received before the timeout is not lost
+        assert_eq!(
+            network_io.recv_until(b"D").expect("recv_until() failed"),
+            b"ABCD"
+        );
+    }
+
+    #[test]
+    fn test_peek() {
+        // spawn the tcp echo server
+        let local_addr = setup_server();
+
+        let mut network_io =
+            NetworkIO::new(&local_addr.to_string()).expect("Failed to create NetworkIO object");
+        network_io.send(b"ABCD").expect("send() failed");
+        assert_eq!(
+            network_io.recv_until(b"B").expect("recv_until() failed"),
+            b"AB"
+        );
+
+        // buffered data is peeked without consuming it
+        assert_eq!(network_io.peek(1).expect("peek() failed"), b"C");
+        assert_eq!(network_io.peek(10).expect("peek() failed"), b"CD");
+        assert_eq!(network_io.recv(10).expect("recv() failed"), b"CD");
+
+        // and so is data still waiting in the stream
+        network_io.send(b"EF").expect("send() failed");
+        assert_eq!(network_io.peek(1).expect("peek() failed"), b"E");
+        assert_eq!(
+            network_io.recv_until(b"F").expect("recv_until() failed"),
+            b"EF"
+        );
+    }
+
+    #[test]
+    fn test_recv_until_timeout() {
+        // spawn the tcp echo server
+        let local_addr = setup_server();
+
+        let mut network_io =
+            NetworkIO::new(&local_addr.to_string()).expect("Failed to create NetworkIO object");
+        network_io.send(b"ABCD").expect("send() failed");
+
+        // the terminator never arrives, the partial data is handed out with the timeout
+        let start = std::time::Instant::now();
+        let err = network_io
+            .recv_until_timeout(b"X", Duration::from_millis(100))
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<Timeout>().unwrap().received, b"ABCD");
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // the regular read timeout is restored
+        assert_eq!(network_io.read_timeout, Duration::new(5, 0));
+        network_io.send(b"EF").expect("send() failed");
+        assert_eq!(
+            network_io
+                .recv_until_timeout(b"E", Duration::from_secs(5))
+                .unwrap(),
+            b"E"
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_send_recvuntil_empty_terminator() {
@@ -0,0 +1,421 @@
+use anyhow::{bail, Context, Result};
+use ssh2::{Channel, Session};
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::{Eof, ProgramIO, Timeout};
+
+/// port of hosts given without one
+const DEFAULT_SSH_PORT: u16 = 22;
+
+/// how the ssh user authenticates
+#[derive(Clone, Debug, PartialEq)]
+pub enum SshAuth {
+    Password(String),
+    /// path to a private key file
+    KeyFile(String),
+}
+
+impl SshAuth {
+    /// the secret is the path to a private key if use_key is set, otherwise the password
+    pub fn new(secret: &str, use_key: bool) -> Result<Self> {
+        if !use_key {
+            return Ok(SshAuth::Password(secret.to_owned()));
+        }
+        let path = secret.trim();
+        if path.is_empty() {
+            bail!("Missing private key file");
+        }
+        Ok(SshAuth::KeyFile(path.to_owned()))
+    }
+}
+
+/// parameters required to open an ssh session
+#[derive(Clone, Debug, PartialEq)]
+pub struct SshParams {
+    /// host of form ip:port
+    pub host: String,
+    pub user: String,
+    pub auth: SshAuth,
+}
+
+impl SshParams {
+    /// parameters as entered in the gui, the port defaults to 22
+    pub fn new(host: &str, user: &str, auth: SshAuth) -> Result<Self> {
+        let host = host.trim();
+        let user = user.trim();
+        if host.is_empty() {
+            bail!("Missing ssh host");
+        }
+        if user.is_empty() {
+            bail!("Missing ssh user");
+        }
+
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) => {
+                let port: u16 = port
+                    .parse()
+                    .context(format!("Invalid port {} of ssh host {}", port, host))?;
+                format!("{}:{}", name, port)
+            }
+            None => format!("{}:{}", host, DEFAULT_SSH_PORT),
+        };
+
+        Ok(SshParams {
+            host,
+            user: user.to_owned(),
+            auth,
+        })
+    }
+}
+
+pub struct SshIO {
+    session: Session,
+    channel: Channel,
+    /// data that has been read from the channel but not yet returned
+    buffer: Vec<u8>,
+    /// command executed on the remote host (empty for a shell)
+    command: String,
+    /// eof has been sent on the channel
+    input_closed: bool,
+}
+
+impl SshIO {
+    /// connect to the host and run command, an empty command opens a shell
+    pub fn new(params: &SshParams, command: &str) -> Result<Self> {
+        // create a TCP stream to the host and run the ssh handshake over it
+        let stream = TcpStream::connect(&params.host)
+            .context(format!("Failed to open connection to {}", params.host))?;
+
+        let mut session = Session::new().context("Failed to create ssh session")?;
+        session.set_tcp_stream(stream);
+        session.set_timeout(5000);
+        session.handshake().context("ssh handshake failed")?;
+
+        match &params.auth {
+            SshAuth::Password(password) => session.userauth_password(&params.user, password),
+            SshAuth::KeyFile(path) => {
+                session.userauth_pubkey_file(&params.user, None, Path::new(path), None)
+            }
+        }
+        .context(format!("Failed to authenticate as {}", params.user))?;
+
+        if !session.authenticated() {
+            bail!("ssh authentication failed");
+        }
+
+        let channel = Self::open_channel(&session, command)?;
+
+        Ok(SshIO {
+            session,
+            channel,
+            buffer: Vec::new(),
+            command: command.to_owned(),
+            input_closed: false,
+        })
+    }
+
+    /// open a new channel on the session that runs command (or a shell)
+    fn open_channel(session: &Session, command: &str) -> Result<Channel> {
+        let mut channel = session
+            .channel_session()
+            .context("Failed to open ssh channel")?;
+
+        if command.is_empty() {
+            channel.shell().context("Failed to start remote shell")?;
+        } else {
+            channel
+                .exec(command)
+                .context(format!("Failed to execute {}", command))?;
+        }
+
+        Ok(channel)
+    }
+
+    /// read up to num_bytes of whatever is available from the channel without blocking
+    fn read_nonblocking(&mut self, num_bytes: usize, stderr: bool) -> Result<Vec<u8>> {
+        let mut temp = vec![0; num_bytes.min(4096)];
+
+        self.session.set_blocking(false);
+        let result = if stderr {
+            self.channel.stderr().read(&mut temp)
+        } else {
+            self.channel.read(&mut temp)
+        };
+        self.session.set_blocking(true);
+
+        match result {
+            Ok(read_size) => Ok(temp[..read_size].to_vec()),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(Vec::new()),
+            Err(e) => Err(e).context("Failed to read from process"),
+        }
+    }
+
+    fn check_input_open(&self) -> Result<()> {
+        if self.input_closed {
+            bail!("Input of the ssh channel has been closed");
+        }
+        Ok(())
+    }
+
+    /// close the channel, errors are ignored since the remote end might already be gone
+    fn close_channel(&mut self) {
+        let _ = self.channel.send_eof();
+        let _ = self.channel.close();
+        let _ = self.channel.wait_close();
+    }
+}
+
+/// read up to num_bytes from the channel into buffer if it is empty, fails with Eof if the channel
+/// has been closed
+fn fill_buffer(buffer: &mut Vec<u8>, channel: &mut impl Read, num_bytes: usize) -> Result<()> {
+    if !buffer.is_empty() || num_bytes == 0 {
+        return Ok(());
+    }
+
+    let mut temp = vec![0; num_bytes];
+    let read_size = channel
+        .read(&mut temp)
+        .context("Failed to read from process")?;
+    if read_size == 0 {
+        return Err(Eof { received: vec![] }.into());
+    }
+    buffer.extend_from_slice(&temp[..read_size]);
+    Ok(())
+}
+
+/// hand out buffered data first, otherwise a single read of up to num_bytes from the channel
+fn buffered_recv(
+    buffer: &mut Vec<u8>,
+    channel: &mut impl Read,
+    num_bytes: usize,
+) -> Result<Vec<u8>> {
+    fill_buffer(buffer, channel, num_bytes)?;
+    let read_size = num_bytes.min(buffer.len());
+    Ok(buffer.drain(..read_size).collect())
+}
+
+/// like buffered_recv, but the data stays in the buffer. the channel cannot be peeked, so this is
+/// how a later recv sees the same bytes
+fn buffered_peek(
+    buffer: &mut Vec<u8>,
+    channel: &mut impl Read,
+    num_bytes: usize,
+) -> Result<Vec<u8>> {
+    fill_buffer(buffer, channel, num_bytes)?;
+    let read_size = num_bytes.min(buffer.len());
+    Ok(buffer[..read_size].to_vec())
+}
+
+impl ProgramIO for SshIO {
+    fn send(&mut self, data: &[u8]) -> Result<()> {
+        self.check_input_open()?;
+        self.channel
+            .write_all(data)
+            .context("Failed to send to process")?;
+        self.channel.flush().context("Failed to send to process")?;
+
+        Ok(())
+    }
+
+    fn send_line(&mut self, data: &[u8]) -> Result<()> {
+        self.check_input_open()?;
+        self.channel
+            .write_all(data)
+            .context("Failed to send to process")?;
+        self.channel
+            .write_all(b"\n")
+            .context("Failed to send newline")?;
+        self.channel.flush().context("Failed to send to process")?;
+
+        Ok(())
+    }
+
+    fn recv(&mut self, num_bytes: usize) -> Result<Vec<u8>> {
+        buffered_recv(&mut self.buffer, &mut self.channel, num_bytes)
+    }
+
+    fn peek(&mut self, num_bytes: usize) -> Result<Vec<u8>> {
+        buffered_peek(&mut self.buffer, &mut self.channel, num_bytes)
+    }
+
+    fn recv_until(&mut self, terminator: &[u8]) -> Result<Vec<u8>> {
+        // temporary buffer
+        let mut temp = [0u8; 4096];
+
+        loop {
+            // if the buffered data contains the terminator, return everything up to and including it
+            if let Some(pos) = self
+                .buffer
+                .windows(terminator.len())
+                .position(|x| x == terminator)
+            {
+                return Ok(self.buffer.drain(..pos + terminator.len()).collect());
+            }
+
+            // otherwise block until more data arrives, this fails once the session timeout elapses
+            let read_size = self
+                .channel
+                .read(&mut temp)
+                .context("Failed to read from process")?;
+            if read_size == 0 {
+                return Err(Eof {
+                    received: self.buffer.drain(..).collect(),
+                }
+                .into());
+            }
+            self.buffer.extend_from_slice(&temp[..read_size]);
+        }
+    }
+
+    fn recv_until_timeout(&mut self, terminator: &[u8], timeout: Duration) -> Result<Vec<u8>> {
+        let start = Instant::now();
+
+        loop {
+            if let Some(pos) = self
+                .buffer
+                .windows(terminator.len())
+                .position(|x| x == terminator)
+            {
+                return Ok(self.buffer.drain(..pos + terminator.len()).collect());
+            }
+
+            // poll the channel like clean does
+            let received = self.read_nonblocking(4096, false)?;
+            if !received.is_empty() {
+                self.buffer.extend(received);
+                continue;
+            }
+            if self.channel.eof() {
+                return Err(Eof {
+                    received: self.buffer.drain(..).collect(),
+                }
+                .into());
+            }
+            if start.elapsed() >= timeout {
+                return Err(Timeout {
+                    received: self.buffer.drain(..).collect(),
+                }
+                .into());
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn clean(&mut self, timeout: Duration) -> Result<Vec<u8>> {
+        // start with the buffered data
+        let mut data: Vec<u8> = self.buffer.drain(..).collect();
+        let mut last_data = Instant::now();
+
+        // poll the channel until no new data arrived within the timeout
+        while last_data.elapsed() < timeout && !self.channel.eof() {
+            let received = self.read_nonblocking(4096, false)?;
+            if received.is_empty() {
+                std::thread::sleep(Duration::from_millis(10));
+            } else {
+                data.extend(received);
+                last_data = Instant::now();
+            }
+        }
+
+        Ok(data)
+    }
+
+    fn recv_stderr(&mut self, num_bytes: usize) -> Result<Vec<u8>> {
+        self.read_nonblocking(num_bytes, true)
+    }
+
+    fn attach_debugger(&self, _script: Option<&str>) -> Result<()> {
+        bail!("Not implemented")
+    }
+
+    fn close_stdin(&mut self) -> Result<()> {
+        self.channel
+            .send_eof()
+            .context("Failed to send eof on ssh channel")?;
+        self.input_closed = true;
+        Ok(())
+    }
+
+    fn restart(&mut self) -> Result<()> {
+        // the session stays alive, only the channel running the command is replaced
+        self.close_channel();
+        self.channel = Self::open_channel(&self.session, &self.command)?;
+        self.buffer.clear();
+        self.input_closed = false;
+        Ok(())
+    }
+}
+
+impl Drop for SshIO {
+    fn drop(&mut self) {
+        // close channel and session on drop
+        self.close_channel();
+        let _ = self.session.disconnect(None, "", None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_ssh_params() {
+        let auth = SshAuth::new("hunter2", false).unwrap();
+        assert_eq!(auth, SshAuth::Password("hunter2".to_string()));
+        assert_eq!(
+            SshAuth::new(" ~/.ssh/id_rsa ", true).unwrap(),
+            SshAuth::KeyFile("~/.ssh/id_rsa".to_string())
+        );
+        assert!(SshAuth::new(" ", true).is_err());
+
+        let params = SshParams::new(" ctf.example.com ", "ctf", auth.clone()).unwrap();
+        assert_eq!(params.host, "ctf.example.com:22");
+        assert_eq!(params.user, "ctf");
+        assert_eq!(
+            SshParams::new("127.0.0.1:2222", "root", auth.clone())
+                .unwrap()
+                .host,
+            "127.0.0.1:2222"
+        );
+
+        assert!(SshParams::new("", "ctf", auth.clone()).is_err());
+        assert!(SshParams::new("host", " ", auth.clone()).is_err());
+        assert!(SshParams::new("host:ssh", "ctf", auth).is_err());
+    }
+
+    #[test]
+    fn test_buffered_recv() {
+        let mut channel = Cursor::new(b"hello world".to_vec());
+        let mut buffer = vec![];
+
+        // peeked data stays buffered until recv hands it out
+        assert_eq!(
+            buffered_peek(&mut buffer, &mut channel, 5).unwrap(),
+            b"hello"
+        );
+        assert_eq!(
+            buffered_peek(&mut buffer, &mut channel, 8).unwrap(),
+            b"hello"
+        );
+        assert_eq!(buffered_recv(&mut buffer, &mut channel, 3).unwrap(), b"hel");
+        assert_eq!(buffered_recv(&mut buffer, &mut channel, 8).unwrap(), b"lo");
+        assert_eq!(
+            buffered_recv(&mut buffer, &mut channel, 16).unwrap(),
+            b" world"
+        );
+        assert!(buffered_recv(&mut buffer, &mut channel, 0)
+            .unwrap()
+            .is_empty());
+
+        // a closed channel is an eof without any data
+        let error = buffered_recv(&mut buffer, &mut channel, 4).unwrap_err();
+        assert!(error.downcast_ref::<Eof>().unwrap().received.is_empty());
+        let error = buffered_peek(&mut buffer, &mut channel, 4).unwrap_err();
+        assert!(error.downcast_ref::<Eof>().is_some());
+    }
+}
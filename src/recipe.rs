@@ -8,13 +8,15 @@ use iced_native::Button;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::gui::Message;
+use crate::theme::Theme;
 use iced::container;
 use iced_graphics::Color;
 
-use anyhow::Result;
-use iced::{Align, Column, Container, Length, Row, Space, Text, TextInput};
+use anyhow::{bail, Context, Result};
+use iced::{Align, Checkbox, Column, Container, Length, Row, Space, Text, TextInput};
 use iced_native::text_input;
 use iced_native::{pick_list, PickList};
+use log::*;
 
 static INGREDIENT_VIEW_ID_CTR: AtomicUsize = AtomicUsize::new(0);
 
@@ -27,8 +29,11 @@ struct IngredientViewState {
     select_container: button::State,
     add: button::State,
     remove: button::State,
+    delete: button::State,
     move_up: button::State,
     move_down: button::State,
+    duplicate: button::State,
+    position: text_input::State,
     output_changer: button::State,
 }
 #[derive(Serialize, Deserialize)]
@@ -44,11 +49,17 @@ pub struct IngredientView {
     selected: bool,
     #[serde(skip_serializing, skip_deserializing)]
     show_output_text: bool,
+    /// 1-based position typed into the move to position input
+    #[serde(skip_serializing, skip_deserializing)]
+    pub position: String,
     #[serde(skip_serializing, skip_deserializing)]
     state: IngredientViewState,
     has_input: bool,
     has_output: bool,
     pub category: CommandCategory,
+    /// received data is only returned and not added to the program output
+    #[serde(default)]
+    pub silent: bool,
 }
 
 impl Clone for IngredientView {
@@ -63,36 +74,59 @@ impl Clone for IngredientView {
             output: self.output.clone(),
             selected: self.selected,
             show_output_text: self.show_output_text,
+            position: String::new(),
             has_input: self.has_input,
             has_output: self.has_output,
             category: self.category,
+            silent: self.silent,
         }
     }
 }
 
 pub struct IngredientStyle {
+    theme: Theme,
     selected: bool,
+    failed: bool,
 }
 
 impl IngredientStyle {
-    pub fn new() -> Self {
-        IngredientStyle { selected: false }
+    pub fn new(theme: Theme) -> Self {
+        IngredientStyle {
+            theme,
+            selected: false,
+            failed: false,
+        }
     }
 
-    pub fn selected(selected: bool) -> Self {
-        IngredientStyle { selected }
+    pub fn selected(theme: Theme, selected: bool) -> Self {
+        IngredientStyle {
+            theme,
+            selected,
+            failed: false,
+        }
+    }
+
+    pub fn failed(theme: Theme, failed: bool) -> Self {
+        IngredientStyle {
+            theme,
+            selected: false,
+            failed,
+        }
     }
 }
 
 impl container::StyleSheet for IngredientStyle {
     fn style(&self) -> container::Style {
-        let color = if self.selected {
-            Color::from_rgb8(200, 200, 255)
+        let color = if self.failed {
+            self.theme.failed()
+        } else if self.selected {
+            self.theme.selected()
         } else {
-            Color::WHITE
+            self.theme.surface()
         };
 
         container::Style {
+            text_color: Some(self.theme.text()),
             background: Some(Background::Color(color)),
             ..container::Style::default()
         }
@@ -102,6 +136,7 @@ impl container::StyleSheet for IngredientStyle {
 impl button::StyleSheet for IngredientStyle {
     fn active(&self) -> button::Style {
         button::Style {
+            text_color: self.theme.text(),
             ..Default::default()
         }
     }
@@ -125,16 +160,26 @@ impl IngredientView {
             output: String::default(),
             selected: false,
             show_output_text: false,
+            position: String::new(),
             state: IngredientViewState::default(),
             has_input: T::has_input(),
             has_output: T::has_output(),
             category: T::category(),
+            silent: false,
         }
     }
 
     pub fn run(&self, state: &mut State) -> Result<()> {
-        let cmd = create_command(self.cmd_type, &self.input.as_bytes(), state);
-        let res = cmd.execute(state)?;
+        let cmd = create_command(self.cmd_type, &self.input.as_bytes(), state)?;
+        // ingredients run by a silent custom ingredient are silent as well
+        let silent = state.silent;
+        state.silent = silent || self.silent;
+        let res = cmd.execute(state);
+        state.silent = silent;
+        // the program may have died during the ingredient (e.g. crashed on a payload)
+        let status = state.update_exit_status();
+        let res = res?;
+        status?;
         if !self.output.is_empty() && res.is_some() {
             state
                 .registers
@@ -143,22 +188,30 @@ impl IngredientView {
         Ok(())
     }
 
-    pub fn draw_preview<'a>(&'a mut self) -> Container<'a, Message> {
+    pub fn draw_preview<'a>(&'a mut self, theme: Theme) -> Container<'a, Message> {
         let title = Text::new(&self.title)
             .size(24)
-            .color([0.2, 0.2, 0.2])
+            .color(theme.secondary_text())
             .width(Length::FillPortion(25));
 
         let add_button = Button::new(&mut self.state.add, Text::new("+"))
             .width(Length::Shrink)
             .on_press(Message::AddIngredientPreview(self.id));
 
-        let row = Row::new()
+        let mut row = Row::new()
             .push(Space::with_width(Length::FillPortion(1)))
             .spacing(20)
             .push(title)
             .push(add_button);
 
+        // custom ingredients are files in ingredients/ named by their input
+        if self.category == CommandCategory::Custom {
+            let delete_button = Button::new(&mut self.state.delete, Text::new("x"))
+                .width(Length::Shrink)
+                .on_press(Message::DeleteIngredient(self.input.clone()));
+            row = row.push(delete_button);
+        }
+
         let mut column = Column::new()
             .align_items(Align::Start)
             .width(Length::Fill)
@@ -166,7 +219,7 @@ impl IngredientView {
             .push(row);
 
         let description = Text::new(&self.description)
-            .color([0.2, 0.2, 0.2])
+            .color(theme.secondary_text())
             .width(Length::FillPortion(9));
         if self.selected {
             let desc_row = Row::new()
@@ -175,40 +228,61 @@ impl IngredientView {
             column = column.push(desc_row);
         }
 
-        let click_style: Box<dyn button::StyleSheet> = IngredientStyle::new().into();
+        let click_style: Box<dyn button::StyleSheet> = IngredientStyle::new(theme).into();
         let clickable = Button::new(&mut self.state.select_container, column)
             .on_press(Message::SelectIngredientPreview(self.id))
             .width(Length::Fill)
             .style(click_style);
 
         let boxed_style: Box<dyn container::StyleSheet> =
-            IngredientStyle::selected(self.selected).into();
+            IngredientStyle::selected(theme, self.selected).into();
         Container::new(clickable)
             .style(boxed_style)
             .width(Length::Fill)
     }
 
-    pub fn draw_active<'a>(&'a mut self, registers: Vec<String>) -> Container<'a, Message> {
+    pub fn draw_active<'a>(
+        &'a mut self,
+        registers: Vec<String>,
+        failed: bool,
+        theme: Theme,
+    ) -> Container<'a, Message> {
         let title = Text::new(&self.title).size(24).width(Length::Fill);
         let description = Text::new(&self.description);
 
         let remove_button = Button::new(&mut self.state.remove, Text::new("-"))
             .on_press(Message::RemoveIngredient(self.id));
+        let duplicate_button = Button::new(&mut self.state.duplicate, Text::new("Copy"))
+            .on_press(Message::DuplicateIngredient(self.id));
         let move_up_button = Button::new(&mut self.state.move_up, Text::new("↑"))
             .on_press(Message::MoveIngredientUp(self.id));
         let move_down_button = Button::new(&mut self.state.move_down, Text::new("↓"))
             .on_press(Message::MoveIngredientDown(self.id));
 
+        let id = self.id;
+
+        let mut position_input = TextInput::new(
+            &mut self.state.position,
+            "Pos",
+            &self.position,
+            move |msg| Message::IngredientPositionChange(id, msg),
+        )
+        .width(Length::Units(40));
+        if let Ok(position) = self.position.trim().parse::<usize>() {
+            position_input =
+                position_input.on_submit(Message::MoveIngredientTo(id, position.saturating_sub(1)));
+        }
+
         let title_row = Row::new()
             .spacing(5)
             .push(title)
             .push(move_up_button)
             .push(move_down_button)
+            .push(position_input)
+            .push(duplicate_button)
             .push(remove_button)
             .width(Length::Shrink);
 
-        let id = self.id;
-
         let mut row = Row::new();
 
         if self.has_input {
@@ -242,7 +316,10 @@ impl IngredientView {
             }
             let output_changer = Button::new(&mut self.state.output_changer, Text::new("<>"))
                 .on_press(Message::IngredientOutputChangeType(id));
-            row = row.push(output_changer);
+            let silent_checkbox = Checkbox::new(self.silent, "Silent", move |silent| {
+                Message::IngredientSilentChanged(id, silent)
+            });
+            row = row.push(output_changer).push(silent_checkbox);
         }
 
         let column = Column::new()
@@ -253,18 +330,26 @@ impl IngredientView {
             .push(description)
             .push(row);
 
-        let click_style: Box<dyn button::StyleSheet> = IngredientStyle::new().into();
+        let click_style: Box<dyn button::StyleSheet> = IngredientStyle::new(theme).into();
 
         let clickable = Button::new(&mut self.state.select_container, column)
             .on_press(Message::SelectIngredient(self.id))
             .style(click_style);
 
-        let boxed_style: Box<dyn container::StyleSheet> = IngredientStyle::new().into();
+        let boxed_style: Box<dyn container::StyleSheet> =
+            IngredientStyle::failed(theme, failed).into();
         Container::new(clickable)
             .style(boxed_style)
             .width(Length::Fill)
     }
 
+    /// case-insensitive substring match against title and description
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        self.title.to_lowercase().contains(&filter)
+            || self.description.to_lowercase().contains(&filter)
+    }
+
     pub fn set_output(&mut self, output: String) {
         self.output = output;
     }
@@ -282,6 +367,88 @@ impl IngredientView {
     }
 }
 
+/// version of the saved recipe format. when changing it, migrate older recipes in parse_recipe
+pub const RECIPE_VERSION: u64 = 1;
+
+/// serialize a recipe (or custom ingredient) as {"version": RECIPE_VERSION, "ingredients": [...]}
+pub fn serialize_recipe(recipe: &[IngredientView]) -> Result<String> {
+    serde_json::to_string(&serde_json::json!({
+        "version": RECIPE_VERSION,
+        "ingredients": recipe,
+    }))
+    .context("Failed to serialize recipe")
+}
+
+/// parse a saved recipe, recipes saved before versioning are a bare array of ingredients
+pub fn parse_recipe(data: &str) -> Result<Vec<IngredientView>> {
+    let value: serde_json::Value = serde_json::from_str(data).context("Invalid json")?;
+    let (version, ingredients) = match value {
+        serde_json::Value::Array(_) => (0, value),
+        serde_json::Value::Object(mut object) => {
+            let version = object
+                .get("version")
+                .and_then(|version| version.as_u64())
+                .context("Missing recipe version")?;
+            let ingredients = object
+                .remove("ingredients")
+                .context("Missing ingredients")?;
+            (version, ingredients)
+        }
+        _ => bail!("Expected a recipe object"),
+    };
+    if version > RECIPE_VERSION {
+        bail!(
+            "Recipe version {} is newer than the supported version {}",
+            version,
+            RECIPE_VERSION
+        );
+    }
+
+    // version 0 only lacks the envelope, future migrations of the ingredients go here
+    serde_json::from_value(ingredients).context(format!("Invalid version {} recipe", version))
+}
+
+/// run the ingredients in order until one fails, returns the id of the failed one and the error
+pub fn run_recipe(recipe: &[IngredientView], state: &mut State) -> Result<(), (usize, String)> {
+    for ingredient in recipe {
+        if let Err(e) = ingredient.run(state) {
+            debug!("Error occured: '{:?}'.", e);
+            return Err((
+                ingredient.id,
+                format!("{} failed: {:#}", ingredient.title, e),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// rerun the recipe on a restarted program until every ingredient passes (e.g. an Assert checking
+/// that the exploit worked) or max_attempts runs failed. registers are kept between the runs.
+/// returns the number of runs and the result of the last one
+pub fn run_until_success(
+    recipe: &[IngredientView],
+    state: &mut State,
+    max_attempts: usize,
+) -> (usize, Result<(), (usize, String)>) {
+    let mut attempt = 1;
+    loop {
+        state.clear_output();
+        let result = run_recipe(recipe, state);
+        if result.is_ok() || attempt >= max_attempts {
+            return (attempt, result);
+        }
+        info!("Attempt {} of {} failed, restarting", attempt, max_attempts);
+
+        if let Err(e) = state.program.restart() {
+            let result = result.map_err(|(id, error)| {
+                (id, format!("{}\nUnable to restart program: {:#}", error, e))
+            });
+            return (attempt, result);
+        }
+        attempt += 1;
+    }
+}
+
 pub struct CategoryViewState {}
 pub struct CategoryView {
     pub ingredients: Vec<IngredientView>,
@@ -306,14 +473,138 @@ impl CategoryView {
         self.ingredients.push(ingredient);
     }
 
-    pub fn draw<'a>(&'a mut self) -> Container<'a, Message> {
+    pub fn has_match(&self, filter: &str) -> bool {
+        self.ingredients.iter().any(|i| i.matches(filter))
+    }
+
+    pub fn draw<'a>(&'a mut self, theme: Theme, filter: &str) -> Container<'a, Message> {
         let title = Text::new(&self.title).size(30);
 
         let mut column = Column::new().push(title).padding(10);
 
         for ingredient in &mut self.ingredients {
-            column = column.push(ingredient.draw_preview());
+            if ingredient.matches(filter) {
+                column = column.push(ingredient.draw_preview(theme));
+            }
         }
         Container::new(column)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{AssertCmd, EvaluateCmd, LogCmd, RecvLineCmd, RegexCmd, SendLineCmd};
+    use crate::utils::Target;
+
+    /// ingredient that evaluates an expression into a register
+    fn evaluate(expression: &str, output: &str) -> IngredientView {
+        let mut ingredient = IngredientView::new::<EvaluateCmd>();
+        ingredient.input = expression.to_string();
+        ingredient.output = output.to_string();
+        ingredient
+    }
+
+    #[test]
+    fn test_parse_recipe() {
+        let mut send = IngredientView::new::<SendLineCmd>();
+        send.input = "hello".to_string();
+        let recipe = vec![send, evaluate("1 + 1", "two")];
+
+        let serialized = serialize_recipe(&recipe).unwrap();
+        assert!(serialized.starts_with(r#"{"ingredients":["#));
+        assert!(serialized.ends_with(r#""version":1}"#));
+        let loaded = parse_recipe(&serialized).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].input, "hello");
+        assert_eq!(loaded[1].output, "two");
+
+        // recipes saved before versioning
+        let legacy = serde_json::to_string(&recipe).unwrap();
+        let loaded = parse_recipe(&legacy).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1].input, "1 + 1");
+
+        assert!(parse_recipe("[]").unwrap().is_empty());
+        assert!(parse_recipe(r#"{"version": 2, "ingredients": []}"#).is_err());
+        assert!(parse_recipe(r#"{"ingredients": []}"#).is_err());
+        assert!(parse_recipe(r#"{"version": 1}"#).is_err());
+        assert!(parse_recipe(r#"{"version": 1, "ingredients": [{"title": "x"}]}"#).is_err());
+        assert!(parse_recipe("42").is_err());
+        assert!(parse_recipe("not json").is_err());
+    }
+
+    #[test]
+    fn test_run_until_success() {
+        let mut state = State::new(Target::Local, "cat", &[]).unwrap();
+        state.registers.set("n", b"0".to_vec());
+
+        // only the third run passes the assert
+        let mut check = IngredientView::new::<AssertCmd>();
+        check.input = "$n == 3".to_string();
+        let recipe = vec![evaluate("$n + 1", "n"), check];
+
+        let (attempts, result) = run_until_success(&recipe, &mut state, 10);
+        assert_eq!(attempts, 3);
+        assert!(result.is_ok());
+
+        state.registers.set("n", b"10".to_vec());
+        let (attempts, result) = run_until_success(&recipe, &mut state, 2);
+        assert_eq!(attempts, 2);
+        let (id, error) = result.unwrap_err();
+        assert_eq!(id, recipe[1].id);
+        assert!(error.starts_with("Assert failed"));
+        assert_eq!(state.registers.get("n").unwrap(), b"12");
+    }
+
+    #[test]
+    fn test_silent() {
+        let mut state = State::new(Target::Local, "cat", &[]).unwrap();
+        SendLineCmd::from_parameter(b"hidden", &state)
+            .unwrap()
+            .execute(&mut state)
+            .unwrap();
+
+        let mut recv = IngredientView::new::<RecvLineCmd>();
+        recv.output = "line".to_string();
+        recv.silent = true;
+        recv.run(&mut state).unwrap();
+        assert_eq!(state.registers.get("line").unwrap(), b"hidden\n");
+        assert!(state.output.is_empty());
+        assert!(!state.silent);
+
+        // the flag is kept in saved recipes, older recipes without it are not silent
+        let serialized = serde_json::to_string(&recv).unwrap();
+        let loaded: IngredientView = serde_json::from_str(&serialized).unwrap();
+        assert!(loaded.silent);
+        let legacy = serialized.replace(",\"silent\":true", "");
+        assert!(
+            !serde_json::from_str::<IngredientView>(&legacy)
+                .unwrap()
+                .silent
+        );
+
+        SendLineCmd::from_parameter(b"shown", &state)
+            .unwrap()
+            .execute(&mut state)
+            .unwrap();
+        recv.silent = false;
+        recv.run(&mut state).unwrap();
+        assert_eq!(state.output, b"shown\n");
+    }
+
+    #[test]
+    fn test_ingredient_filter() {
+        let log = IngredientView::new::<LogCmd>();
+        assert!(log.matches(""));
+        assert!(log.matches("LOG"));
+        assert!(log.matches("a mess"));
+        assert!(!log.matches("regex"));
+
+        let mut category = CategoryView::new(CommandCategory::Misc);
+        category.push(log);
+        category.push(IngredientView::new::<RegexCmd>());
+        assert!(category.has_match("Regex"));
+        assert!(!category.has_match("no such ingredient"));
+    }
+}
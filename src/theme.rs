@@ -0,0 +1,106 @@
+use iced::{container, Background, Color};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::Light
+    }
+}
+
+impl Theme {
+    pub fn toggled(self) -> Self {
+        match self {
+            Theme::Light => Theme::Dark,
+            Theme::Dark => Theme::Light,
+        }
+    }
+
+    /// label of the button that switches to the other theme
+    pub fn toggle_label(self) -> &'static str {
+        match self {
+            Theme::Light => "Dark theme",
+            Theme::Dark => "Light theme",
+        }
+    }
+
+    pub fn background(self) -> Color {
+        match self {
+            Theme::Light => Color::WHITE,
+            Theme::Dark => Color::from_rgb8(36, 36, 40),
+        }
+    }
+
+    /// background of ingredient boxes
+    pub fn surface(self) -> Color {
+        match self {
+            Theme::Light => Color::WHITE,
+            Theme::Dark => Color::from_rgb8(52, 52, 58),
+        }
+    }
+
+    pub fn selected(self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb8(200, 200, 255),
+            Theme::Dark => Color::from_rgb8(62, 62, 120),
+        }
+    }
+
+    pub fn failed(self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb8(255, 200, 200),
+            Theme::Dark => Color::from_rgb8(120, 44, 44),
+        }
+    }
+
+    pub fn text(self) -> Color {
+        match self {
+            Theme::Light => Color::BLACK,
+            Theme::Dark => Color::from_rgb8(222, 222, 222),
+        }
+    }
+
+    /// data sent to the program in the program output
+    pub fn sent_text(self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb8(0, 90, 200),
+            Theme::Dark => Color::from_rgb8(120, 170, 255),
+        }
+    }
+
+    /// dimmed text such as descriptions
+    pub fn secondary_text(self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb(0.2, 0.2, 0.2),
+            Theme::Dark => Color::from_rgb(0.7, 0.7, 0.7),
+        }
+    }
+}
+
+// top level containers set the background and the default text color of their children
+impl container::StyleSheet for Theme {
+    fn style(&self) -> container::Style {
+        container::Style {
+            text_color: Some(self.text()),
+            background: Some(Background::Color(self.background())),
+            ..container::Style::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle() {
+        assert_eq!(Theme::default(), Theme::Light);
+        assert_eq!(Theme::Light.toggled(), Theme::Dark);
+        assert_eq!(Theme::Dark.toggled().toggled(), Theme::Dark);
+    }
+}
@@ -1,61 +1,370 @@
+use crate::binary_handling::{self, Binary};
 use crate::{command, program_io::*};
 
 use anyhow::anyhow;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use log::*;
 
+use crate::misc::fiddling::enhex;
+use crate::misc::packing::Endian;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 pub struct State {
     pub program: Box<dyn ProgramIO>,
     pub program_path: String,
     pub do_exit: bool,
     pub registers: Registers,
-    pub output: String,
+    /// raw bytes received from the program during the current run, stderr is only in the transcript
+    pub output: Vec<u8>,
+    /// sent and received data of the current run in order
+    pub transcript: Transcript,
+    /// set by the Interactive ingredient, the gui then lets the user talk to the program directly
+    pub interactive: bool,
+    /// exit status once the program terminated, updated after every ingredient
+    pub exit_status: Option<ExitStatus>,
+    /// runtime load address of the binary (e.g. from a leak), symbols of a PIE are offsets to it
+    pub binary_base: Option<u64>,
+    /// whether the Shell ingredient may run commands (allow_shell of the config)
+    pub allow_shell: bool,
+    /// received data is not added to the output, set while a silent ingredient runs
+    pub silent: bool,
+    /// number of custom ingredients currently running inside each other (e.g. through Repeat)
+    pub custom_depth: usize,
+    /// parsed binary together with the path it was parsed from
+    binary_cache: Option<(String, Box<dyn Binary>)>,
+    /// byte order of the binary together with the path it was read from, in a RefCell because
+    /// expressions only get a shared State
+    endian_cache: RefCell<Option<(String, Endian)>>,
 }
 
 pub enum Target {
     Local,
+    LocalPty,
     Network,
+    #[cfg(feature = "ssh2")]
+    Ssh(SshParams),
 }
 
 impl State {
     pub fn new(target_type: Target, target: &str, args: &[&str]) -> Result<Self> {
         match target_type {
-            Target::Local => {
+            Target::Local => Self::new_local(target, args, false, LocalOptions::default()),
+            Target::LocalPty => Self::new_local(target, args, true, LocalOptions::default()),
+            Target::Network => {
                 let state = State {
                     program: Box::new(
-                        LocalIO::new(target, args).context("Failed to spawn program")?,
+                        NetworkIO::connect_with_retries(
+                            target,
+                            DEFAULT_CONNECT_ATTEMPTS,
+                            DEFAULT_CONNECT_DELAY,
+                        )
+                        .context("Failed to spawn program")?,
                     ),
-                    program_path: target.to_string(),
+                    program_path: "No binary path in network mode".to_string(),
                     registers: Registers::new(),
                     do_exit: false,
-                    output: String::new(),
+                    output: Vec::new(),
+                    transcript: Transcript::default(),
+                    interactive: false,
+                    exit_status: None,
+                    binary_base: None,
+                    allow_shell: false,
+                    silent: false,
+                    custom_depth: 0,
+                    binary_cache: None,
+                    endian_cache: RefCell::new(None),
                 };
                 Ok(state)
             }
-            Target::Network => {
+            #[cfg(feature = "ssh2")]
+            Target::Ssh(params) => {
                 let state = State {
-                    program: Box::new(NetworkIO::new(target).context("Failed to spawn program")?),
-                    program_path: "No binary path in network mode".to_string(),
+                    program: Box::new(
+                        SshIO::new(&params, target).context("Failed to spawn program")?,
+                    ),
+                    program_path: "No binary path in ssh mode".to_string(),
                     registers: Registers::new(),
                     do_exit: false,
-                    output: String::new(),
+                    output: Vec::new(),
+                    transcript: Transcript::default(),
+                    interactive: false,
+                    exit_status: None,
+                    binary_base: None,
+                    allow_shell: false,
+                    silent: false,
+                    custom_depth: 0,
+                    binary_cache: None,
+                    endian_cache: RefCell::new(None),
                 };
                 Ok(state)
             }
         }
     }
+
+    /// spawn a local program (attached to a pty if requested) with environment options
+    pub fn new_local(
+        target: &str,
+        args: &[&str],
+        pty: bool,
+        options: LocalOptions,
+    ) -> Result<Self> {
+        Ok(State {
+            program: Box::new(
+                LocalIO::with_options(target, args, pty, options)
+                    .context("Failed to spawn program")?,
+            ),
+            program_path: target.to_string(),
+            registers: Registers::new(),
+            do_exit: false,
+            output: Vec::new(),
+            transcript: Transcript::default(),
+            interactive: false,
+            exit_status: None,
+            binary_base: None,
+            allow_shell: false,
+            silent: false,
+            custom_depth: 0,
+            binary_cache: None,
+            endian_cache: RefCell::new(None),
+        })
+    }
+
+    /// keep data received from the program in the output and the transcript
+    pub fn record_received(&mut self, data: &[u8]) {
+        if self.silent {
+            return;
+        }
+        self.output.extend_from_slice(data);
+        self.transcript.push(Direction::Received, data);
+    }
+
+    /// keep data the program wrote to stderr in the transcript, it is not part of the output
+    pub fn record_stderr(&mut self, data: &[u8]) {
+        self.transcript.push(Direction::Stderr, data);
+    }
+
+    /// keep data sent to the program in the transcript
+    pub fn record_sent(&mut self, data: &[u8]) {
+        self.transcript.push(Direction::Sent, data);
+    }
+
+    /// forget the output and transcript of the previous run
+    pub fn clear_output(&mut self) {
+        self.output.clear();
+        self.transcript = Transcript::default();
+    }
+
+    /// poll whether the program terminated, the exit code or the terminating signal (e.g. 11 for a
+    /// segfault) is put into the register exit_code or exit_signal
+    pub fn update_exit_status(&mut self) -> Result<()> {
+        let status = self.program.exit_status()?;
+        if status == self.exit_status {
+            return Ok(());
+        }
+
+        // a restarted program has not exited yet
+        self.registers.remove("exit_code");
+        self.registers.remove("exit_signal");
+        if let Some(status) = status {
+            if let Some(code) = status.code() {
+                self.registers
+                    .set("exit_code", code.to_string().into_bytes());
+            }
+            if let Some(signal) = status.signal() {
+                self.registers
+                    .set("exit_signal", signal.to_string().into_bytes());
+            }
+        }
+        self.exit_status = status;
+        Ok(())
+    }
+
+    /// wait until the program terminated and return its exit status
+    pub fn wait_for_exit(&mut self, timeout: Duration) -> Result<ExitStatus> {
+        let start = Instant::now();
+        loop {
+            self.update_exit_status()?;
+            if let Some(status) = self.exit_status {
+                return Ok(status);
+            }
+            if start.elapsed() > timeout {
+                bail!(
+                    "Program did not exit within {}ms (the exit status is only known for local programs)",
+                    timeout.as_millis()
+                );
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// byte order of the binary at program_path, little endian if it is no elf (e.g. in network mode).
+    /// the binary is only read on first use or after program_path changed
+    pub fn default_endian(&self) -> Endian {
+        let mut cache = self.endian_cache.borrow_mut();
+        match &*cache {
+            Some((path, endian)) if *path == self.program_path => *endian,
+            _ => {
+                let endian =
+                    binary_handling::file_endian(&self.program_path).unwrap_or(Endian::Little);
+                *cache = Some((self.program_path.clone(), endian));
+                endian
+            }
+        }
+    }
+
+    /// get the parsed binary of program_path, parsing it on first use or after program_path changed
+    pub fn binary(&mut self) -> Result<&dyn Binary> {
+        let stale = match &self.binary_cache {
+            Some((path, _)) => *path != self.program_path,
+            None => true,
+        };
+        if stale {
+            let binary = binary_handling::from_path(&self.program_path)?;
+            self.binary_cache = Some((self.program_path.clone(), binary));
+        }
+
+        match &self.binary_cache {
+            Some((_, binary)) => Ok(binary.as_ref()),
+            None => Err(anyhow!("Binary cache is empty")),
+        }
+    }
+}
+
+/// whether data was sent to or received from the program (on stdout or stderr)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+    Stderr,
+}
+
+impl Direction {
+    /// marks the lines of the plain text transcript
+    fn prefix(self) -> &'static str {
+        match self {
+            Direction::Sent => ">> ",
+            Direction::Received => "<< ",
+            Direction::Stderr => "[stderr] ",
+        }
+    }
+}
+
+/// data exchanged with the program in order, consecutive data of the same direction is merged
+#[derive(Debug, Default)]
+pub struct Transcript {
+    pub chunks: Vec<(Direction, Vec<u8>)>,
+}
+
+impl Transcript {
+    pub fn push(&mut self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        trace!(
+            "{}",
+            Transcript::plain_text(direction, &String::from_utf8_lossy(data))
+        );
+        match self.chunks.last_mut() {
+            Some((last, chunk)) if *last == direction => chunk.extend_from_slice(data),
+            _ => self.chunks.push((direction, data.to_vec())),
+        }
+    }
+
+    /// every line prefixed with >> (sent), << (received) or [stderr], e.g. for the log file
+    pub fn to_plain_text(&self) -> String {
+        self.chunks
+            .iter()
+            .map(|(direction, data)| {
+                Transcript::plain_text(*direction, &String::from_utf8_lossy(data))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn plain_text(direction: Direction, text: &str) -> String {
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        text.split('\n')
+            .map(|line| format!("{}{}", direction.prefix(), line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// how the content of a register is shown when it is logged
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayHint {
+    /// list of byte values
+    Raw,
+    /// little endian integer in hex, longer values as hex string
+    Hex,
+    /// little endian unsigned integer
+    Decimal,
+    /// printable characters with escapes for everything else
+    Ascii,
+}
+
+impl Default for DisplayHint {
+    fn default() -> Self {
+        DisplayHint::Raw
+    }
+}
+
+impl FromStr for DisplayHint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "raw" => DisplayHint::Raw,
+            "hex" => DisplayHint::Hex,
+            "dec" | "decimal" => DisplayHint::Decimal,
+            "ascii" => DisplayHint::Ascii,
+            _ => bail!(
+                "Unknown display hint {}, expected raw, hex, dec or ascii",
+                s
+            ),
+        })
+    }
+}
+
+impl DisplayHint {
+    pub fn format(self, bytes: &[u8]) -> String {
+        // integers are only shown for values that fit into an u64
+        let as_int = || {
+            let mut buf = [0; 8];
+            buf[..bytes.len()].copy_from_slice(bytes);
+            u64::from_le_bytes(buf)
+        };
+
+        match self {
+            DisplayHint::Hex if bytes.len() <= 8 => format!("{:#x}", as_int()),
+            DisplayHint::Hex => enhex(bytes),
+            DisplayHint::Decimal if !bytes.is_empty() && bytes.len() <= 8 => as_int().to_string(),
+            DisplayHint::Ascii => bytes
+                .iter()
+                .flat_map(|c| std::ascii::escape_default(*c))
+                .map(char::from)
+                .collect(),
+            _ => format!("{:?}", bytes),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Registers {
     pub map: HashMap<String, Vec<u8>>,
+    hints: HashMap<String, DisplayHint>,
 }
 
 impl Registers {
     pub fn new() -> Registers {
         Registers {
             map: HashMap::new(),
+            hints: HashMap::new(),
         }
     }
 
@@ -71,6 +380,45 @@ impl Registers {
         self.map.contains_key(name)
     }
 
+    pub fn remove(&mut self, name: &str) -> Option<Vec<u8>> {
+        self.hints.remove(name);
+        self.map.remove(name)
+    }
+
+    /// moves the content of a register to a new name, existing registers are not overwritten
+    pub fn rename(&mut self, old: &str, new: &str) -> Result<()> {
+        if old == new {
+            return Ok(());
+        }
+        if self.exists(new) {
+            bail!("Register {} already exists", new);
+        }
+        let hint = self.hint(old);
+        let val = self
+            .remove(old)
+            .context(format!("Register {} does not exist", old))?;
+        self.set(new, val);
+        self.set_hint(new, hint)?;
+        Ok(())
+    }
+
+    pub fn set_hint(&mut self, name: &str, hint: DisplayHint) -> Result<()> {
+        if !self.exists(name) {
+            bail!("Register {} does not exist", name);
+        }
+        self.hints.insert(name.to_owned(), hint);
+        Ok(())
+    }
+
+    pub fn hint(&self, name: &str) -> DisplayHint {
+        self.hints.get(name).copied().unwrap_or_default()
+    }
+
+    /// register content formatted according to its display hint
+    pub fn format(&self, name: &str) -> Option<String> {
+        Some(self.hint(name).format(self.get(name)?))
+    }
+
     pub fn available_registers(&self) -> Vec<String> {
         self.map.keys().cloned().collect()
     }
@@ -79,3 +427,189 @@ impl Registers {
 pub fn print_registers(regs: &Registers) {
     println!("{:?}", regs);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_hint_format() {
+        let leak = b"\x10\x32\x54\x76\xfd\x7f\x00\x00";
+        assert_eq!(DisplayHint::Raw.format(b"AB"), "[65, 66]");
+        assert_eq!(DisplayHint::Hex.format(leak), "0x7ffd76543210");
+        assert_eq!(DisplayHint::Hex.format(b""), "0x0");
+        assert_eq!(DisplayHint::Hex.format(b"ABCDEFGHI"), "414243444546474849");
+        assert_eq!(DisplayHint::Decimal.format(b"\x39\x05"), "1337");
+        assert_eq!(DisplayHint::Decimal.format(leak), "140726588682768");
+        assert_eq!(
+            DisplayHint::Decimal.format(b"ABCDEFGHI"),
+            "[65, 66, 67, 68, 69, 70, 71, 72, 73]"
+        );
+        assert_eq!(
+            DisplayHint::Ascii.format(b"flag{x}\n\x00\xff"),
+            "flag{x}\\n\\x00\\xff"
+        );
+    }
+
+    #[test]
+    fn test_display_hint_parse() {
+        assert_eq!("hex".parse::<DisplayHint>().unwrap(), DisplayHint::Hex);
+        assert_eq!("dec".parse::<DisplayHint>().unwrap(), DisplayHint::Decimal);
+        assert!("binary".parse::<DisplayHint>().is_err());
+    }
+
+    #[test]
+    fn test_register_hints() {
+        let mut registers = Registers::new();
+        registers.set("leak", b"\x39\x05".to_vec());
+        assert_eq!(registers.format("leak").unwrap(), "[57, 5]");
+        assert!(registers.set_hint("missing", DisplayHint::Hex).is_err());
+
+        registers.set_hint("leak", DisplayHint::Decimal).unwrap();
+        assert_eq!(registers.format("leak").unwrap(), "1337");
+        // get is not affected by the hint
+        assert_eq!(registers.get("leak").unwrap(), b"\x39\x05");
+
+        // the hint follows renames and is dropped on removal
+        registers.rename("leak", "value").unwrap();
+        assert_eq!(registers.hint("value"), DisplayHint::Decimal);
+        registers.remove("value");
+        registers.set("value", vec![1]);
+        assert_eq!(registers.hint("value"), DisplayHint::Raw);
+        assert_eq!(registers.format("missing"), None);
+    }
+
+    #[test]
+    fn test_transcript() {
+        let mut state = State::new(Target::Local, "cat", &[]).unwrap();
+        state.record_received(b"name: ");
+        state.record_sent(b"");
+        state.record_sent(b"AAAA");
+        state.record_sent(b"\n");
+        state.record_received(b"hello AAAA\nbye\n");
+        state.record_stderr(b"warn");
+        state.silent = true;
+        state.record_stderr(b"ing\n");
+
+        // stderr is kept apart from the output and not hidden by silent ingredients
+        assert_eq!(state.output, b"name: hello AAAA\nbye\n");
+        assert_eq!(
+            state.transcript.chunks,
+            vec![
+                (Direction::Received, b"name: ".to_vec()),
+                (Direction::Sent, b"AAAA\n".to_vec()),
+                (Direction::Received, b"hello AAAA\nbye\n".to_vec()),
+                (Direction::Stderr, b"warning\n".to_vec()),
+            ]
+        );
+        assert_eq!(
+            state.transcript.to_plain_text(),
+            "<< name: \n>> AAAA\n<< hello AAAA\n<< bye\n[stderr] warning"
+        );
+
+        state.clear_output();
+        assert!(state.output.is_empty() && state.transcript.chunks.is_empty());
+    }
+
+    #[test]
+    fn test_register_remove() {
+        let mut registers = Registers::new();
+        registers.set("leak", b"\x41".to_vec());
+
+        assert_eq!(registers.remove("leak"), Some(b"\x41".to_vec()));
+        assert!(!registers.exists("leak"));
+        assert_eq!(registers.remove("leak"), None);
+    }
+
+    #[test]
+    fn test_register_rename() {
+        let mut registers = Registers::new();
+        registers.set("a", b"first".to_vec());
+        registers.set("b", b"second".to_vec());
+
+        registers.rename("a", "c").unwrap();
+        assert!(!registers.exists("a"));
+        assert_eq!(registers.get("c").unwrap(), b"first");
+
+        // existing names are not overwritten and missing registers can not be renamed
+        assert!(registers.rename("c", "b").is_err());
+        assert_eq!(registers.get("b").unwrap(), b"second");
+        assert_eq!(registers.get("c").unwrap(), b"first");
+        assert!(registers.rename("missing", "d").is_err());
+        assert!(!registers.exists("d"));
+
+        registers.rename("b", "b").unwrap();
+        assert_eq!(registers.get("b").unwrap(), b"second");
+    }
+
+    #[test]
+    fn test_update_exit_status() {
+        let mut state = State::new(Target::Local, "sh", &[]).unwrap();
+        state.update_exit_status().unwrap();
+        assert_eq!(state.exit_status, None);
+
+        state.program.send_line(b"kill -SEGV $$").unwrap();
+        while state.exit_status.is_none() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            state.update_exit_status().unwrap();
+        }
+        assert_eq!(state.registers.get("exit_signal").unwrap(), b"11");
+        assert!(!state.registers.exists("exit_code"));
+
+        assert_eq!(
+            state.wait_for_exit(Duration::default()).unwrap().signal(),
+            Some(11)
+        );
+
+        // the registers are cleared once the program runs again
+        state.program.restart().unwrap();
+        state.update_exit_status().unwrap();
+        assert_eq!(state.exit_status, None);
+        assert!(!state.registers.exists("exit_signal"));
+        assert!(state.wait_for_exit(Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn test_binary_cache() {
+        // address of the parsed binary, the same one is handed out while it is cached
+        fn binary_ptr(state: &mut State) -> *const u8 {
+            state.binary().unwrap() as *const dyn Binary as *const u8
+        }
+
+        let mut state = State::new(Target::Local, "cat", &[]).unwrap();
+        state.program_path = "test_data/bin64".to_string();
+
+        let first = binary_ptr(&mut state);
+        assert_eq!(binary_ptr(&mut state), first);
+        assert_eq!(
+            state.binary().unwrap().get_sym_addr("main").unwrap(),
+            state.binary().unwrap().get_sym_addr("main").unwrap()
+        );
+
+        // changing the path invalidates the cache
+        state.program_path = "test_data/bin64_static".to_string();
+        assert_eq!(
+            state.binary().unwrap().get_sym_addr("main").unwrap(),
+            0x401655
+        );
+    }
+
+    #[test]
+    fn test_endian_cache() {
+        let mut state = State::new(Target::Local, "cat", &[]).unwrap();
+        let path = std::env::temp_dir().join(format!("bochumoxide_endian_{}", std::process::id()));
+        state.program_path = path.to_str().unwrap().to_string();
+
+        // elf ident of a big endian 32-bit binary
+        std::fs::write(&path, b"\x7fELF\x01\x02").unwrap();
+        assert_eq!(state.default_endian(), Endian::Big);
+
+        // the file is not read again while the path stays the same
+        std::fs::write(&path, b"\x7fELF\x01\x01").unwrap();
+        assert_eq!(state.default_endian(), Endian::Big);
+        std::fs::remove_file(&path).unwrap();
+
+        state.program_path = "test_data/bin64".to_string();
+        assert_eq!(state.default_endian(), Endian::Little);
+    }
+}
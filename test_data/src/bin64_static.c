@@ -0,0 +1,8 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+int main() {
+    puts("Hello World!");
+    system("/bin/sh");
+    return 0;
+}
@@ -0,0 +1,35 @@
+@ hello world for a thumb-only cortex-m3 linux target, its plt stubs are thumb-2 code
+	.syntax unified
+	.cpu cortex-m3
+	.eabi_attribute Tag_CPU_arch, 10
+	.eabi_attribute Tag_CPU_arch_profile, 77
+	.thumb
+	.eabi_attribute Tag_ARM_ISA_use, 0
+	.eabi_attribute Tag_THUMB_ISA_use, 2
+
+	.text
+	.globl _start
+	.type _start, %function
+	.thumb_func
+_start:
+	ldr r0, =main
+	movs r1, #0
+	movs r2, #0
+	movs r3, #0
+	bl __libc_start_main
+	bl abort
+
+	.globl main
+	.type main, %function
+	.thumb_func
+main:
+	push {r7, lr}
+	ldr r0, =hello
+	bl puts
+	movs r0, #0
+	pop {r7, pc}
+	.ltorg
+
+	.section .rodata
+hello:
+	.asciz "Hello World!"
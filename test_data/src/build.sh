@@ -0,0 +1,17 @@
+#!/bin/sh
+# rebuilds the test binaries that have their source in this directory, run it from test_data/src.
+# the addresses asserted by the tests depend on the exact toolchain versions noted below
+set -e
+
+# gcc 12.2.0 and glibc 2.36 (Debian 12)
+gcc -static -no-pie -o ../bin64_static bin64_static.c
+
+# llvm-mc 14.0.6 and ld.lld 22 (e.g. rust-lld linked as ld.lld), no arm gcc needed. the target is a
+# thumb-only core because for cores that also run arm code the linkers emit arm plt stubs
+LD_LLD=${LD_LLD:-ld.lld}
+tmp=$(mktemp -d)
+llvm-mc -triple=thumbv7m-linux-gnueabi -filetype=obj -o "$tmp/libc_stub.o" libc_stub.s
+"$LD_LLD" -shared -soname libc.so.6 -o "$tmp/libc.so.6" "$tmp/libc_stub.o"
+llvm-mc -triple=thumbv7m-linux-gnueabi -filetype=obj -o "$tmp/bin_thumb32.o" bin_thumb32.s
+"$LD_LLD" --dynamic-linker /lib/ld-linux.so.3 -o ../bin_thumb32 "$tmp/bin_thumb32.o" "$tmp/libc.so.6"
+rm -r "$tmp"
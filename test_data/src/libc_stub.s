@@ -0,0 +1,20 @@
+@ stand-in for the libc bin_thumb32 is linked against, only the imported symbols matter
+	.syntax unified
+	.cpu cortex-m3
+	.eabi_attribute Tag_CPU_arch, 10
+	.eabi_attribute Tag_CPU_arch_profile, 77
+	.thumb
+	.eabi_attribute Tag_ARM_ISA_use, 0
+	.eabi_attribute Tag_THUMB_ISA_use, 2
+	.text
+	.globl puts, abort, __libc_start_main
+	.type puts, %function
+	.type abort, %function
+	.type __libc_start_main, %function
+	.thumb_func
+puts:
+	.thumb_func
+abort:
+	.thumb_func
+__libc_start_main:
+	bx lr